@@ -0,0 +1,223 @@
+//! Comparison of two extraction runs via their metadata.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::config::METADATA_FILENAME;
+use crate::error::Result;
+use crate::output::{FrameMetadata, OutputMetadata, OutputWriter};
+
+/// Timestamp differences below this are treated as identical.
+const SHIFT_EPSILON_SECONDS: f64 = 1e-3;
+
+/// A frame present in both runs.
+#[derive(Debug, Clone)]
+pub struct MatchedFrame {
+    pub before: FrameMetadata,
+    pub after: FrameMetadata,
+}
+
+impl MatchedFrame {
+    /// Timestamp change from the first run to the second, in seconds.
+    pub fn shift_seconds(&self) -> f64 {
+        self.after.timestamp_seconds - self.before.timestamp_seconds
+    }
+
+    /// Whether the timestamp moved between runs.
+    pub fn is_shifted(&self) -> bool {
+        self.shift_seconds().abs() > SHIFT_EPSILON_SECONDS
+    }
+}
+
+/// Differences between two extraction runs.
+#[derive(Debug, Clone)]
+pub struct CompareReport {
+    pub matched: Vec<MatchedFrame>,
+    /// Frames only in the first run.
+    pub removed: Vec<FrameMetadata>,
+    /// Frames only in the second run.
+    pub added: Vec<FrameMetadata>,
+}
+
+impl CompareReport {
+    /// Whether both runs selected the same frames at the same timestamps.
+    pub fn is_match(&self) -> bool {
+        self.removed.is_empty()
+            && self.added.is_empty()
+            && !self.matched.iter().any(|m| m.is_shifted())
+    }
+}
+
+impl fmt::Display for CompareReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for m in self.matched.iter().filter(|m| m.is_shifted()) {
+            writeln!(
+                f,
+                "~ {} @ {} -> {} @ {} ({:+.3}s)",
+                m.before.filename,
+                m.before.timestamp_formatted,
+                m.after.filename,
+                m.after.timestamp_formatted,
+                m.shift_seconds()
+            )?;
+        }
+        for frame in &self.removed {
+            writeln!(f, "- {} @ {}", frame.filename, frame.timestamp_formatted)?;
+        }
+        for frame in &self.added {
+            writeln!(f, "+ {} @ {}", frame.filename, frame.timestamp_formatted)?;
+        }
+
+        let shifted = self.matched.iter().filter(|m| m.is_shifted()).count();
+        write!(
+            f,
+            "{} matched ({} shifted), {} removed, {} added",
+            self.matched.len(),
+            shifted,
+            self.removed.len(),
+            self.added.len()
+        )
+    }
+}
+
+/// Load run metadata from an output directory or a metadata.json path.
+pub fn load_metadata(path: &Path) -> Result<OutputMetadata> {
+    if path.is_dir() {
+        OutputWriter::read_metadata(&path.join(METADATA_FILENAME))
+    } else {
+        OutputWriter::read_metadata(path)
+    }
+}
+
+/// Match frames of two runs by nearest timestamp.
+///
+/// Pairs are assigned closest-first; frames further apart than
+/// `tolerance_seconds` are reported as added or removed.
+pub fn compare_metadata(
+    before: &OutputMetadata,
+    after: &OutputMetadata,
+    tolerance_seconds: f64,
+) -> CompareReport {
+    let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+    for (i, a) in before.frames.iter().enumerate() {
+        for (j, b) in after.frames.iter().enumerate() {
+            let distance = (a.timestamp_seconds - b.timestamp_seconds).abs();
+            if distance <= tolerance_seconds {
+                candidates.push((distance, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| x.0.total_cmp(&y.0).then(x.1.cmp(&y.1)));
+
+    let mut before_used = vec![false; before.frames.len()];
+    let mut after_used = vec![false; after.frames.len()];
+    let mut pairs = Vec::new();
+    for (_, i, j) in candidates {
+        if !before_used[i] && !after_used[j] {
+            before_used[i] = true;
+            after_used[j] = true;
+            pairs.push((i, j));
+        }
+    }
+    pairs.sort();
+
+    CompareReport {
+        matched: pairs
+            .into_iter()
+            .map(|(i, j)| MatchedFrame {
+                before: before.frames[i].clone(),
+                after: after.frames[j].clone(),
+            })
+            .collect(),
+        removed: unused(&before.frames, &before_used),
+        added: unused(&after.frames, &after_used),
+    }
+}
+
+fn unused(frames: &[FrameMetadata], used: &[bool]) -> Vec<FrameMetadata> {
+    frames
+        .iter()
+        .zip(used)
+        .filter(|(_, &used)| !used)
+        .map(|(frame, _)| frame.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Timestamp;
+
+    fn frame(filename: &str, timestamp: f64) -> FrameMetadata {
+        FrameMetadata {
+            filename: filename.to_string(),
+            segment_index: 0,
+            frame_index: (timestamp * 30.0) as usize,
+            timestamp_seconds: Timestamp::from_seconds(timestamp),
+            timestamp_formatted: format!("{:.3}", timestamp),
+            start_frame_timestamp: Timestamp::from_seconds(timestamp),
+            end_frame_timestamp: Timestamp::from_seconds(timestamp),
+            duration_seconds: 0.0,
+            sharpness: 0.0,
+            exposure: 1.0,
+            relocation: None,
+            boundary: None,
+            clip: None,
+            pixel_sha256: None,
+            palette: None,
+            page: None,
+        }
+    }
+
+    fn run(frames: Vec<FrameMetadata>) -> OutputMetadata {
+        OutputMetadata {
+            source_video: "video.mp4".to_string(),
+            video_duration_seconds: 60.0,
+            video_frame_count: 1800,
+            extracted_frames: frames.len(),
+            detail_level: "summary".to_string(),
+            quality_preset: "balanced".to_string(),
+            sample_offset: 0,
+            skipped_frames: Vec::new(),
+            settings_fingerprint: String::new(),
+            complete: true,
+            preview: false,
+            trim_start_seconds: 0.0,
+            trim_end_seconds: 0.0,
+            decoded_frame_count: None,
+            scenes: None,
+            channel_stats: None,
+            frames,
+        }
+    }
+
+    #[test]
+    fn test_identical_runs_match() {
+        let a = run(vec![frame("0001.jpg", 1.0), frame("0002.jpg", 10.0)]);
+        let report = compare_metadata(&a, &a, 0.5);
+
+        assert!(report.is_match());
+        assert_eq!(report.matched.len(), 2);
+    }
+
+    #[test]
+    fn test_added_removed_and_shifted() {
+        let a = run(vec![
+            frame("0001.jpg", 1.0),
+            frame("0002.jpg", 10.0),
+            frame("0003.jpg", 20.0),
+        ]);
+        let b = run(vec![
+            frame("0001.jpg", 1.2),
+            frame("0002.jpg", 20.0),
+            frame("0003.jpg", 30.0),
+        ]);
+        let report = compare_metadata(&a, &b, 0.5);
+
+        assert!(!report.is_match());
+        assert_eq!(report.matched.len(), 2);
+        assert!((report.matched[0].shift_seconds() - 0.2).abs() < 1e-9);
+        assert_eq!(report.removed[0].filename, "0002.jpg");
+        assert_eq!(report.added[0].filename, "0003.jpg");
+    }
+}
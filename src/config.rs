@@ -1,18 +1,128 @@
 //! Configuration types for SceneSplit.
 
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Config file name searched for in the current directory.
+pub const CONFIG_FILENAME: &str = "scenesplit.toml";
+
+/// Suffix appended to a video's file name to find its sidecar config.
+pub const SIDECAR_SUFFIX: &str = "scenesplit.json";
+
+/// Model input size used when the model doesn't declare a fixed one.
+pub const DEFAULT_MODEL_INPUT_SIZE: u32 = 224;
+
+/// ONNX Runtime graph optimization level (0 disables optimization, 3 applies
+/// every optimization).
+pub const DEFAULT_ORT_OPT_LEVEL: u8 = 3;
+
+/// Seconds a model download may wait to connect or for more data.
+pub const DEFAULT_DOWNLOAD_TIMEOUT_SECS: f64 = 30.0;
 
 /// Default output directory name.
 pub const DEFAULT_OUTPUT_DIR: &str = "scenesplit_output";
 
+/// Name of the metadata file written to the output directory.
+pub const METADATA_FILENAME: &str = "metadata.json";
+
+/// Name of the `sha256sum`-compatible manifest written with `--checksums`.
+pub const CHECKSUMS_FILENAME: &str = "SHA256SUMS";
+
 /// Output image format.
 pub const OUTPUT_IMAGE_FORMAT: &str = "jpg";
 
 /// Output image quality (1-100).
 pub const OUTPUT_IMAGE_QUALITY: i32 = 95;
 
+/// Rough JPEG size per pixel at `OUTPUT_IMAGE_QUALITY`, for free-space checks.
+pub const ESTIMATED_JPEG_BYTES_PER_PIXEL: f64 = 0.5;
+
+/// Grayscale levels at or below this count as clipped to black.
+pub const EXPOSURE_BLACK_LEVEL: u8 = 4;
+
+/// Grayscale levels at or above this count as clipped to white.
+pub const EXPOSURE_WHITE_LEVEL: u8 = 251;
+
+/// Width stills are scaled down to before comparing them with SSIM.
+pub const SSIM_COMPARE_WIDTH: u32 = 256;
+
+/// Side of the square blocks SSIM statistics are computed over.
+pub const SSIM_BLOCK_SIZE: u32 = 8;
+
+/// Sampled frame count above which a similarity matrix dump warns about size.
+pub const SIMILARITY_MATRIX_WARN_FRAMES: usize = 5_000;
+
+/// Long edge `--quality draft` shrinks frames to at decode, unless
+/// `--decode-scale` is given.
+pub const DRAFT_ANALYSIS_LONG_EDGE: u32 = 256;
+
+/// Frames embedded, evenly spaced, to choose a level for `--detail auto`.
+pub const AUTO_DETAIL_PROBE_FRAMES: usize = 50;
+
+/// Share of probe gaps with a scene change at or above which `--detail auto`
+/// picks `key`.
+pub const AUTO_DETAIL_KEY_SHARE: f32 = 0.5;
+
+/// Frames decoded, evenly spaced, to measure channel statistics for
+/// `--calibrate`.
+pub const CALIBRATION_PROBE_FRAMES: usize = 300;
+
+/// Gap between a detail level's split and merge thresholds.
+pub const HYSTERESIS_MARGIN: f32 = 0.05;
+
+/// How far, as a share of the expected count, the number of sampled frames
+/// may drift before a warning. Container frame counts are often estimates.
+pub const SAMPLE_COUNT_TOLERANCE: f64 = 0.02;
+
+/// Sampled frames buffered between checks of free memory with
+/// `--min-free-mem`.
+pub const MEMORY_CHECK_INTERVAL_FRAMES: usize = 64;
+
+/// Luma difference from both vertical neighbors, in the same direction,
+/// beyond which a pixel counts as combed.
+pub const COMBING_LUMA_DELTA: i32 = 12;
+
+/// Median share of combed pixels over the probe frames at or above which
+/// a video is likely interlaced.
+pub const INTERLACED_COMBING_SHARE: f64 = 0.02;
+
+/// Sampled frames, evenly spaced, checked for interlacing combs.
+pub const INTERLACE_PROBE_FRAMES: usize = 15;
+
+/// Pixels, evenly spaced, clustered to find a still's `--palette`.
+pub const PALETTE_SAMPLE_PIXELS: usize = 4096;
+
+/// Most k-means iterations run to find a still's `--palette`.
+pub const PALETTE_ITERATIONS: usize = 10;
+
+/// Length of each evenly spaced window analyzed by `--preview-sample`.
+pub const PREVIEW_WINDOW_SECONDS: f64 = 1.0;
+
+/// Frames before a boundary examined for a gradual similarity decline.
+pub const TRANSITION_LEAD_IN_FRAMES: usize = 3;
+
+/// Share of the total similarity decline that must happen in the boundary
+/// frame itself for the boundary to count as a hard cut.
+pub const CUT_DROP_SHARE: f32 = 0.6;
+
+/// Share of probe gaps with a scene change at or below which `--detail auto`
+/// picks `all`.
+pub const AUTO_DETAIL_ALL_SHARE: f32 = 0.15;
+
+/// Default EMA weight given to the existing segment anchor.
+///
+/// Higher values keep the anchor stable; lower values let it follow the
+/// most recent frames more closely.
+pub const DEFAULT_ANCHOR_ALPHA: f32 = 0.9;
+
 /// Detail level controlling extraction granularity.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DetailLevel {
     /// Minimal: only major scene changes (5-10 frames for long video)
     Key,
@@ -21,6 +131,8 @@ pub enum DetailLevel {
     Summary,
     /// Comprehensive: all detected semantic changes (20-30 frames)
     All,
+    /// Probe the video's pacing and pick one of the levels above
+    Auto,
 }
 
 impl DetailLevel {
@@ -31,24 +143,52 @@ impl DetailLevel {
     pub fn similarity_threshold(self) -> f32 {
         match self {
             DetailLevel::Key => 0.92,
-            DetailLevel::Summary => 0.85,
+            // Auto is resolved before segmentation; fall back to Summary
+            DetailLevel::Summary | DetailLevel::Auto => 0.85,
             DetailLevel::All => 0.75,
         }
     }
 
+    /// Similarity to the current segment's anchor at which a frame following
+    /// a candidate boundary cancels it (see `--merge-threshold`).
+    ///
+    /// Sits [`HYSTERESIS_MARGIN`] above the split threshold, so similarity
+    /// hovering around a single cutoff doesn't toggle boundaries on and off.
+    pub fn merge_threshold(self) -> f32 {
+        (self.similarity_threshold() + HYSTERESIS_MARGIN).min(1.0)
+    }
+
     /// Minimum frames between keyframes to avoid over-extraction.
     pub fn min_segment_frames(self) -> usize {
         match self {
-            DetailLevel::Key => 90,     // ~3 seconds at 30fps
-            DetailLevel::Summary => 45, // ~1.5 seconds
-            DetailLevel::All => 15,     // ~0.5 seconds
+            DetailLevel::Key => 90,                         // ~3 seconds at 30fps
+            DetailLevel::Summary | DetailLevel::Auto => 45, // ~1.5 seconds
+            DetailLevel::All => 15,                         // ~0.5 seconds
+        }
+    }
+
+    /// Level for a video where `change_share` of the gaps between probe
+    /// frames contain a scene change.
+    ///
+    /// Fast-paced videos get `Key` so the stills stay few; slow ones get
+    /// `All` so subtle changes aren't missed.
+    pub fn for_change_share(change_share: f32) -> Self {
+        if change_share >= AUTO_DETAIL_KEY_SHARE {
+            DetailLevel::Key
+        } else if change_share <= AUTO_DETAIL_ALL_SHARE {
+            DetailLevel::All
+        } else {
+            DetailLevel::Summary
         }
     }
 }
 
 /// Quality preset affecting processing fidelity and speed.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum QualityPreset {
+    /// Fastest: sparse sampling and low-resolution grayscale analysis
+    Draft,
     /// Quick processing, lower fidelity
     Fast,
     /// Default: good balance
@@ -62,6 +202,7 @@ impl QualityPreset {
     /// Sample every Nth frame for embedding computation.
     pub fn frame_sample_rate(self) -> usize {
         match self {
+            QualityPreset::Draft => 30,
             QualityPreset::Fast => 15,
             QualityPreset::Balanced => 5,
             QualityPreset::Best => 1,
@@ -71,18 +212,318 @@ impl QualityPreset {
     /// Batch size for embedding computation.
     pub fn embedding_batch_size(self) -> usize {
         match self {
+            QualityPreset::Draft => 64,
             QualityPreset::Fast => 64,
             QualityPreset::Balanced => 32,
             QualityPreset::Best => 16,
         }
     }
 
-    /// Factor to resize images for embedding (1.0 = full size).
-    pub fn image_resize_factor(self) -> f32 {
-        match self {
-            QualityPreset::Fast => 0.5,
-            QualityPreset::Balanced => 0.75,
-            QualityPreset::Best => 1.0,
+    /// Whether frames are analyzed as grayscale (replicated to 3 channels).
+    pub fn grayscale_analysis(self) -> bool {
+        self == QualityPreset::Draft
+    }
+
+    /// Long edge frames are shrunk to at decode for analysis, if any.
+    pub fn analysis_long_edge(self) -> Option<u32> {
+        (self == QualityPreset::Draft).then_some(DRAFT_ANALYSIS_LONG_EDGE)
+    }
+}
+
+/// How non-square frames are fitted to the model's square input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreprocessMode {
+    /// Resize straight to a square, distorting wide or tall frames
+    #[default]
+    Stretch,
+    /// Pad to a square with black bars, keeping the whole frame undistorted
+    Letterbox,
+    /// Take the central square, discarding the edges of wide or tall frames
+    CenterCrop,
+}
+
+/// Which segment's representative `--poster` saves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PosterStrategy {
+    /// The representative nearest the mean of all representatives
+    #[default]
+    Centroid,
+    /// The representative of the segment with the most sampled frames
+    Longest,
+    /// The first segment's representative
+    First,
+}
+
+/// Chroma subsampling of JPEG stills (`--jpeg-subsampling`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+pub enum JpegSubsampling {
+    /// Full color resolution; colored text keeps sharp edges
+    #[default]
+    #[value(name = "444")]
+    #[serde(rename = "444")]
+    Chroma444,
+    /// Half the horizontal color resolution
+    #[value(name = "422")]
+    #[serde(rename = "422")]
+    Chroma422,
+    /// Half the horizontal and vertical color resolution; smallest files
+    #[value(name = "420")]
+    #[serde(rename = "420")]
+    Chroma420,
+}
+
+/// Which frames of each stride are sampled for analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleStrategy {
+    /// The first frame of every stride
+    #[default]
+    Uniform,
+    /// One frame at a seeded random position within every stride
+    Random,
+    /// Only the container's keyframes (I-frames)
+    Keyframe,
+}
+
+/// Which segments `--max-frames` keeps when there are too many.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaxFramesStrategy {
+    /// The segments whose representatives differ most from one another
+    #[default]
+    Distinct,
+    /// The segments whose representatives are most evenly spread in time
+    Coverage,
+}
+
+/// How a spatial feature map output `(C, H, W)` is reduced to a
+/// `C`-dimensional embedding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PoolingMode {
+    /// Global average pooling
+    #[default]
+    Mean,
+    /// Global max pooling
+    Max,
+}
+
+/// How much console output to produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Progress, notices and warnings
+    #[default]
+    Normal,
+    /// Notices and warnings only, no progress
+    Quiet,
+    /// Errors only
+    Silent,
+}
+
+impl Verbosity {
+    /// Whether stage progress and progress bars are shown.
+    pub fn shows_progress(self) -> bool {
+        self == Verbosity::Normal
+    }
+
+    /// Whether warnings and one-off notices are shown.
+    pub fn shows_warnings(self) -> bool {
+        self != Verbosity::Silent
+    }
+}
+
+/// Default options loaded from a `scenesplit.toml` file.
+///
+/// Every field is optional; anything left out falls back to the CLI default.
+/// Values given on the command line always take precedence over the file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    pub model: Option<PathBuf>,
+    pub detail: Option<DetailLevel>,
+    pub quality: Option<QualityPreset>,
+    pub output: Option<PathBuf>,
+    pub anchor_alpha: Option<f32>,
+    pub min_drop: Option<f32>,
+    pub split_threshold: Option<f32>,
+    pub merge_threshold: Option<f32>,
+    pub normalize_color: Option<bool>,
+    pub preprocess: Option<PreprocessMode>,
+    pub sample_offset: Option<usize>,
+    pub require_codec: Option<Vec<String>>,
+    pub reject_codec: Option<Vec<String>>,
+    pub quiet: Option<bool>,
+    pub silent: Option<bool>,
+}
+
+impl ConfigFile {
+    /// Parse and validate config file contents.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents).map_err(|e| Error::Config(e.to_string()))?;
+
+        for (key, value) in [
+            ("anchor-alpha", config.anchor_alpha),
+            ("min-drop", config.min_drop),
+            ("split-threshold", config.split_threshold),
+            ("merge-threshold", config.merge_threshold),
+        ] {
+            if let Some(value) = value {
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(Error::Config(format!(
+                        "'{}' must be in range 0.0-1.0, got {}",
+                        key, value
+                    )));
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Load a config file from an explicit path.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("Failed to read '{}': {}", path.display(), e)))?;
+        Self::parse(&contents).map_err(|e| match e {
+            Error::Config(reason) => {
+                Error::Config(format!("Invalid config '{}': {}", path.display(), reason))
+            }
+            other => other,
+        })
+    }
+
+    /// Load `scenesplit.toml` from the current directory, if present.
+    pub fn discover() -> Result<Self> {
+        let path = Path::new(CONFIG_FILENAME);
+        if path.is_file() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Per-video overrides loaded from a `<video>.scenesplit.json` sidecar.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoSidecar {
+    pub model: Option<PathBuf>,
+    pub detail: Option<DetailLevel>,
+}
+
+impl VideoSidecar {
+    /// Sidecar path for a video, e.g. `clip.mp4` -> `clip.mp4.scenesplit.json`.
+    pub fn path_for(video: &Path) -> PathBuf {
+        let mut name = video.as_os_str().to_owned();
+        name.push(".");
+        name.push(SIDECAR_SUFFIX);
+        PathBuf::from(name)
+    }
+
+    /// Load the sidecar next to `video`, if one exists.
+    ///
+    /// A relative model path is resolved against the sidecar's directory.
+    pub fn discover(video: &Path) -> Result<Self> {
+        let path = Self::path_for(video);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let invalid = |reason: String| {
+            Error::Config(format!("Invalid sidecar '{}': {}", path.display(), reason))
+        };
+        let contents = fs::read_to_string(&path).map_err(|e| invalid(e.to_string()))?;
+        let mut sidecar: Self =
+            serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        if let (Some(model), Some(dir)) = (&sidecar.model, path.parent()) {
+            sidecar.model = Some(dir.join(model));
         }
+        Ok(sidecar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detail_for_change_share() {
+        assert_eq!(DetailLevel::for_change_share(0.8), DetailLevel::Key);
+        assert_eq!(DetailLevel::for_change_share(0.3), DetailLevel::Summary);
+        assert_eq!(DetailLevel::for_change_share(0.0), DetailLevel::All);
+    }
+
+    #[test]
+    fn test_jpeg_subsampling_names() {
+        assert_eq!(
+            JpegSubsampling::from_str("420", false),
+            Ok(JpegSubsampling::Chroma420)
+        );
+        assert_eq!(
+            serde_json::to_string(&JpegSubsampling::Chroma422).unwrap(),
+            "\"422\""
+        );
+        assert_eq!(JpegSubsampling::default(), JpegSubsampling::Chroma444);
+    }
+
+    #[test]
+    fn test_parse_config_file() {
+        let config = ConfigFile::parse(
+            r#"
+            detail = "all"
+            quality = "fast"
+            output = "frames"
+            anchor-alpha = 0.8
+            split-threshold = 0.82
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.detail, Some(DetailLevel::All));
+        assert_eq!(config.quality, Some(QualityPreset::Fast));
+        assert_eq!(config.output, Some(PathBuf::from("frames")));
+        assert_eq!(config.anchor_alpha, Some(0.8));
+        assert_eq!(config.split_threshold, Some(0.82));
+        assert_eq!(config.merge_threshold, None);
+        assert_eq!(config.model, None);
+    }
+
+    #[test]
+    fn test_parse_config_file_rejects_unknown_keys() {
+        assert!(ConfigFile::parse("detial = \"all\"").is_err());
+    }
+
+    #[test]
+    fn test_video_sidecar_discover() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("clip.mp4");
+        assert_eq!(
+            VideoSidecar::discover(&video).unwrap(),
+            VideoSidecar::default()
+        );
+
+        fs::write(
+            dir.path().join("clip.mp4.scenesplit.json"),
+            r#"{"model": "screencast.onnx", "detail": "all"}"#,
+        )
+        .unwrap();
+        let sidecar = VideoSidecar::discover(&video).unwrap();
+        assert_eq!(sidecar.model, Some(dir.path().join("screencast.onnx")));
+        assert_eq!(sidecar.detail, Some(DetailLevel::All));
+
+        fs::write(
+            dir.path().join("clip.mp4.scenesplit.json"),
+            r#"{"modle": 1}"#,
+        )
+        .unwrap();
+        assert!(VideoSidecar::discover(&video).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_file_rejects_out_of_range() {
+        assert!(ConfigFile::parse("anchor-alpha = 1.5").is_err());
+        assert!(ConfigFile::parse("merge-threshold = -0.1").is_err());
     }
 }
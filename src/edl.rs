@@ -0,0 +1,210 @@
+//! CMX3600 edit decision lists with one event per segment.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::segmentation::SemanticSegment;
+use crate::video::VideoMetadata;
+
+/// Reel name used for every event.
+const REEL: &str = "AX";
+
+/// SMPTE timecode for a frame rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timecode {
+    /// Whole frames per timecode second (30 for 29.97).
+    timebase: u64,
+    /// Frame numbers skipped at the start of each minute, except every
+    /// tenth; zero for non-drop-frame.
+    dropped: u64,
+}
+
+impl Timecode {
+    /// Drop-frame timecode for the NTSC rates 29.97 and 59.94, non-drop-frame
+    /// otherwise.
+    pub fn for_fps(fps: f64) -> Self {
+        let timebase = fps.round().max(1.0) as u64;
+        let ntsc = (fps - timebase as f64 * 1000.0 / 1001.0).abs() < 0.01;
+        let dropped = if ntsc && timebase.is_multiple_of(30) {
+            timebase / 15
+        } else {
+            0
+        };
+        Self { timebase, dropped }
+    }
+
+    pub fn is_drop_frame(&self) -> bool {
+        self.dropped > 0
+    }
+
+    /// Format `frame` as `HH:MM:SS:FF` (`HH:MM:SS;FF` for drop-frame).
+    pub fn format(&self, frame: usize) -> String {
+        let mut frame = frame as u64;
+        if self.dropped > 0 {
+            let per_minute = self.timebase * 60 - self.dropped;
+            let per_ten_minutes = per_minute * 10 + self.dropped;
+            let tens = frame / per_ten_minutes;
+            let rest = frame % per_ten_minutes;
+            frame += self.dropped * 9 * tens;
+            if rest > self.dropped {
+                frame += self.dropped * ((rest - self.dropped) / per_minute);
+            }
+        }
+
+        let fps = self.timebase;
+        let separator = if self.dropped > 0 { ';' } else { ':' };
+        format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            frame / (fps * 3600),
+            frame / (fps * 60) % 60,
+            frame / fps % 60,
+            separator,
+            frame % fps
+        )
+    }
+}
+
+/// Write one cut event per segment to `path`.
+///
+/// Each event runs from its segment's first frame to the next segment's
+/// first frame, or to `end_frame` (exclusive), where the analyzed part of
+/// the video ends, for the last one. Source and record timecodes are the
+/// same, so the cuts line up with the original clip.
+pub fn write_edl(
+    path: &Path,
+    segments: &[SemanticSegment],
+    video: &VideoMetadata,
+    end_frame: usize,
+) -> Result<()> {
+    let file = File::create(path)
+        .map_err(|e| Error::Output(format!("Failed to create '{}': {}", path.display(), e)))?;
+    let mut writer = BufWriter::new(file);
+
+    let clip = video
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let title = video
+        .path
+        .file_stem()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let timecode = Timecode::for_fps(video.fps);
+
+    writeln!(writer, "TITLE: {}", title)?;
+    if timecode.is_drop_frame() {
+        writeln!(writer, "FCM: DROP FRAME")?;
+    } else {
+        writeln!(writer, "FCM: NON-DROP FRAME")?;
+    }
+
+    for (i, segment) in segments.iter().enumerate() {
+        let start = segment.start_frame_idx;
+        let end = segments
+            .get(i + 1)
+            .map(|next| next.start_frame_idx)
+            .unwrap_or(end_frame.max(segment.end_frame_idx + 1));
+        let (tc_in, tc_out) = (timecode.format(start), timecode.format(end));
+
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{:03}  {:<8} V     C        {} {} {} {}",
+            i + 1,
+            REEL,
+            tc_in,
+            tc_out,
+            tc_in,
+            tc_out
+        )?;
+        writeln!(writer, "* FROM CLIP NAME: {}", clip)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segmentation::uniform_segments;
+    use crate::timestamp::Timestamp;
+    use crate::video::Frame;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_non_drop_frame_timecode() {
+        let tc = Timecode::for_fps(25.0);
+        assert!(!tc.is_drop_frame());
+        assert_eq!(tc.format(0), "00:00:00:00");
+        assert_eq!(tc.format(90_000), "01:00:00:00");
+        assert_eq!(tc.format(1_524), "00:01:00:24");
+        assert_eq!(Timecode::for_fps(30.0).format(1_800), "00:01:00:00");
+    }
+
+    #[test]
+    fn test_drop_frame_timecode() {
+        let tc = Timecode::for_fps(30_000.0 / 1_001.0);
+        assert!(tc.is_drop_frame());
+        assert_eq!(tc.format(1_799), "00:00:59;29");
+        // Frame numbers 00 and 01 are skipped at the start of minute 1...
+        assert_eq!(tc.format(1_800), "00:01:00;02");
+        // ...but not at minute 10
+        assert_eq!(tc.format(17_982), "00:10:00;00");
+        assert_eq!(tc.format(107_892), "01:00:00;00");
+
+        assert_eq!(Timecode::for_fps(59.94).format(3_600), "00:01:00;04");
+        assert!(!Timecode::for_fps(23.976).is_drop_frame());
+    }
+
+    #[test]
+    fn test_write_edl() {
+        let video = VideoMetadata {
+            path: PathBuf::from("/footage/interview.mp4"),
+            width: 1920,
+            height: 1080,
+            fps: 25.0,
+            frame_count: 250,
+            duration_seconds: 10.0,
+            codec: "avc1".to_string(),
+            decoded_frame_count: None,
+        };
+        let frames = [0, 100]
+            .into_iter()
+            .map(|index| Frame {
+                index,
+                timestamp_seconds: Timestamp::from_frame(index, 25.0),
+                data: Vec::new(),
+                width: 0,
+                height: 0,
+                data16: None,
+            })
+            .collect();
+        let segments = uniform_segments(frames, 10.0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cuts.edl");
+        write_edl(&path, &segments, &video, 250).unwrap();
+
+        let edl = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = edl.lines().collect();
+        assert_eq!(lines[0], "TITLE: interview");
+        assert_eq!(lines[1], "FCM: NON-DROP FRAME");
+        assert_eq!(
+            lines[3],
+            "001  AX       V     C        00:00:00:00 00:00:04:00 00:00:00:00 00:00:04:00"
+        );
+        assert_eq!(lines[4], "* FROM CLIP NAME: interview.mp4");
+        assert!(lines[6].starts_with("002  AX       V     C        00:00:04:00 00:00:10:00"));
+
+        // With the last two seconds trimmed, the last event ends at 8s
+        write_edl(&path, &segments, &video, 200).unwrap();
+        let edl = std::fs::read_to_string(&path).unwrap();
+        assert!(edl
+            .lines()
+            .any(|l| l.starts_with("002  AX       V     C        00:00:04:00 00:00:08:00")));
+    }
+}
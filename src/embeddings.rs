@@ -4,10 +4,14 @@ use std::path::Path;
 
 use ndarray::{s, Array4};
 use ort::session::{builder::GraphOptimizationLevel, Session};
+use ort::value::ValueType;
+use serde::{Deserialize, Serialize};
 
-use crate::config::QualityPreset;
+use crate::config::{PoolingMode, PreprocessMode, QualityPreset, DEFAULT_MODEL_INPUT_SIZE};
 use crate::error::{Error, Result};
-use crate::video::Frame;
+use crate::profile;
+use crate::timestamp::Timestamp;
+use crate::video::{Frame, Region};
 
 /// A frame with its computed embedding vector.
 #[derive(Debug, Clone)]
@@ -22,17 +26,85 @@ impl EmbeddedFrame {
         self.frame.index
     }
 
-    /// Frame timestamp in seconds.
-    #[allow(dead_code)]
-    pub fn timestamp_seconds(&self) -> f64 {
+    /// Frame timestamp.
+    pub fn timestamp_seconds(&self) -> Timestamp {
         self.frame.timestamp_seconds
     }
 }
 
+/// Per-channel mean and standard deviation used to normalize model input,
+/// in RGB order on a 0 to 1 scale.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStats {
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+}
+
+impl ChannelStats {
+    /// The ImageNet statistics most pretrained models expect.
+    pub const IMAGENET: Self = Self {
+        mean: [0.485, 0.456, 0.406],
+        std: [0.229, 0.224, 0.225],
+    };
+}
+
+impl Default for ChannelStats {
+    fn default() -> Self {
+        Self::IMAGENET
+    }
+}
+
+/// Mean and standard deviation of each color channel over every pixel of
+/// `frames`.
+///
+/// Standard deviations are floored at one gray level so a flat channel
+/// can't blow up the normalized input. With no pixels at all the ImageNet
+/// statistics are returned.
+pub fn channel_stats(frames: &[Frame]) -> ChannelStats {
+    let mut sums = [0f64; 3];
+    let mut squares = [0f64; 3];
+    let mut pixels = 0u64;
+    for frame in frames {
+        for pixel in frame.data.chunks_exact(3) {
+            for c in 0..3 {
+                let value = pixel[c] as f64 / 255.0;
+                sums[c] += value;
+                squares[c] += value * value;
+            }
+            pixels += 1;
+        }
+    }
+    if pixels == 0 {
+        return ChannelStats::IMAGENET;
+    }
+
+    let n = pixels as f64;
+    let mean = sums.map(|sum| sum / n);
+    let mut std = [0f32; 3];
+    for c in 0..3 {
+        let variance = (squares[c] / n - mean[c] * mean[c]).max(0.0);
+        std[c] = (variance.sqrt() as f32).max(1.0 / 255.0);
+    }
+    ChannelStats {
+        mean: mean.map(|m| m as f32),
+        std,
+    }
+}
+
 /// Compute semantic embeddings for video frames using ONNX Runtime.
 pub struct EmbeddingModel {
     session: Session,
     quality: QualityPreset,
+    input_size: u32,
+    normalize_color: bool,
+    preprocess: PreprocessMode,
+    pooling: PoolingMode,
+    masks: Vec<Region>,
+    mask_frame_size: Option<(u32, u32)>,
+    channel_stats: ChannelStats,
+    batch_size: usize,
+    skip_bad_frames: bool,
+    skipped: Vec<(usize, String)>,
 }
 
 impl EmbeddingModel {
@@ -42,86 +114,171 @@ impl EmbeddingModel {
     ///
     /// * `model_path` - Path to the ONNX model file (ResNet50 or similar).
     /// * `quality` - Quality preset affecting image preprocessing.
-    pub fn new<P: AsRef<Path>>(model_path: P, quality: QualityPreset) -> Result<Self> {
+    /// * `opt_level` - ONNX Runtime graph optimization level, 0 to 3.
+    pub fn new<P: AsRef<Path>>(
+        model_path: P,
+        quality: QualityPreset,
+        opt_level: u8,
+    ) -> Result<Self> {
         let session = Session::builder()?
-            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .with_optimization_level(graph_optimization_level(opt_level))?
             .with_intra_threads(4)?
             .commit_from_file(model_path)?;
 
-        Ok(Self { session, quality })
+        let input_size = match session.inputs.first().map(|i| &i.input_type) {
+            Some(ValueType::Tensor { shape, .. }) => model_input_size(shape)?,
+            _ => None,
+        };
+
+        Ok(Self {
+            session,
+            quality,
+            input_size: input_size.unwrap_or(DEFAULT_MODEL_INPUT_SIZE),
+            normalize_color: false,
+            preprocess: PreprocessMode::default(),
+            pooling: PoolingMode::default(),
+            masks: Vec::new(),
+            mask_frame_size: None,
+            channel_stats: ChannelStats::IMAGENET,
+            batch_size: quality.embedding_batch_size(),
+            skip_bad_frames: false,
+            skipped: Vec::new(),
+        })
     }
 
-    /// Preprocess a frame for the embedding model.
+    /// Set how spatial feature map outputs are pooled into a vector.
+    pub fn with_pooling(mut self, mode: PoolingMode) -> Self {
+        self.pooling = mode;
+        self
+    }
+
+    /// Override the square input size read from the model, in pixels.
+    pub fn with_input_size(mut self, size: Option<u32>) -> Self {
+        if let Some(size) = size {
+            self.input_size = size;
+        }
+        self
+    }
+
+    /// Apply gray-world white balance to frames before embedding.
     ///
-    /// Resizes to 224x224, normalizes with ImageNet mean/std.
-    fn preprocess_frame(&self, frame: &Frame) -> Result<Array4<f32>> {
-        let resize_factor = self.quality.image_resize_factor();
+    /// Only the model input is affected; the frames themselves are untouched.
+    pub fn with_color_normalization(mut self, enabled: bool) -> Self {
+        self.normalize_color = enabled;
+        self
+    }
 
-        // Calculate target size after optional resize
-        let (src_width, src_height) = if resize_factor < 1.0 {
-            (
-                (frame.width as f32 * resize_factor) as u32,
-                (frame.height as f32 * resize_factor) as u32,
-            )
-        } else {
-            (frame.width, frame.height)
-        };
+    /// Blank these regions of each frame before embedding, so static
+    /// overlays such as scoreboards don't affect similarity.
+    ///
+    /// Only the model input is affected; the frames themselves are untouched.
+    pub fn with_masks(mut self, masks: Vec<Region>) -> Self {
+        self.masks = masks;
+        self
+    }
 
-        // Create image from raw RGB data
-        let img = image::RgbImage::from_raw(frame.width, frame.height, frame.data.clone())
-            .ok_or_else(|| Error::Embedding("Failed to create image from frame data".into()))?;
-
-        // Resize if needed
-        let img = if resize_factor < 1.0 {
-            image::imageops::resize(
-                &img,
-                src_width,
-                src_height,
-                image::imageops::FilterType::Triangle,
-            )
-        } else {
-            img
-        };
+    /// Size of the frames the masks are given for. Frames decoded at
+    /// another size (`--decode-scale`) have the masks scaled to match.
+    pub fn with_mask_frame_size(mut self, size: Option<(u32, u32)>) -> Self {
+        self.mask_frame_size = size;
+        self
+    }
 
-        // Resize to 224x224 for the model
-        let img = image::imageops::resize(&img, 224, 224, image::imageops::FilterType::Triangle);
+    /// Normalize model input with these statistics instead of ImageNet's.
+    pub fn with_channel_stats(mut self, stats: ChannelStats) -> Self {
+        self.channel_stats = stats;
+        self
+    }
 
-        // Convert to NCHW format with normalization
-        // ImageNet mean: [0.485, 0.456, 0.406], std: [0.229, 0.224, 0.225]
-        let mean = [0.485f32, 0.456, 0.406];
-        let std = [0.229f32, 0.224, 0.225];
+    /// Set how non-square frames are fitted to the square model input.
+    pub fn with_preprocess(mut self, mode: PreprocessMode) -> Self {
+        self.preprocess = mode;
+        self
+    }
 
-        let mut tensor = Array4::<f32>::zeros((1, 3, 224, 224));
+    /// Embed at most this many frames per model run, below the quality
+    /// preset's batch size.
+    pub fn with_max_batch(mut self, max: Option<usize>) -> Self {
+        self.batch_size = capped_batch_size(self.quality, max);
+        self
+    }
 
-        for y in 0..224 {
-            for x in 0..224 {
-                let pixel = img.get_pixel(x as u32, y as u32);
-                for c in 0..3 {
-                    let value = pixel[c] as f32 / 255.0;
-                    let normalized = (value - mean[c]) / std[c];
-                    tensor[[0, c, y, x]] = normalized;
-                }
-            }
-        }
+    /// Frames embedded per model run.
+    ///
+    /// Starts at the quality preset's batch size (capped by
+    /// [`with_max_batch`](Self::with_max_batch)) and halves whenever a batch
+    /// runs out of device memory.
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
 
-        Ok(tensor)
+    /// Drop frames that fail to embed instead of aborting the batch.
+    ///
+    /// Dropped frames are collected for [`take_skipped`](Self::take_skipped).
+    pub fn with_skip_bad_frames(mut self, enabled: bool) -> Self {
+        self.skip_bad_frames = enabled;
+        self
+    }
+
+    /// Run one all-zero input through the model and check that it returns a
+    /// usable embedding.
+    ///
+    /// Catches models ONNX Runtime loads but can't execute (unsupported
+    /// operators, mismatched input shapes) before any video is decoded.
+    pub fn verify(&mut self) -> Result<()> {
+        let size = self.input_size as usize;
+        let input = ort::value::Tensor::from_array(Array4::<f32>::zeros((1, 3, size, size)))?;
+        let outputs = self.session.run(ort::inputs![input]).map_err(|e| {
+            Error::ModelLoad(format!(
+                "Model failed a test inference on a 1x3x{}x{} input: {}",
+                size, size, e
+            ))
+        })?;
+        let (shape, data) = outputs[0].try_extract_tensor::<f32>().map_err(|e| {
+            Error::ModelLoad(format!(
+                "Model test inference returned no f32 tensor: {}",
+                e
+            ))
+        })?;
+        check_test_output(shape, data)
+            .map_err(|reason| Error::ModelLoad(format!("Model test inference {}", reason)))
+    }
+
+    /// Preprocess a frame for the embedding model.
+    ///
+    /// Fits to a square per the preprocess mode, resizes to the model's
+    /// input size and normalizes with the channel statistics, ImageNet's
+    /// unless calibrated.
+    fn preprocess_frame(&self, frame: &Frame) -> Result<Array4<f32>> {
+        let _span = profile::span("preprocess");
+        frame_to_tensor(
+            frame,
+            self.input_size,
+            self.preprocess,
+            &frame_masks(&self.masks, self.mask_frame_size, frame),
+            self.normalize_color,
+            self.quality.grayscale_analysis(),
+            &self.channel_stats,
+        )
     }
 
     /// Compute the embedding for a single frame.
-    #[allow(dead_code)]
     pub fn compute_embedding(&mut self, frame: &Frame) -> Result<EmbeddedFrame> {
         let input = self.preprocess_frame(frame)?;
         let input_value = ort::value::Tensor::from_array(input)?;
-        let outputs = self.session.run(ort::inputs![input_value])?;
+        let outputs = {
+            let _span = profile::span("inference");
+            self.session.run(ort::inputs![input_value])?
+        };
 
         // Get the output tensor - new API returns (shape, data) tuple
-        let (_, data) = outputs[0]
+        let (shape, data) = outputs[0]
             .try_extract_tensor::<f32>()
             .map_err(|e| Error::Embedding(format!("Failed to extract embedding: {}", e)))?;
 
-        // Flatten and normalize
-        let flat: Vec<f32> = data.to_vec();
-        let embedding = normalize_vector(&flat);
+        let embedding = batch_embeddings(shape, data, self.pooling)?
+            .pop()
+            .ok_or_else(|| Error::Embedding("Model returned no embedding".into()))?;
 
         Ok(EmbeddedFrame {
             frame: frame.clone(),
@@ -142,59 +299,334 @@ impl EmbeddingModel {
             return Ok(Vec::new());
         }
 
-        let batch_size = self.quality.embedding_batch_size();
         let mut results = Vec::with_capacity(frames.len());
+        let mut processed = 0;
 
-        for chunk in frames.chunks(batch_size) {
-            // Process batch
-            let mut batch_tensor = Array4::<f32>::zeros((chunk.len(), 3, 224, 224));
-
-            for (i, frame) in chunk.iter().enumerate() {
-                let preprocessed = self.preprocess_frame(frame)?;
-                batch_tensor
-                    .slice_mut(s![i, .., .., ..])
-                    .assign(&preprocessed.slice(s![0, .., .., ..]));
-            }
-
-            let batch_value = ort::value::Tensor::from_array(batch_tensor)?;
-            let outputs = self.session.run(ort::inputs![batch_value])?;
-
-            let (shape, data) = outputs[0]
-                .try_extract_tensor::<f32>()
-                .map_err(|e| Error::Embedding(format!("Failed to extract embeddings: {}", e)))?;
-
-            // Calculate embedding size from shape (batch_size, embedding_dim, ...)
-            let embedding_size = shape.iter().skip(1).product::<i64>() as usize;
-
-            // Extract individual embeddings from batch output
-            for (i, frame) in chunk.iter().enumerate() {
-                let start = i * embedding_size;
-                let _end = start + embedding_size;
-                let flat: Vec<f32> = data
-                    .iter()
-                    .skip(start)
-                    .take(embedding_size)
-                    .cloned()
-                    .collect();
-                let embedding = normalize_vector(&flat);
-
-                results.push(EmbeddedFrame {
-                    frame: frame.clone(),
-                    embedding,
-                });
+        while processed < frames.len() {
+            let chunk = &frames[processed..(processed + self.batch_size).min(frames.len())];
+            match self.embed_chunk(chunk) {
+                Ok(embedded) => results.extend(embedded),
+                // Retry the same frames in smaller batches until they fit
+                Err(e) if chunk.len() > 1 && is_allocation_failure(&e) => {
+                    self.batch_size = chunk.len() / 2;
+                    continue;
+                }
+                // Retry one frame at a time to isolate the bad ones
+                Err(_) if self.skip_bad_frames => {
+                    for frame in chunk {
+                        match self.compute_embedding(frame) {
+                            Ok(embedded) => results.push(embedded),
+                            Err(e) => self.skipped.push((frame.index, e.to_string())),
+                        }
+                    }
+                }
+                Err(e) => return Err(e),
             }
+            processed += chunk.len();
 
             if let Some(ref mut cb) = progress_callback {
-                cb(results.len(), frames.len());
+                cb(processed, frames.len());
             }
         }
 
         Ok(results)
     }
+
+    /// Frames dropped since the last call, with the reason each failed.
+    pub fn take_skipped(&mut self) -> Vec<(usize, String)> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    /// Embed one batch of frames in a single model run.
+    fn embed_chunk(&mut self, chunk: &[Frame]) -> Result<Vec<EmbeddedFrame>> {
+        let size = self.input_size as usize;
+        let mut batch_tensor = Array4::<f32>::zeros((chunk.len(), 3, size, size));
+
+        for (i, frame) in chunk.iter().enumerate() {
+            let preprocessed = self.preprocess_frame(frame)?;
+            batch_tensor
+                .slice_mut(s![i, .., .., ..])
+                .assign(&preprocessed.slice(s![0, .., .., ..]));
+        }
+
+        let batch_value = ort::value::Tensor::from_array(batch_tensor)?;
+        let outputs = {
+            let _span = profile::span("inference");
+            self.session.run(ort::inputs![batch_value])?
+        };
+
+        let (shape, data) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| Error::Embedding(format!("Failed to extract embeddings: {}", e)))?;
+
+        let embeddings = batch_embeddings(shape, data, self.pooling)?;
+        if embeddings.len() != chunk.len() {
+            return Err(Error::Embedding(format!(
+                "Model returned {} embeddings for a batch of {}",
+                embeddings.len(),
+                chunk.len()
+            )));
+        }
+
+        Ok(chunk
+            .iter()
+            .zip(embeddings)
+            .map(|(frame, embedding)| EmbeddedFrame {
+                frame: frame.clone(),
+                embedding,
+            })
+            .collect())
+    }
+}
+
+/// Frames embedded per model run at `quality`, capped by `--max-batch`.
+pub fn capped_batch_size(quality: QualityPreset, max: Option<usize>) -> usize {
+    quality
+        .embedding_batch_size()
+        .min(max.unwrap_or(usize::MAX))
+        .max(1)
+}
+
+/// Whether an inference error is the device running out of memory, which a
+/// smaller batch may avoid.
+fn is_allocation_failure(error: &Error) -> bool {
+    let message = error.to_string().to_lowercase();
+    ["out of memory", "failed to allocate", "bad_alloc"]
+        .iter()
+        .any(|needle| message.contains(needle))
+}
+
+/// Split a batch output of `shape` (batch_size, embedding_dim, ...) into
+/// one pooled, normalized embedding per batch entry.
+///
+/// Single frames go through here as a batch of one, so a frame embeds the
+/// same alone as in a batch.
+fn batch_embeddings(shape: &[i64], data: &[f32], pooling: PoolingMode) -> Result<Vec<Vec<f32>>> {
+    let batch = shape.first().copied().unwrap_or(0).max(0) as usize;
+    let embedding_size = shape.iter().skip(1).product::<i64>().max(0) as usize;
+    if batch == 0 || embedding_size == 0 || data.len() != batch * embedding_size {
+        return Err(Error::Embedding(format!(
+            "Model output of {} values doesn't match its shape {:?}",
+            data.len(),
+            shape
+        )));
+    }
+
+    Ok(data
+        .chunks(embedding_size)
+        .map(|output| normalize_vector(&pool_output(output, &shape[1..], pooling)))
+        .collect())
+}
+
+/// Check a model's output for a single input: one batch entry holding a
+/// non-empty, finite feature vector.
+fn check_test_output(shape: &[i64], data: &[f32]) -> std::result::Result<(), String> {
+    if shape.first() != Some(&1) {
+        return Err(format!("returned shape {:?} for a batch of 1", shape));
+    }
+    if data.is_empty() {
+        return Err("returned an empty embedding".to_string());
+    }
+    if data.iter().any(|x| !x.is_finite()) {
+        return Err("returned non-finite values".to_string());
+    }
+    Ok(())
+}
+
+/// Reduce one frame's model output of shape `dims` (batch dimension
+/// removed) to its embedding.
+///
+/// Feature maps `(C, H, W, ...)` are pooled over every spatial position to
+/// `C` values; pre-pooled outputs such as `(C)` or `(C, 1, 1)` are just
+/// flattened.
+fn pool_output(output: &[f32], dims: &[i64], mode: PoolingMode) -> Vec<f32> {
+    let spatial = dims.iter().skip(1).product::<i64>().max(1) as usize;
+    if spatial == 1 {
+        return output.to_vec();
+    }
+
+    output
+        .chunks(spatial)
+        .map(|channel| match mode {
+            PoolingMode::Mean => channel.iter().sum::<f32>() / channel.len() as f32,
+            PoolingMode::Max => channel.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        })
+        .collect()
+}
+
+/// `masks`, given for `mask_frame_size` frames, scaled to `frame`.
+fn frame_masks(
+    masks: &[Region],
+    mask_frame_size: Option<(u32, u32)>,
+    frame: &Frame,
+) -> Vec<Region> {
+    let size = (frame.width, frame.height);
+    match mask_frame_size {
+        Some(from) if from != size => masks.iter().map(|m| m.rescale(from, size)).collect(),
+        _ => masks.to_vec(),
+    }
+}
+
+/// Turn a frame into a normalized `(1, 3, size, size)` NCHW tensor.
+///
+/// The frame is resized straight from its source resolution to the model
+/// input; any intermediate downscale would be undone by that final resize.
+fn frame_to_tensor(
+    frame: &Frame,
+    size: u32,
+    preprocess: PreprocessMode,
+    masks: &[Region],
+    normalize_color: bool,
+    grayscale: bool,
+    stats: &ChannelStats,
+) -> Result<Array4<f32>> {
+    // Create image from raw RGB data
+    let mut data = frame.data.clone();
+    for mask in masks {
+        mask.blank(&mut data, frame.width, frame.height);
+    }
+    if normalize_color {
+        gray_world_balance(&mut data);
+    }
+    let img = image::RgbImage::from_raw(frame.width, frame.height, data)
+        .ok_or_else(|| Error::Embedding("Failed to create image from frame data".into()))?;
+
+    // Resize to the model's input size
+    let img = fit_square(img, preprocess);
+    let img = if img.dimensions() == (size, size) {
+        img
+    } else {
+        image::imageops::resize(&img, size, size, image::imageops::FilterType::Triangle)
+    };
+
+    // Drop color information when the preset trades accuracy for speed
+    let img = if grayscale {
+        let gray = image::imageops::grayscale(&img);
+        image::RgbImage::from_fn(gray.width(), gray.height(), |x, y| {
+            let luma = gray.get_pixel(x, y)[0];
+            image::Rgb([luma, luma, luma])
+        })
+    } else {
+        img
+    };
+
+    // Convert to NCHW format with normalization
+    let ChannelStats { mean, std } = *stats;
+
+    let size = size as usize;
+    let mut tensor = Array4::<f32>::zeros((1, 3, size, size));
+
+    for y in 0..size {
+        for x in 0..size {
+            let pixel = img.get_pixel(x as u32, y as u32);
+            for c in 0..3 {
+                let value = pixel[c] as f32 / 255.0;
+                let normalized = (value - mean[c]) / std[c];
+                tensor[[0, c, y, x]] = normalized;
+            }
+        }
+    }
+
+    Ok(tensor)
+}
+
+/// Make an image square according to the preprocess mode.
+///
+/// `Stretch` returns the image unchanged and leaves the distortion to the
+/// final resize.
+fn fit_square(img: image::RgbImage, mode: PreprocessMode) -> image::RgbImage {
+    let (width, height) = img.dimensions();
+    if width == height {
+        return img;
+    }
+
+    match mode {
+        PreprocessMode::Stretch => img,
+        PreprocessMode::Letterbox => {
+            let side = width.max(height);
+            let mut canvas = image::RgbImage::new(side, side);
+            let x = (side - width) / 2;
+            let y = (side - height) / 2;
+            image::imageops::overlay(&mut canvas, &img, x as i64, y as i64);
+            canvas
+        }
+        PreprocessMode::CenterCrop => {
+            let side = width.min(height);
+            let x = (width - side) / 2;
+            let y = (height - side) / 2;
+            image::imageops::crop_imm(&img, x, y, side, side).to_image()
+        }
+    }
+}
+
+/// Gray-world white balance on packed RGB data.
+///
+/// Scales each channel so that its mean matches the overall mean, removing
+/// global color casts that would otherwise dominate the embedding.
+fn gray_world_balance(data: &mut [u8]) {
+    let pixels = data.len() / 3;
+    if pixels == 0 {
+        return;
+    }
+
+    let mut sums = [0u64; 3];
+    for pixel in data.chunks_exact(3) {
+        for c in 0..3 {
+            sums[c] += pixel[c] as u64;
+        }
+    }
+
+    let means = sums.map(|sum| sum as f32 / pixels as f32);
+    let gray = means.iter().sum::<f32>() / 3.0;
+    let gains = means.map(|mean| if mean > 0.0 { gray / mean } else { 1.0 });
+
+    for pixel in data.chunks_exact_mut(3) {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f32 * gains[c]).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// ONNX Runtime optimization level for `level`; anything above 3 is Level 3.
+fn graph_optimization_level(level: u8) -> GraphOptimizationLevel {
+    match level {
+        0 => GraphOptimizationLevel::Disable,
+        1 => GraphOptimizationLevel::Level1,
+        2 => GraphOptimizationLevel::Level2,
+        _ => GraphOptimizationLevel::Level3,
+    }
+}
+
+/// Spatial input size declared by an NCHW model input shape.
+///
+/// Returns `None` when height or width is dynamic (`-1`), and an error when
+/// the declared input isn't square.
+fn model_input_size(shape: &[i64]) -> Result<Option<u32>> {
+    let (height, width) = match shape {
+        [_, _, height, width] => (*height, *width),
+        _ => return Ok(None),
+    };
+    if height <= 0 || width <= 0 {
+        return Ok(None);
+    }
+    if height != width {
+        return Err(Error::ModelLoad(format!(
+            "Model expects a non-square {}x{} input; only square inputs are supported",
+            width, height
+        )));
+    }
+    Ok(u32::try_from(height).ok())
 }
 
 /// Normalize a vector to unit length.
-fn normalize_vector(v: &[f32]) -> Vec<f32> {
+///
+/// A zero vector is returned unchanged.
+///
+/// ```ignore
+/// let v = normalize_vector(&[3.0, 4.0]);
+/// assert_eq!(v, vec![0.6, 0.8]);
+/// ```
+pub fn normalize_vector(v: &[f32]) -> Vec<f32> {
     let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
     if norm > 0.0 {
         v.iter().map(|x| x / norm).collect()
@@ -204,13 +636,144 @@ fn normalize_vector(v: &[f32]) -> Vec<f32> {
 }
 
 /// Compute cosine similarity between two normalized embedding vectors.
+///
+/// Inputs must already be unit length (see [`normalize_vector`]); this is a
+/// plain dot product.
+///
+/// ```ignore
+/// let a = normalize_vector(&[1.0, 1.0]);
+/// let b = normalize_vector(&[1.0, 0.0]);
+/// assert!((cosine_similarity(&a, &b) - 0.7071).abs() < 1e-4);
+/// ```
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    // ndarray's unrolled dot product vectorizes; a plain fold over f32 can't
+    // be reordered by the compiler and stays scalar
+    let len = a.len().min(b.len());
+    ndarray::ArrayView1::from(&a[..len]).dot(&ndarray::ArrayView1::from(&b[..len]))
+}
+
+/// Compute cosine similarity between two normalized embedding vectors.
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    cosine_similarity_scalar(a, b)
+}
+
+/// Scalar dot product, for targets without SIMD and as a test reference.
+#[cfg(any(test, not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn cosine_similarity_scalar(a: &[f32], b: &[f32]) -> f32 {
     a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
 }
 
+/// Cosine distances (`1 - similarity`) between every pair of normalized vectors.
+///
+/// Returns the upper triangle without the diagonal, row by row: for `n`
+/// vectors that's `(0,1), (0,2), ..., (0,n-1), (1,2), ...`, `n * (n - 1) / 2`
+/// values in total.
+///
+/// ```ignore
+/// let d = pairwise_distances(&[vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0]]);
+/// assert_eq!(d, vec![1.0, 0.0, 1.0]);
+/// ```
+#[allow(dead_code)]
+pub fn pairwise_distances<V: AsRef<[f32]>>(vectors: &[V]) -> Vec<f32> {
+    let n = vectors.len();
+    let mut distances = Vec::with_capacity(n * n.saturating_sub(1) / 2);
+    for (i, a) in vectors.iter().enumerate() {
+        for b in &vectors[i + 1..] {
+            distances.push(1.0 - cosine_similarity(a.as_ref(), b.as_ref()));
+        }
+    }
+    distances
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::DRAFT_ANALYSIS_LONG_EDGE;
+
+    #[test]
+    fn test_capped_batch_size() {
+        assert_eq!(capped_batch_size(QualityPreset::Balanced, None), 32);
+        assert_eq!(capped_batch_size(QualityPreset::Balanced, Some(8)), 8);
+        assert_eq!(capped_batch_size(QualityPreset::Best, Some(100)), 16);
+        assert_eq!(capped_batch_size(QualityPreset::Fast, Some(0)), 1);
+    }
+
+    #[test]
+    fn test_check_test_output() {
+        assert!(check_test_output(&[1, 2048], &[0.5; 2048]).is_ok());
+        assert!(check_test_output(&[1, 2048, 7, 7], &[0.0; 2048 * 49]).is_ok());
+        assert!(check_test_output(&[2, 4], &[0.0; 8]).is_err());
+        assert!(check_test_output(&[1, 0], &[]).is_err());
+        assert!(check_test_output(&[1, 2], &[f32::NAN, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_pool_output() {
+        // Two channels over a 2x2 feature map
+        let map = [1.0, 2.0, 3.0, 6.0, -1.0, -2.0, -3.0, -4.0];
+        assert_eq!(
+            pool_output(&map, &[2, 2, 2], PoolingMode::Mean),
+            vec![3.0, -2.5]
+        );
+        assert_eq!(
+            pool_output(&map, &[2, 2, 2], PoolingMode::Max),
+            vec![6.0, -1.0]
+        );
+
+        // Already pooled outputs pass through unchanged
+        assert_eq!(pool_output(&map, &[8], PoolingMode::Max), map.to_vec());
+        assert_eq!(
+            pool_output(&map, &[8, 1, 1], PoolingMode::Max),
+            map.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_batch_embeddings_match_single() {
+        let frame_output = |i: usize, len: usize| -> Vec<f32> {
+            (0..len)
+                .map(|j| ((i * 7 + j * 3) % 11) as f32 - 5.0)
+                .collect()
+        };
+
+        // Pre-pooled, pre-pooled with unit spatial dims, and feature maps
+        for dims in [vec![4], vec![4, 1, 1], vec![3, 2, 2]] {
+            let len = dims.iter().product::<i64>() as usize;
+            let batch_shape: Vec<i64> = std::iter::once(3).chain(dims.iter().copied()).collect();
+            let single_shape: Vec<i64> = std::iter::once(1).chain(dims.iter().copied()).collect();
+            let batch_data: Vec<f32> = (0..3).flat_map(|i| frame_output(i, len)).collect();
+
+            for pooling in [PoolingMode::Mean, PoolingMode::Max] {
+                let batched = batch_embeddings(&batch_shape, &batch_data, pooling).unwrap();
+                assert_eq!(batched.len(), 3);
+                for (i, embedding) in batched.iter().enumerate() {
+                    let single =
+                        batch_embeddings(&single_shape, &frame_output(i, len), pooling).unwrap();
+                    assert_eq!(&single[0], embedding, "dims {:?}, frame {}", dims, i);
+                }
+            }
+        }
+
+        // Outputs that don't fill their shape are rejected, not misaligned
+        assert!(batch_embeddings(&[3, 4], &[0.0; 8], PoolingMode::Mean).is_err());
+        assert!(batch_embeddings(&[0, 4], &[], PoolingMode::Mean).is_err());
+    }
+
+    #[test]
+    fn test_is_allocation_failure() {
+        let error = |message: &str| Error::Embedding(message.to_string());
+        assert!(is_allocation_failure(&error(
+            "CUDA failure 2: out of memory ; GPU=0"
+        )));
+        assert!(is_allocation_failure(&error(
+            "Failed to allocate memory for requested buffer of size 822083584"
+        )));
+        assert!(!is_allocation_failure(&error(
+            "Got invalid dimensions for input: data"
+        )));
+    }
 
     #[test]
     fn test_cosine_similarity_identical() {
@@ -225,6 +788,33 @@ mod tests {
         assert!(cosine_similarity(&v1, &v2).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_cosine_similarity_matches_scalar() {
+        let a = normalize_vector(&(0..2048).map(|i| (i as f32).sin()).collect::<Vec<_>>());
+        let b = normalize_vector(&(0..2048).map(|i| (i as f32).cos()).collect::<Vec<_>>());
+        assert!((cosine_similarity(&a, &b) - cosine_similarity_scalar(&a, &b)).abs() < 1e-5);
+        // Mismatched lengths compare the shared prefix, like `zip`
+        assert!(
+            (cosine_similarity(&a, &a[..2]) - cosine_similarity_scalar(&a, &a[..2])).abs() < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_model_input_size() {
+        assert_eq!(model_input_size(&[1, 3, 384, 384]).unwrap(), Some(384));
+        assert_eq!(model_input_size(&[-1, 3, -1, -1]).unwrap(), None);
+        assert_eq!(model_input_size(&[1, 2048]).unwrap(), None);
+        assert!(model_input_size(&[1, 3, 224, 256]).is_err());
+    }
+
+    #[test]
+    fn test_pairwise_distances() {
+        let vectors = [vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0]];
+        assert_eq!(pairwise_distances(&vectors), vec![1.0, 0.0, 1.0]);
+        assert!(pairwise_distances::<Vec<f32>>(&[]).is_empty());
+        assert!(pairwise_distances(&[vec![1.0]]).is_empty());
+    }
+
     #[test]
     fn test_cosine_similarity_opposite() {
         let v1 = vec![1.0, 0.0, 0.0];
@@ -232,6 +822,197 @@ mod tests {
         assert!((cosine_similarity(&v1, &v2) + 1.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_gray_world_balance() {
+        // Warm cast: strong red, weak blue
+        let mut data: Vec<u8> = [200u8, 100, 50].repeat(4);
+        gray_world_balance(&mut data);
+
+        for pixel in data.chunks_exact(3) {
+            assert!(pixel.iter().all(|&v| (v as i32 - 117).abs() <= 1));
+        }
+    }
+
+    #[test]
+    fn test_frame_to_tensor_resizes_once() {
+        // A frame already at the input size reaches the model untouched
+        let size = 8u32;
+        let data: Vec<u8> = (0..size * size * 3).map(|i| (i * 7 % 256) as u8).collect();
+        let frame = Frame {
+            index: 0,
+            timestamp_seconds: crate::timestamp::Timestamp::ZERO,
+            data: data.clone(),
+            width: size,
+            height: size,
+            data16: None,
+        };
+        let tensor = frame_to_tensor(
+            &frame,
+            size,
+            PreprocessMode::Stretch,
+            &[],
+            false,
+            false,
+            &ChannelStats::IMAGENET,
+        )
+        .unwrap();
+
+        let ChannelStats { mean, std } = ChannelStats::IMAGENET;
+        for y in 0..size as usize {
+            for x in 0..size as usize {
+                for c in 0..3 {
+                    let value = data[(y * size as usize + x) * 3 + c] as f32 / 255.0;
+                    assert_eq!(tensor[[0, c, y, x]], (value - mean[c]) / std[c]);
+                }
+            }
+        }
+
+        // Larger frames are scaled straight to the input size
+        let large = Frame {
+            data: vec![128; 32 * 16 * 3],
+            width: 32,
+            height: 16,
+            ..frame
+        };
+        let tensor = frame_to_tensor(
+            &large,
+            size,
+            PreprocessMode::Letterbox,
+            &[],
+            false,
+            true,
+            &ChannelStats::IMAGENET,
+        )
+        .unwrap();
+        assert_eq!(tensor.shape(), &[1, 3, 8, 8]);
+    }
+
+    #[test]
+    fn test_masks_follow_decode_scale() {
+        // The right half of a 16x8 frame is masked; --decode-scale 8 decodes
+        // it at 8x4
+        let mask: Region = "8,0,8,8".parse().unwrap();
+        let frame = Frame {
+            index: 0,
+            timestamp_seconds: crate::timestamp::Timestamp::ZERO,
+            data: vec![255; 8 * 4 * 3],
+            width: 8,
+            height: 4,
+            data16: None,
+        };
+
+        let masks = frame_masks(&[mask], Some((16, 8)), &frame);
+        assert_eq!(masks, vec!["4,0,4,4".parse().unwrap()]);
+        assert_eq!(frame_masks(&[mask], Some((8, 4)), &frame), vec![mask]);
+
+        let tensor = frame_to_tensor(
+            &frame,
+            8,
+            PreprocessMode::Stretch,
+            &masks,
+            false,
+            false,
+            &ChannelStats::IMAGENET,
+        )
+        .unwrap();
+        let ChannelStats { mean, std } = ChannelStats::IMAGENET;
+        let blank = -mean[0] / std[0];
+        assert!(tensor[[0, 0, 3, 3]] > blank);
+        assert_eq!(tensor[[0, 0, 3, 4]], blank);
+        assert_eq!(tensor[[0, 0, 0, 7]], blank);
+    }
+
+    #[test]
+    fn test_draft_scale_matches_full_resolution() {
+        // A smooth 1024x576 frame, and the same frame as --quality draft
+        // decodes it
+        let full = image::RgbImage::from_fn(1024, 576, |x, y| {
+            image::Rgb([(x / 4) as u8, (y * 255 / 575) as u8, 128])
+        });
+        let (width, height) = (DRAFT_ANALYSIS_LONG_EDGE, DRAFT_ANALYSIS_LONG_EDGE * 9 / 16);
+        let small =
+            image::imageops::resize(&full, width, height, image::imageops::FilterType::Triangle);
+        let tensor = |image: image::RgbImage| {
+            let frame = Frame {
+                index: 0,
+                timestamp_seconds: crate::timestamp::Timestamp::ZERO,
+                width: image.width(),
+                height: image.height(),
+                data: image.into_raw(),
+                data16: None,
+            };
+            frame_to_tensor(
+                &frame,
+                224,
+                PreprocessMode::Stretch,
+                &[],
+                false,
+                true,
+                &ChannelStats::IMAGENET,
+            )
+            .unwrap()
+        };
+
+        let before = tensor(full);
+        let after = tensor(small);
+        let largest = before
+            .iter()
+            .zip(after.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        // About two grayscale levels after normalization
+        assert!(largest < 0.05, "largest difference {}", largest);
+    }
+
+    #[test]
+    fn test_channel_stats() {
+        // Red alternates 0 and 255, green is flat, blue is always 51
+        let frame = Frame {
+            index: 0,
+            timestamp_seconds: crate::timestamp::Timestamp::ZERO,
+            data: [0u8, 128, 51, 255, 128, 51].repeat(2),
+            width: 2,
+            height: 2,
+            data16: None,
+        };
+        let stats = channel_stats(&[frame]);
+
+        assert!((stats.mean[0] - 0.5).abs() < 1e-6);
+        assert!((stats.std[0] - 0.5).abs() < 1e-6);
+        assert!((stats.mean[2] - 0.2).abs() < 1e-6);
+        // Flat channels are floored at one gray level
+        assert_eq!(stats.std[1], 1.0 / 255.0);
+
+        assert_eq!(channel_stats(&[]), ChannelStats::IMAGENET);
+    }
+
+    #[test]
+    fn test_fit_square() {
+        // 4x2 image: left half red, right half blue
+        let img = image::RgbImage::from_fn(4, 2, |x, _| {
+            if x < 2 {
+                image::Rgb([255, 0, 0])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        });
+
+        let stretched = fit_square(img.clone(), PreprocessMode::Stretch);
+        assert_eq!(stretched.dimensions(), (4, 2));
+
+        let letterboxed = fit_square(img.clone(), PreprocessMode::Letterbox);
+        assert_eq!(letterboxed.dimensions(), (4, 4));
+        assert_eq!(letterboxed.get_pixel(0, 0), &image::Rgb([0, 0, 0]));
+        assert_eq!(letterboxed.get_pixel(0, 1), &image::Rgb([255, 0, 0]));
+        assert_eq!(letterboxed.get_pixel(3, 2), &image::Rgb([0, 0, 255]));
+        assert_eq!(letterboxed.get_pixel(3, 3), &image::Rgb([0, 0, 0]));
+
+        let cropped = fit_square(img, PreprocessMode::CenterCrop);
+        assert_eq!(cropped.dimensions(), (2, 2));
+        assert_eq!(cropped.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+        assert_eq!(cropped.get_pixel(1, 0), &image::Rgb([0, 0, 255]));
+    }
+
     #[test]
     fn test_normalize_vector() {
         let v = vec![3.0, 4.0];
@@ -239,4 +1020,56 @@ mod tests {
         let norm: f32 = normalized.iter().map(|x| x * x).sum::<f32>().sqrt();
         assert!((norm - 1.0).abs() < 1e-6);
     }
+
+    /// A one-node model that average-pools a `(batch, 3, 8, 8)` input to
+    /// `(batch, 3, 1, 1)`, so an embedding is the frame's normalized mean
+    /// color.
+    const POOL_MODEL: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/global_average_pool.onnx"
+    );
+
+    #[test]
+    #[ignore = "needs the ONNX Runtime library"]
+    fn test_batch_matches_single_embeddings() {
+        let mut model = EmbeddingModel::new(POOL_MODEL, QualityPreset::Balanced, 1)
+            .unwrap()
+            .with_max_batch(Some(2));
+        assert_eq!(model.input_size, 8);
+        model.verify().unwrap();
+
+        // Five flat 8x8 frames of different colors, embedded in batches of
+        // two, three and one
+        let frames: Vec<Frame> = (0..5u8)
+            .map(|i| Frame {
+                index: i as usize,
+                timestamp_seconds: crate::timestamp::Timestamp::ZERO,
+                data: [i * 50, 100, 200 - i * 30].repeat(64),
+                width: 8,
+                height: 8,
+                data16: None,
+            })
+            .collect();
+        let batched = model
+            .compute_embeddings_batch::<fn(usize, usize)>(&frames, None)
+            .unwrap();
+        assert_eq!(batched.len(), frames.len());
+
+        let ChannelStats { mean, std } = ChannelStats::IMAGENET;
+        for (frame, batched) in frames.iter().zip(&batched) {
+            let single = model.compute_embedding(frame).unwrap();
+            assert_eq!(single.index(), batched.index());
+            for (a, b) in single.embedding.iter().zip(&batched.embedding) {
+                assert!((a - b).abs() < 1e-6, "{} vs {}", a, b);
+            }
+
+            let pooled: Vec<f32> = (0..3)
+                .map(|c| (frame.data[c] as f32 / 255.0 - mean[c]) / std[c])
+                .collect();
+            let expected = normalize_vector(&pooled);
+            for (a, b) in single.embedding.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-5, "{} vs {}", a, b);
+            }
+        }
+    }
 }
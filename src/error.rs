@@ -26,6 +26,18 @@ pub enum Error {
     #[error("Failed to decode video '{path}': {reason}")]
     VideoDecode { path: PathBuf, reason: String },
 
+    /// Video codec excluded by `--require-codec` or `--reject-codec`.
+    #[error("Codec '{codec}' of '{path}' is not allowed: {reason}")]
+    CodecRejected {
+        path: PathBuf,
+        codec: String,
+        reason: String,
+    },
+
+    /// Processing finished without a single still.
+    #[error("No stills were extracted from '{0}': no frames could be sampled or embedded. Pass --allow-empty to accept an empty result")]
+    NoStillsExtracted(PathBuf),
+
     /// Error with video capture.
     #[error("Video capture error: {0}")]
     #[allow(dead_code)]
@@ -39,6 +51,14 @@ pub enum Error {
     #[error("Model load error: {0}")]
     ModelLoad(String),
 
+    /// Error reading the config file.
+    #[error("Config error: {0}")]
+    Config(String),
+
+    /// Available memory dropped below `--min-free-mem` while buffering frames.
+    #[error("Available memory fell to {available_mib} MiB, below --min-free-mem {min_mib} MiB")]
+    LowMemory { available_mib: u64, min_mib: u64 },
+
     /// Error writing output.
     #[error("Output error: {0}")]
     Output(String),
@@ -0,0 +1,153 @@
+//! Structured pipeline events, appended to a JSON Lines log as they happen.
+
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::timestamp::Timestamp;
+
+/// A pipeline milestone.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    VideoOpened {
+        path: &'a Path,
+        width: u32,
+        height: u32,
+        fps: f64,
+        frame_count: u32,
+        duration_seconds: f64,
+    },
+    /// Running total of frames sampled so far.
+    FramesExtracted {
+        count: usize,
+    },
+    /// A segment kept for output, after `--every-nth-segment` and the other
+    /// thinning options; `segment_index` matches its stills' metadata.
+    SegmentFinalized {
+        segment_index: usize,
+        start_timestamp_seconds: Timestamp,
+        end_timestamp_seconds: Timestamp,
+        frame_count: usize,
+    },
+    FrameWritten {
+        filename: &'a str,
+        frame_index: usize,
+        segment_index: usize,
+        timestamp_seconds: Timestamp,
+    },
+    Finished {
+        frames_extracted: usize,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    elapsed_seconds: f64,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// Appends one JSON object per event to a file, or does nothing when
+/// disabled.
+///
+/// Each line is flushed as soon as it is written so the log can be tailed.
+pub struct EventLog {
+    file: Option<Mutex<LineWriter<File>>>,
+    started: Instant,
+}
+
+impl EventLog {
+    /// An event log that discards every event.
+    pub fn disabled() -> Self {
+        Self {
+            file: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                Error::Output(format!(
+                    "Failed to open event log '{}': {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        Ok(Self {
+            file: Some(Mutex::new(LineWriter::new(file))),
+            started: Instant::now(),
+        })
+    }
+
+    /// Append `event`, stamped with the seconds since the log was opened.
+    pub fn emit(&self, event: Event) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let record = Record {
+            elapsed_seconds: self.started.elapsed().as_secs_f64(),
+            event: &event,
+        };
+        let line = serde_json::to_string(&record)
+            .map_err(|e| Error::Output(format!("Failed to encode event: {}", e)))?;
+        let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_appends_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        // Earlier runs' events are kept
+        std::fs::write(&path, "{\"event\":\"finished\",\"frames_extracted\":1}\n").unwrap();
+
+        let log = EventLog::open(&path).unwrap();
+        log.emit(Event::FramesExtracted { count: 12 }).unwrap();
+        log.emit(Event::FrameWritten {
+            filename: "001.jpg",
+            frame_index: 30,
+            segment_index: 0,
+            timestamp_seconds: Timestamp::from_seconds(1.0),
+        })
+        .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["event"], "frames_extracted");
+        assert_eq!(lines[1]["count"], 12);
+        assert_eq!(lines[2]["event"], "frame_written");
+        assert_eq!(lines[2]["filename"], "001.jpg");
+        assert_eq!(lines[2]["timestamp_seconds"], 1.0);
+        assert!(lines[2]["elapsed_seconds"].is_f64());
+    }
+
+    #[test]
+    fn test_disabled_log_discards_events() {
+        let log = EventLog::disabled();
+        log.emit(Event::Finished {
+            frames_extracted: 0,
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+//! Filmstrips: several frames of one segment composited side by side.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{imageops, RgbImage};
+
+use crate::error::{Error, Result};
+use crate::output::OutputMetadata;
+use crate::segmentation::SemanticSegment;
+use crate::video::{Frame, VideoLoader};
+
+/// First and last frame index of segment `segment` in a previous run.
+pub fn segment_frame_range(
+    metadata: &OutputMetadata,
+    segment: usize,
+    fps: f64,
+) -> Result<(usize, usize)> {
+    let frame = metadata
+        .frames
+        .iter()
+        .find(|f| f.segment_index == segment)
+        .ok_or_else(|| {
+            let segments = metadata
+                .frames
+                .last()
+                .map(|f| f.segment_index + 1)
+                .unwrap_or(0);
+            Error::Config(format!(
+                "segment {} not found; the run has {} segments",
+                segment, segments
+            ))
+        })?;
+
+    let last_frame = (metadata.video_frame_count as usize).saturating_sub(1);
+    let start = frame.start_frame_timestamp.as_frames(fps).min(last_frame);
+    let end = frame
+        .end_frame_timestamp
+        .as_frames(fps)
+        .clamp(start, last_frame);
+    Ok((start, end))
+}
+
+/// `count` frame indices spread evenly over `start..=end`, both ends included.
+pub fn filmstrip_indices(start: usize, end: usize, count: usize) -> Vec<usize> {
+    let span = end - start;
+    let count = count.clamp(1, span + 1);
+    if count == 1 {
+        return vec![start];
+    }
+
+    (0..count)
+        .map(|i| start + (i * span + (count - 1) / 2) / (count - 1))
+        .collect()
+}
+
+/// Place frames left to right in one image.
+///
+/// Frames come from the same video, so they share a size.
+pub fn compose(frames: &[Frame]) -> Result<RgbImage> {
+    let Some(first) = frames.first() else {
+        return Err(Error::Output("no frames to compose".to_string()));
+    };
+    let (width, height) = (first.width, first.height);
+    let mut strip = RgbImage::new(width * frames.len() as u32, height);
+
+    for (i, frame) in frames.iter().enumerate() {
+        let tile =
+            RgbImage::from_raw(frame.width, frame.height, frame.data.clone()).ok_or_else(|| {
+                Error::Output(format!(
+                    "Failed to create image buffer for frame {}",
+                    frame.index
+                ))
+            })?;
+        imageops::replace(&mut strip, &tile, i as i64 * width as i64, 0);
+    }
+
+    Ok(strip)
+}
+
+/// Decode `count` frames across a segment of a previous run and save them
+/// as one wide image at `output`.
+pub fn write_filmstrip(
+    video_path: &Path,
+    metadata: &OutputMetadata,
+    segment: usize,
+    count: usize,
+    output: &Path,
+) -> Result<()> {
+    let mut video = VideoLoader::new(video_path)?;
+    let fps = video.metadata()?.fps;
+    let (start, end) = segment_frame_range(metadata, segment, fps)?;
+
+    let frames = filmstrip_indices(start, end, count)
+        .into_iter()
+        .map(|index| video.get_frame_at(index))
+        .collect::<Result<Vec<_>>>()?;
+
+    compose(&frames)?.save(output).map_err(|e| {
+        Error::Output(format!(
+            "Failed to write filmstrip '{}': {}",
+            output.display(),
+            e
+        ))
+    })
+}
+
+/// The two adjacent frames at each segment boundary: the last frame before
+/// the next segment and that segment's first frame.
+///
+/// `segments` should be the detected segments, before any are dropped by
+/// thinning; otherwise a pair's first frame belongs to a dropped segment
+/// rather than the one before it in the list.
+pub fn boundary_frame_pairs(segments: &[SemanticSegment]) -> Vec<(usize, usize)> {
+    segments
+        .windows(2)
+        .map(|pair| {
+            let after = pair[1].start_frame_idx;
+            (after.saturating_sub(1), after)
+        })
+        .collect()
+}
+
+/// Decode each `(before, after)` frame pair from [`boundary_frame_pairs`]
+/// and save it as a diptych, `boundary_NNN.jpg` in `dir`, numbered from 1.
+///
+/// Returns the paths written.
+pub fn write_boundaries(
+    video: &mut VideoLoader,
+    pairs: &[(usize, usize)],
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).map_err(|e| {
+        Error::Output(format!(
+            "Failed to create boundary directory '{}': {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    let mut written = Vec::new();
+    for (i, &(before, after)) in pairs.iter().enumerate() {
+        let frames = [video.get_frame_at(before)?, video.get_frame_at(after)?];
+        let path = dir.join(format!("boundary_{:03}.jpg", i + 1));
+        compose(&frames)?.save(&path).map_err(|e| {
+            Error::Output(format!(
+                "Failed to write boundary '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segmentation::{every_nth_segment, uniform_segments};
+    use crate::timestamp::Timestamp;
+
+    #[test]
+    fn test_filmstrip_indices() {
+        assert_eq!(filmstrip_indices(30, 90, 5), vec![30, 45, 60, 75, 90]);
+        assert_eq!(filmstrip_indices(10, 12, 5), vec![10, 11, 12]);
+        assert_eq!(filmstrip_indices(7, 7, 4), vec![7]);
+        assert_eq!(filmstrip_indices(0, 100, 1), vec![0]);
+    }
+
+    #[test]
+    fn test_boundary_frame_pairs() {
+        let frames = [0, 30, 90]
+            .into_iter()
+            .map(|index| Frame {
+                index,
+                timestamp_seconds: Timestamp::from_frame(index, 30.0),
+                data: Vec::new(),
+                width: 0,
+                height: 0,
+                data16: None,
+            })
+            .collect();
+        let segments = uniform_segments(frames, 4.0);
+
+        // Frames between samples are included, so the pairs are adjacent
+        assert_eq!(boundary_frame_pairs(&segments), vec![(29, 30), (89, 90)]);
+        assert!(boundary_frame_pairs(&segments[..1]).is_empty());
+
+        // After thinning to every other segment, the one pair left would
+        // start in the dropped segment, not the kept one before it
+        let thinned = every_nth_segment(segments, 2);
+        assert_eq!(thinned[0].end_frame_idx, 29);
+        assert_eq!(boundary_frame_pairs(&thinned), vec![(89, 90)]);
+    }
+
+    #[test]
+    fn test_compose_places_frames_side_by_side() {
+        let frame = |index: usize, value: u8| Frame {
+            index,
+            timestamp_seconds: Timestamp::ZERO,
+            data: vec![value; 2 * 2 * 3],
+            width: 2,
+            height: 2,
+            data16: None,
+        };
+        let strip = compose(&[frame(0, 10), frame(1, 200)]).unwrap();
+
+        assert_eq!(strip.dimensions(), (4, 2));
+        assert_eq!(strip.get_pixel(1, 1).0, [10, 10, 10]);
+        assert_eq!(strip.get_pixel(2, 0).0, [200, 200, 200]);
+        assert!(compose(&[]).is_err());
+    }
+}
@@ -1,23 +1,44 @@
 //! SceneSplit: Extract semantically distinct still images from video.
 
+mod compare;
 mod config;
+mod edl;
 mod embeddings;
 mod error;
+mod events;
+mod filmstrip;
 mod model;
 mod output;
 mod processor;
+mod profile;
+mod resegment;
 mod segmentation;
+mod timeline;
+mod timestamp;
+mod tune;
 mod video;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::Duration;
 
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
 
-use config::{DetailLevel, QualityPreset};
-use error::Error;
-use model::ensure_model;
-use processor::SceneSplitProcessor;
+use config::{
+    ConfigFile, DetailLevel, JpegSubsampling, MaxFramesStrategy, PoolingMode, PosterStrategy,
+    PreprocessMode, QualityPreset, SampleStrategy, Verbosity, VideoSidecar, DEFAULT_ANCHOR_ALPHA,
+    DEFAULT_DOWNLOAD_TIMEOUT_SECS, DEFAULT_ORT_OPT_LEVEL,
+};
+use embeddings::capped_batch_size;
+use error::{Error, SUPPORTED_FORMATS};
+use model::{download_urls, ensure_model, model_cache_path};
+use output::{EmbeddingDump, InlineFrame, OutputWriter};
+use processor::{ProgressEvent, SceneSplitProcessor};
+use segmentation::SemanticSegmenter;
+use video::Region;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -26,52 +47,693 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// SceneSplit analyzes a video file and extracts representative frames that
 /// capture meaningful visual changes. Output is written to a directory
 /// containing numbered images and a metadata.json file.
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Serialize)]
 #[command(name = "scenesplit")]
 #[command(version = VERSION)]
 #[command(about = "Extract semantically distinct still images from video")]
 #[command(
-    long_about = "SceneSplit analyzes a video file and extracts representative frames that\ncapture meaningful visual changes. Output is written to a directory\ncontaining numbered images and a metadata.json file.\n\nOn first run, the embedding model (~100MB) is downloaded and cached."
+    long_about = "SceneSplit analyzes a video file and extracts representative frames that\ncapture meaningful visual changes. Output is written to a directory\ncontaining numbered images and a metadata.json file.\n\nOn first run, the embedding model (~100MB) is downloaded and cached.\n\nDefaults can be set in a scenesplit.toml file (read from the current\ndirectory, or from --config). Precedence, highest first: command-line\nflags, config file, built-in defaults."
 )]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
+
     /// Path to the input video file
-    #[arg(value_name = "VIDEO")]
-    input_video: PathBuf,
+    #[arg(value_name = "VIDEO", required = true)]
+    #[serde(skip)]
+    input_video: Option<PathBuf>,
 
     /// Path to a custom ONNX model file (default: auto-download ResNet50)
     #[arg(long, short = 'm', value_name = "MODEL")]
     model: Option<PathBuf>,
 
+    /// Give up on a model download mirror after this many seconds without
+    /// connecting or receiving data
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_DOWNLOAD_TIMEOUT_SECS, value_parser = parse_positive)]
+    download_timeout: f64,
+
+    /// Download the model from this URL instead of the built-in one (also SCENESPLIT_MODEL_URL)
+    #[arg(long, value_name = "URL", conflicts_with = "model")]
+    model_url: Option<String>,
+
     /// Granularity level: 'key' (minimal), 'summary' (moderate), 'all' (comprehensive)
     #[arg(long, short = 'd', default_value = "summary", value_enum)]
     detail: DetailLevel,
 
-    /// Processing quality: 'fast', 'balanced', or 'best'
+    /// Processing quality: 'draft', 'fast', 'balanced', or 'best'
     #[arg(long, short = 'q', default_value = "balanced", value_enum)]
     quality: QualityPreset,
 
+    /// Segment anchor EMA weight (0.0-1.0); lower adapts faster to gradual change
+    #[arg(long, value_name = "ALPHA", default_value_t = DEFAULT_ANCHOR_ALPHA, value_parser = parse_unit_interval)]
+    anchor_alpha: f32,
+
+    /// Minimum similarity drop (0.0-1.0) for a boundary to count as a cut
+    #[arg(long, value_name = "DELTA", default_value_t = 0.0, value_parser = parse_unit_interval)]
+    min_drop: f32,
+
+    /// Similarity to the segment anchor (0.0-1.0) below which a frame may
+    /// start a new segment (default: from the detail level)
+    #[arg(long, value_name = "THRESHOLD", value_parser = parse_unit_interval)]
+    split_threshold: Option<f32>,
+
+    /// Similarity to the old anchor (0.0-1.0) at which the frame after a
+    /// boundary cancels it (default: split threshold + 0.05)
+    #[arg(long, value_name = "THRESHOLD", value_parser = parse_unit_interval)]
+    merge_threshold: Option<f32>,
+
+    /// Apply gray-world white balance before analysis (output stills are unchanged)
+    #[arg(long)]
+    normalize_color: bool,
+
+    /// Normalize model input with channel statistics measured on a sample of the video instead of ImageNet's
+    #[arg(long, conflicts_with = "uniform")]
+    calibrate: bool,
+
+    /// Start sampling at this frame instead of frame 0 (skips leaders and slates)
+    #[arg(long, value_name = "FRAMES", default_value_t = 0)]
+    sample_offset: usize,
+
+    /// Analyze about K evenly spaced frames in total, overriding the quality preset's stride
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["uniform", "keyframes_only"])]
+    frame_budget: Option<u32>,
+
+    /// Quick approximate run: analyze about this many seconds, in 1s windows spread over the video
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive, conflicts_with_all = ["uniform", "incremental", "keyframes_only", "frame_budget"])]
+    preview_sample: Option<f64>,
+
+    /// Leave the first SECONDS (e.g. a branded intro) out of analysis and output
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive)]
+    trim_start: Option<f64>,
+
+    /// Leave the last SECONDS (e.g. an outro) out of analysis and output
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive)]
+    trim_end: Option<f64>,
+
+    /// How non-square frames are fitted to the model's square input
+    #[arg(long, value_name = "MODE", default_value = "stretch", value_enum)]
+    preprocess: PreprocessMode,
+
+    /// How a model's spatial feature map output is pooled into an embedding
+    #[arg(long, value_name = "MODE", default_value = "mean", value_enum)]
+    pooling: PoolingMode,
+
+    /// Embed only the video's keyframes (I-frames) for a fast first pass; same as --sample-strategy keyframe
+    #[arg(long, conflicts_with_all = ["uniform", "incremental", "max_frames_in_memory", "min_free_mem", "sample_offset", "sample_strategy"])]
+    keyframes_only: bool,
+
+    /// Which frame of each stride to analyze: the first, a random one, or only keyframes (I-frames)
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        default_value = "uniform",
+        value_enum,
+        conflicts_with = "uniform"
+    )]
+    sample_strategy: SampleStrategy,
+
+    /// Seed for --sample-strategy random; the same seed samples the same frames
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    sample_seed: u64,
+
+    /// Pinpoint each cut to the exact frame by bisecting between sampled frames
+    #[arg(long, conflicts_with_all = ["uniform", "incremental"])]
+    bisect_cuts: bool,
+
+    /// Always include the first sampled frame and the final frame as stills
+    #[arg(long, conflicts_with_all = ["uniform", "incremental", "every_nth_segment"])]
+    include_endpoints: bool,
+
+    /// Keep only every Kth segment (0, K, 2K, ...) after segmentation;
+    /// --max-frames is applied afterwards and has the final say
+    #[arg(long, value_name = "K", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "incremental")]
+    every_nth_segment: u32,
+
+    /// Drop stills whose SSIM to the previous kept still is at least this (0.0-1.0)
+    #[arg(long, value_name = "THRESHOLD", value_parser = parse_unit_interval, conflicts_with = "incremental")]
+    ssim_threshold: Option<f32>,
+
+    /// Keep output stills at least this many seconds apart, dropping the ones in between
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive, conflicts_with = "incremental")]
+    min_output_spacing: Option<f64>,
+
+    /// Write at most N stills, chosen by --max-frames-strategy
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "incremental")]
+    max_frames: Option<u32>,
+
+    /// Which stills --max-frames keeps: the most distinct, or the most evenly spread in time
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        default_value = "distinct",
+        value_enum,
+        requires = "max_frames"
+    )]
+    max_frames_strategy: MaxFramesStrategy,
+
+    /// Drop frames that fail to embed (with a warning) instead of aborting
+    #[arg(long)]
+    skip_bad_frames: bool,
+
+    /// Skip the test inference that checks the model runs before decoding starts
+    #[arg(long)]
+    no_verify_model: bool,
+
+    /// ONNX Runtime graph optimization level; 0 disables it for models that misbehave
+    #[arg(long, value_name = "0-3", default_value_t = DEFAULT_ORT_OPT_LEVEL, value_parser = clap::value_parser!(u8).range(0..=3))]
+    ort_opt_level: u8,
+
+    /// Embed at most N frames per model run, for GPUs with little memory
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_batch: Option<u32>,
+
+    /// Square model input size in pixels (default: read from the model, else 224)
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    input_size: Option<u32>,
+
+    /// Succeed with a warning when no stills are extracted instead of failing
+    #[arg(long)]
+    allow_empty: bool,
+
+    /// Write a sha256sum-compatible SHA256SUMS manifest of all outputs
+    #[arg(long)]
+    checksums: bool,
+
+    /// Record a SHA-256 of each still's decoded pixels in the metadata, stable across encoders
+    #[arg(long)]
+    pixel_hash: bool,
+
+    /// Record each still's N dominant colors (k-means) as hex strings in the metadata
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=16))]
+    palette: Option<u32>,
+
+    /// JPEG chroma subsampling: '444' keeps colored text sharp, '420' gives the smallest files
+    #[arg(long, value_name = "MODE", default_value = "444")]
+    jpeg_subsampling: JpegSubsampling,
+
+    /// Save stills of 16-bit sources as 16-bit PNG instead of 8-bit JPEG
+    #[arg(long)]
+    preserve_depth: bool,
+
+    /// Analyze and save only this region of each frame, in source pixels
+    #[arg(long, value_name = "X,Y,W,H")]
+    crop: Option<Region>,
+
+    /// Blend the fields of interlaced video before analysis and output to remove combing
+    #[arg(long)]
+    deinterlace: bool,
+
+    /// Decode frames shrunk to this long edge for analysis, re-reading only
+    /// the chosen stills at full resolution (e.g. 512)
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    decode_scale: Option<u32>,
+
+    /// Blank this region before embedding so overlays like scoreboards don't
+    /// drive scene detection; stills keep it (repeatable)
+    #[arg(long = "mask", value_name = "X,Y,W,H")]
+    masks: Vec<Region>,
+
+    /// Write the N×N similarity matrix of all sampled frames (.csv, else raw float32)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["uniform", "incremental"])]
+    dump_similarity_matrix: Option<PathBuf>,
+
+    /// Write each sampled frame's embedding as JSON, for `scenesplit segment`
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["uniform", "incremental"])]
+    dump_embeddings: Option<PathBuf>,
+
+    /// Write per-frame anchor similarity, boundaries and anchor norm (.csv, else JSON)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["uniform", "incremental"])]
+    debug_anchor: Option<PathBuf>,
+
+    /// Write the segment cuts as a CMX3600 EDL for Premiere, DaVinci Resolve and other NLEs
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["incremental", "json_stdout"])]
+    export_edl: Option<PathBuf>,
+
+    /// Write segments as a JSON array of {start, end, thumb, label} for timeline widgets
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["incremental", "json_stdout"])]
+    timeline_json: Option<PathBuf>,
+
+    /// Also record super-scenes split at COARSE with scenes split at FINE nested inside, in the metadata
+    #[arg(long, value_name = "COARSE,FINE", value_parser = parse_threshold_pair, conflicts_with_all = ["uniform", "incremental", "json_stdout"])]
+    hierarchical: Option<(f32, f32)>,
+
+    /// Save the two frames either side of each segment boundary as a diptych in DIR
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["incremental", "json_stdout"])]
+    export_boundaries: Option<PathBuf>,
+
+    /// Write the stills as the pages of one TIFF at this path instead of separate image files
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["incremental", "json_stdout", "bucket_by", "checksums", "clips"])]
+    multipage_tiff: Option<PathBuf>,
+
+    /// Save the still that best represents the whole video to this path
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["uniform", "incremental", "json_stdout"])]
+    poster: Option<PathBuf>,
+
+    /// Which representative --poster saves: nearest the mean, of the longest segment, or the first
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        default_value = "centroid",
+        value_enum,
+        requires = "poster"
+    )]
+    poster_strategy: PosterStrategy,
+
+    /// Also save a clip this many seconds long around each still (NNNN.mp4)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive, conflicts_with_all = ["incremental", "json_stdout"])]
+    clips: Option<f64>,
+
+    /// Append a JSON line per pipeline milestone to this file while processing
+    #[arg(long, value_name = "PATH")]
+    event_log: Option<PathBuf>,
+
+    /// Print per-operation timings (decode, preprocess, inference, ...) at the end
+    #[arg(long)]
+    profile: bool,
+
+    /// Only process videos with one of these codec FourCCs (e.g. avc1)
+    #[arg(long, value_name = "FOURCC", value_delimiter = ',')]
+    require_codec: Vec<String>,
+
+    /// Refuse videos with any of these codec FourCCs (e.g. hvc1,hev1)
+    #[arg(long, value_name = "FOURCC", value_delimiter = ',')]
+    reject_codec: Vec<String>,
+
+    /// Number of maximally distinct stills to keep per segment
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    per_segment: u32,
+
+    /// Pick each segment's representative at this percentile of its timeline
+    #[arg(long, value_name = "0-100", default_value_t = 50, value_parser = clap::value_parser!(u8).range(0..=100))]
+    representative_percentile: u8,
+
+    /// Replace a representative whose sharpness (variance of the Laplacian)
+    /// is below this with the nearest sharp enough frame of its segment
+    #[arg(long, value_name = "THRESHOLD", value_parser = parse_positive)]
+    reject_blur: Option<f64>,
+
+    /// Write each still as soon as its segment is finalized
+    #[arg(long)]
+    incremental: bool,
+
+    /// Stream frames through embedding when a video would sample more than N frames
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_frames_in_memory: Option<u32>,
+
+    /// Stream frames through embedding whenever buffering them would leave less than MIB of memory available
+    #[arg(long, value_name = "MIB", value_parser = clap::value_parser!(u64).range(1..))]
+    min_free_mem: Option<u64>,
+
+    /// Reuse or continue a previous run in the output directory if settings match
+    #[arg(long)]
+    resume: bool,
+
+    /// Extract one frame every N seconds, skipping semantic analysis
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive)]
+    uniform: Option<f64>,
+
+    /// Warn when stills are smaller than this many pixels on their short side
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..))]
+    min_output_dimension: Option<u32>,
+
+    /// Treat output quality warnings (such as --min-output-dimension) as errors
+    #[arg(long)]
+    strict: bool,
+
+    /// Group stills into subdirectories spanning this many minutes (00-10/, 10-20/, ...)
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u32).range(1..))]
+    bucket_by: Option<u32>,
+
+    /// Print stills as JSON with base64-encoded images instead of writing files
+    #[arg(long, conflicts_with_all = ["output", "incremental", "resume", "bucket_by", "checksums", "pixel_hash", "palette", "max_frames_in_memory", "min_free_mem"])]
+    json_stdout: bool,
+
+    /// With --json-stdout, print an object with the run summary and the stills instead of just the stills
+    #[arg(long, requires = "json_stdout")]
+    json_summary: bool,
+
     /// Output directory (default: ./scenesplit_output/)
     #[arg(long, short = 'o', value_name = "DIR")]
     output: Option<PathBuf>,
 
-    /// Suppress progress output
+    /// Suppress progress output (warnings are still shown)
     #[arg(long, short = 's')]
     quiet: bool,
+
+    /// Suppress all output except errors
+    #[arg(long, conflicts_with = "quiet")]
+    silent: bool,
+
+    /// Config file with default options (default: ./scenesplit.toml if present)
+    #[arg(long, short = 'c', value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Print the effective settings as JSON, after merging the config file and sidecar, and exit
+    #[arg(long)]
+    #[serde(skip)]
+    print_config: bool,
 }
 
-fn progress_callback(stage: &str, current: usize, total: usize) {
-    if total > 0 {
-        println!("{}... ({}/{})", stage, current, total);
+/// Utility subcommands.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two extraction runs and report added, removed and shifted frames
+    ///
+    /// Exits with 0 when the runs match, 1 when they differ, and 2 on error.
+    Compare {
+        /// First output directory (or metadata.json)
+        #[arg(value_name = "DIR_A")]
+        before: PathBuf,
+
+        /// Second output directory (or metadata.json)
+        #[arg(value_name = "DIR_B")]
+        after: PathBuf,
+
+        /// Maximum timestamp distance for two frames to be the same still
+        #[arg(long, value_name = "SECONDS", default_value_t = 0.5)]
+        tolerance: f64,
+    },
+
+    /// Embed a video once, then try similarity thresholds interactively
+    Tune {
+        /// Input video file path
+        #[arg(value_name = "VIDEO")]
+        video: PathBuf,
+
+        /// Custom ONNX model path (default: auto-download)
+        #[arg(long, short = 'm', value_name = "PATH")]
+        model: Option<PathBuf>,
+
+        /// Detail level supplying the minimum segment length
+        #[arg(long, short = 'd', default_value = "summary", value_enum)]
+        detail: DetailLevel,
+
+        /// Processing quality preset
+        #[arg(long, short = 'q', default_value = "balanced", value_enum)]
+        quality: QualityPreset,
+    },
+
+    /// Segment embeddings written by --dump-embeddings, without the video or model
+    Segment {
+        /// Embeddings file written by --dump-embeddings
+        #[arg(value_name = "EMBEDDINGS")]
+        embeddings: PathBuf,
+
+        /// Detail level for granularity control
+        #[arg(long, short = 'd', default_value = "summary", value_enum)]
+        detail: DetailLevel,
+
+        /// Override the detail level's split threshold (0.0-1.0)
+        #[arg(long, value_name = "THRESHOLD", value_parser = parse_unit_interval)]
+        split_threshold: Option<f32>,
+
+        /// Override the detail level's merge threshold (0.0-1.0)
+        #[arg(long, value_name = "THRESHOLD", value_parser = parse_unit_interval)]
+        merge_threshold: Option<f32>,
+
+        /// Directory to write metadata.json to (default: print it)
+        #[arg(long, short = 'o', value_name = "DIR")]
+        output: Option<PathBuf>,
+    },
+
+    /// Print version, supported formats, model cache and OpenCV video backends
+    Info,
+
+    /// Composite frames sampled across one segment of a previous run into a wide image
+    Filmstrip {
+        /// Input video file path
+        #[arg(value_name = "VIDEO")]
+        video: PathBuf,
+
+        /// Output directory (or metadata.json) of a run over VIDEO
+        #[arg(long, value_name = "DIR")]
+        run: PathBuf,
+
+        /// Index of the segment to sample
+        #[arg(long, value_name = "INDEX")]
+        segment: usize,
+
+        /// Number of frames in the strip
+        #[arg(long, value_name = "K", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+        frames: u32,
+
+        /// Image to write (format from the extension)
+        #[arg(
+            long,
+            short = 'o',
+            value_name = "PATH",
+            default_value = "filmstrip.jpg"
+        )]
+        output: PathBuf,
+    },
+}
+
+impl Args {
+    /// Fill in options from a config file that were not given on the command line.
+    fn apply_config_file(&mut self, file: ConfigFile, matches: &ArgMatches) {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(model) = file.model.filter(|_| !from_cli("model")) {
+            self.model = Some(model);
+        }
+        if let Some(detail) = file.detail.filter(|_| !from_cli("detail")) {
+            self.detail = detail;
+        }
+        if let Some(quality) = file.quality.filter(|_| !from_cli("quality")) {
+            self.quality = quality;
+        }
+        if let Some(output) = file.output.filter(|_| !from_cli("output")) {
+            self.output = Some(output);
+        }
+        if let Some(alpha) = file.anchor_alpha.filter(|_| !from_cli("anchor_alpha")) {
+            self.anchor_alpha = alpha;
+        }
+        if let Some(min_drop) = file.min_drop.filter(|_| !from_cli("min_drop")) {
+            self.min_drop = min_drop;
+        }
+        if let Some(threshold) = file
+            .split_threshold
+            .filter(|_| !from_cli("split_threshold"))
+        {
+            self.split_threshold = Some(threshold);
+        }
+        if let Some(threshold) = file
+            .merge_threshold
+            .filter(|_| !from_cli("merge_threshold"))
+        {
+            self.merge_threshold = Some(threshold);
+        }
+        if let Some(normalize) = file
+            .normalize_color
+            .filter(|_| !from_cli("normalize_color"))
+        {
+            self.normalize_color = normalize;
+        }
+        if let Some(offset) = file.sample_offset.filter(|_| !from_cli("sample_offset")) {
+            self.sample_offset = offset;
+        }
+        if let Some(codecs) = file.require_codec.filter(|_| !from_cli("require_codec")) {
+            self.require_codec = codecs;
+        }
+        if let Some(codecs) = file.reject_codec.filter(|_| !from_cli("reject_codec")) {
+            self.reject_codec = codecs;
+        }
+        if let Some(preprocess) = file.preprocess.filter(|_| !from_cli("preprocess")) {
+            self.preprocess = preprocess;
+        }
+        // Verbosity flags are only taken from the file when neither was given
+        if !from_cli("quiet") && !from_cli("silent") {
+            self.quiet = file.quiet.unwrap_or(self.quiet);
+            self.silent = file.silent.unwrap_or(self.silent);
+        }
+    }
+
+    /// Apply a video's sidecar overrides to options not given on the command line.
+    fn apply_sidecar(&mut self, sidecar: VideoSidecar, matches: &ArgMatches) {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(model) = sidecar.model.filter(|_| !from_cli("model")) {
+            self.model = Some(model);
+        }
+        if let Some(detail) = sidecar.detail.filter(|_| !from_cli("detail")) {
+            self.detail = detail;
+        }
+    }
+
+    /// Resolve the console verbosity from the quiet/silent flags.
+    fn verbosity(&self) -> Verbosity {
+        if self.silent {
+            Verbosity::Silent
+        } else if self.quiet {
+            Verbosity::Quiet
+        } else {
+            Verbosity::Normal
+        }
+    }
+
+    /// Reject options that `--keyframes-only` conflicts with when keyframe
+    /// sampling is chosen through `--sample-strategy keyframe` instead,
+    /// which clap can't express as a conflict on one value.
+    fn check_keyframe_strategy(&self) -> Result<(), Error> {
+        if self.sample_strategy != SampleStrategy::Keyframe {
+            return Ok(());
+        }
+        let conflicts = [
+            ("--incremental", self.incremental),
+            (
+                "--max-frames-in-memory",
+                self.max_frames_in_memory.is_some(),
+            ),
+            ("--min-free-mem", self.min_free_mem.is_some()),
+            ("--sample-offset", self.sample_offset != 0),
+            ("--frame-budget", self.frame_budget.is_some()),
+            ("--preview-sample", self.preview_sample.is_some()),
+        ];
+        match conflicts.iter().find(|(_, set)| *set) {
+            Some((flag, _)) => Err(Error::Config(format!(
+                "--sample-strategy keyframe cannot be used with {}",
+                flag
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse a float that must lie within [0.0, 1.0].
+fn parse_unit_interval(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
     } else {
-        println!("{}...", stage);
+        Err(format!("{} is not in range 0.0-1.0", value))
+    }
+}
+
+/// Parse `COARSE,FINE` split thresholds, each within [0.0, 1.0], with the
+/// coarse one no higher than the fine one.
+fn parse_threshold_pair(s: &str) -> Result<(f32, f32), String> {
+    let (coarse, fine) = s
+        .split_once(',')
+        .ok_or_else(|| format!("'{}' is not COARSE,FINE", s))?;
+    let (coarse, fine) = (
+        parse_unit_interval(coarse.trim())?,
+        parse_unit_interval(fine.trim())?,
+    );
+    if coarse > fine {
+        return Err(format!(
+            "coarse threshold {} is above fine threshold {}",
+            coarse, fine
+        ));
     }
+    Ok((coarse, fine))
 }
 
-fn run(args: Args) -> Result<(), Error> {
+/// Parse a strictly positive number of seconds.
+fn parse_positive(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("{} must be greater than 0", value))
+    }
+}
+
+/// Shows pipeline progress on the terminal: a bar while frames are
+/// extracted and embedded, with segment and file counts beside it.
+///
+/// The bar is cleared when the printer is dropped.
+struct ProgressPrinter {
+    bar: ProgressBar,
+    segments: usize,
+    files: usize,
+}
+
+impl ProgressPrinter {
+    fn new() -> Self {
+        let bar = ProgressBar::new(0);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{prefix:>20} [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {msg}")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+        Self {
+            bar,
+            segments: 0,
+            files: 0,
+        }
+    }
+
+    fn update(&mut self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::VideoLoaded { metadata } => self.bar.println(format!(
+                "Loaded video: {}x{}, {:.1}s at {:.2} fps",
+                metadata.width, metadata.height, metadata.duration_seconds, metadata.fps
+            )),
+            ProgressEvent::FramesExtracted { done, total } => {
+                self.advance("Extracting frames", done, total)
+            }
+            ProgressEvent::EmbeddingProgress { done, total } => {
+                self.advance("Computing embeddings", done, total)
+            }
+            ProgressEvent::SegmentFinalized { index } => {
+                self.segments = self.segments.max(index + 1);
+                self.bar.set_message(format!("{} segments", self.segments));
+            }
+            ProgressEvent::OutputWritten { path } => {
+                self.files += 1;
+                self.bar.set_message(format!(
+                    "{} segments, {} files written (last: {})",
+                    self.segments,
+                    self.files,
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                ));
+            }
+        }
+    }
+
+    fn advance(&self, stage: &'static str, done: usize, total: usize) {
+        self.bar.set_prefix(stage);
+        self.bar.set_length(total.max(done) as u64);
+        self.bar.set_position(done as u64);
+    }
+}
+
+impl Drop for ProgressPrinter {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+fn run(mut args: Args, matches: &ArgMatches) -> Result<(), Error> {
+    // Merge defaults from the config file (CLI flags take precedence)
+    let file = match &args.config {
+        Some(path) => ConfigFile::load(path)?,
+        None => ConfigFile::discover()?,
+    };
+    args.apply_config_file(file, matches);
+    let verbosity = match args.verbosity() {
+        // Stdout is reserved for the JSON payload
+        Verbosity::Normal if args.json_stdout => Verbosity::Quiet,
+        verbosity => verbosity,
+    };
+
     // Validate input file exists
-    if !args.input_video.exists() {
-        return Err(Error::VideoNotFound(args.input_video));
+    let input_video = args
+        .input_video
+        .take()
+        .expect("clap requires VIDEO without a subcommand");
+    if !input_video.exists() {
+        return Err(Error::VideoNotFound(input_video));
     }
+    args.apply_sidecar(VideoSidecar::discover(&input_video)?, matches);
+    args.check_keyframe_strategy()?;
+    // Captured before the processor takes ownership of the options
+    let printed_config = args
+        .print_config
+        .then(|| print_config_base(&args, &input_video));
 
     // Get model path (user-provided or auto-download)
     let model_path = match args.model {
@@ -84,43 +746,423 @@ fn run(args: Args) -> Result<(), Error> {
             }
             path
         }
-        None => ensure_model(args.quiet)?,
+        // Uniform sampling never loads the model, and printing the config
+        // shouldn't download it
+        None if args.uniform.is_some() || args.print_config => {
+            model_cache_path().unwrap_or_default()
+        }
+        None => ensure_model(
+            verbosity,
+            Duration::from_secs_f64(args.download_timeout),
+            args.model_url.as_deref(),
+        )?,
     };
 
-    if !args.quiet {
+    if verbosity.shows_progress() && !args.print_config {
         println!("SceneSplit v{}", VERSION);
-        println!("Input: {}", args.input_video.display());
-        println!("Model: {}", model_path.display());
-        println!("Detail: {:?}", args.detail);
+        println!("Input: {}", input_video.display());
+        match args.uniform {
+            Some(interval) => println!("Mode: uniform (every {}s)", interval),
+            None => {
+                println!("Model: {}", model_path.display());
+                println!("Detail: {:?}", args.detail);
+                println!(
+                    "Batch size: {}",
+                    capped_batch_size(args.quality, args.max_batch.map(|n| n as usize))
+                );
+                if let Some(seconds) = args.preview_sample {
+                    println!("Preview: about {}s of the video", seconds);
+                }
+            }
+        }
         println!("Quality: {:?}", args.quality);
         println!();
     }
 
-    let processor = SceneSplitProcessor::new(args.detail, args.quality, args.output, model_path);
+    if args.profile {
+        profile::enable();
+    }
 
-    let callback = if args.quiet {
-        None
-    } else {
-        Some(progress_callback)
+    let processor = SceneSplitProcessor::new(args.detail, args.quality, args.output, model_path)
+        .with_anchor_alpha(args.anchor_alpha)
+        .with_min_drop(args.min_drop)
+        .with_split_threshold(args.split_threshold)
+        .with_merge_threshold(args.merge_threshold)
+        .with_uniform_interval(args.uniform)
+        .with_color_normalization(args.normalize_color)
+        .with_calibration(args.calibrate)
+        .with_preprocess(args.preprocess)
+        .with_pooling(args.pooling)
+        .with_sample_offset(args.sample_offset)
+        .with_frame_budget(args.frame_budget.map(|k| k as usize))
+        .with_preview_sample(args.preview_sample)
+        .with_trim(args.trim_start.unwrap_or(0.0), args.trim_end.unwrap_or(0.0))
+        .with_bisect_cuts(args.bisect_cuts)
+        .with_include_endpoints(args.include_endpoints)
+        .with_sample_strategy(if args.keyframes_only {
+            SampleStrategy::Keyframe
+        } else {
+            args.sample_strategy
+        })
+        .with_sample_seed(args.sample_seed)
+        .with_every_nth_segment(args.every_nth_segment as usize)
+        .with_ssim_threshold(args.ssim_threshold.map(f64::from))
+        .with_min_output_spacing(args.min_output_spacing)
+        .with_max_frames(args.max_frames.map(|n| n as usize))
+        .with_max_frames_strategy(args.max_frames_strategy)
+        .with_bucket_minutes(args.bucket_by)
+        .with_skip_bad_frames(args.skip_bad_frames)
+        .with_verify_model(!args.no_verify_model)
+        .with_ort_opt_level(args.ort_opt_level)
+        .with_max_batch(args.max_batch.map(|n| n as usize))
+        .with_preserve_depth(args.preserve_depth)
+        .with_crop(args.crop)
+        .with_deinterlace(args.deinterlace)
+        .with_decode_scale(args.decode_scale)
+        .with_masks(args.masks)
+        .with_checksums(args.checksums)
+        .with_pixel_hashes(args.pixel_hash)
+        .with_palette(args.palette.map(|n| n as usize))
+        .with_jpeg_subsampling(args.jpeg_subsampling)
+        .with_allow_empty(args.allow_empty)
+        .with_input_size(args.input_size)
+        .with_similarity_matrix(args.dump_similarity_matrix)
+        .with_embedding_dump(args.dump_embeddings)
+        .with_anchor_trace(args.debug_anchor)
+        .with_edl(args.export_edl)
+        .with_poster(args.poster)
+        .with_multipage_tiff(args.multipage_tiff)
+        .with_poster_strategy(args.poster_strategy)
+        .with_timeline(args.timeline_json)
+        .with_boundary_export(args.export_boundaries)
+        .with_hierarchy(args.hierarchical)
+        .with_clips(args.clips)
+        .with_event_log(args.event_log)
+        .with_codec_filter(args.require_codec, args.reject_codec)
+        .with_incremental_output(args.incremental)
+        .with_max_frames_in_memory(args.max_frames_in_memory.map(|n| n as usize))
+        .with_min_free_memory(args.min_free_mem.map(|mib| mib << 20))
+        .with_per_segment(args.per_segment as usize)
+        .with_representative_percentile(args.representative_percentile)
+        .with_reject_blur(args.reject_blur)
+        .with_resume(args.resume)
+        .with_min_output_dimension(args.min_output_dimension)
+        .with_strict(args.strict)
+        .with_verbosity(verbosity);
+    if let Some(config) = printed_config {
+        return print_config(config, processor, &input_video);
+    }
+    let processor = processor
+        .resolve_calibration(&input_video)?
+        .resolve_detail(&input_video)?;
+
+    let mut printer = verbosity.shows_progress().then(ProgressPrinter::new);
+    let on_progress = |event| {
+        if let Some(printer) = printer.as_mut() {
+            printer.update(event);
+        }
     };
 
-    let result = processor.process(&args.input_video, callback)?;
+    if args.json_stdout {
+        let (summary, stills) = processor.process_in_memory(&input_video, on_progress)?;
+        let stills: Vec<InlineFrame> = stills
+            .into_iter()
+            .map(|(metadata, bytes)| InlineFrame::new(metadata, &bytes))
+            .collect();
+        let json = if args.json_summary {
+            serde_json::to_string(&serde_json::json!({
+                "summary": summary,
+                "stills": stills,
+            }))
+        } else {
+            serde_json::to_string(&stills)
+        }
+        .map_err(|e| Error::Output(format!("Failed to serialize stills: {}", e)))?;
+        println!("{}", json);
+        return print_profile(args.profile);
+    }
+
+    let result = processor.process(&input_video, on_progress)?;
+    drop(printer);
 
-    if !args.quiet {
+    if verbosity.shows_progress() {
         println!();
         println!("{}", "=".repeat(50));
-        println!("Extracted {} stills", result.frames_extracted);
+        if result.resumed {
+            println!("Previous run is complete; nothing to do");
+        }
+        let summary = result.summary();
+        println!(
+            "Processed {} frames of {:.1}s of video",
+            summary.total_frames_processed, summary.video_duration_seconds
+        );
+        println!(
+            "Detected {} segments (average {:.1}s)",
+            summary.segments_detected, summary.average_segment_seconds
+        );
+        if summary.near_duplicates_removed > 0 {
+            println!(
+                "Removed {} near-duplicate stills",
+                summary.near_duplicates_removed
+            );
+        }
+        println!("Extracted {} stills", summary.frames_extracted);
         println!("Output written to {}/", result.output_dir.display());
+        println!("Metadata: {}", result.metadata_path.display());
+        println!("Settings: {}", summary.settings);
         println!("{}", "=".repeat(50));
     }
 
+    print_profile(args.profile)
+}
+
+/// Options as `--print-config` shows them, before the model, detail level
+/// and thresholds are resolved.
+fn print_config_base(args: &Args, input_video: &Path) -> serde_json::Value {
+    let mut config = serde_json::json!({ "input_video": input_video });
+    if let (Some(config), serde_json::Value::Object(options)) =
+        (config.as_object_mut(), serde_json::json!(args))
+    {
+        config.extend(options);
+    }
+    config
+}
+
+/// Print the settings the run would use as JSON: the options with the model
+/// path, detail level and thresholds resolved, plus where the default model
+/// is cached and downloaded from.
+///
+/// `--detail auto` is resolved by probing the video only when the model is
+/// already on disk; otherwise it's printed as `auto`.
+fn print_config(
+    mut config: serde_json::Value,
+    processor: SceneSplitProcessor,
+    input_video: &Path,
+) -> Result<(), Error> {
+    let processor = processor.with_verbosity(Verbosity::Silent);
+    let settings = processor.effective_settings();
+    let settings = if settings.model.exists() {
+        processor.resolve_detail(input_video)?.effective_settings()
+    } else {
+        settings
+    };
+    if let (Some(config), serde_json::Value::Object(resolved)) =
+        (config.as_object_mut(), serde_json::json!(settings))
+    {
+        config.extend(resolved);
+        config.insert(
+            "model_cache".to_string(),
+            serde_json::json!(model_cache_path()),
+        );
+        let urls = download_urls(config["model_url"].as_str());
+        config.insert("model_urls".to_string(), serde_json::json!(urls));
+    }
+    let json = serde_json::to_string_pretty(&config)
+        .map_err(|e| Error::Output(format!("Failed to encode settings: {}", e)))?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Print the timing summary to stderr when `--profile` is on.
+fn print_profile(enabled: bool) -> Result<(), Error> {
+    if enabled {
+        eprintln!();
+        profile::write_report(std::io::stderr().lock(), &profile::summary())?;
+    }
+    Ok(())
+}
+
+/// Compare two runs, returning whether they match.
+fn run_compare(before: &Path, after: &Path, tolerance: f64) -> Result<bool, Error> {
+    let report = compare::compare_metadata(
+        &compare::load_metadata(before)?,
+        &compare::load_metadata(after)?,
+        tolerance,
+    );
+    println!("{}", report);
+    Ok(report.is_match())
+}
+
+/// Embed a video and run the interactive threshold prompt.
+fn run_tune(
+    video: &Path,
+    model: Option<PathBuf>,
+    detail: DetailLevel,
+    quality: QualityPreset,
+) -> Result<(), Error> {
+    let model_path = match model {
+        Some(path) => path,
+        None => ensure_model(
+            Verbosity::Normal,
+            Duration::from_secs_f64(DEFAULT_DOWNLOAD_TIMEOUT_SECS),
+            None,
+        )?,
+    };
+
+    let processor =
+        SceneSplitProcessor::new(detail, quality, None, model_path).resolve_detail(video)?;
+    eprintln!("Computing embeddings...");
+    let frames = processor.embed_frames(video)?;
+
+    tune::tune_loop(
+        &frames,
+        processor.detail(),
+        std::io::stdin().lock(),
+        std::io::stdout(),
+    )
+}
+
+/// Segment a dump of embeddings and print or write the run metadata.
+fn run_segment(
+    embeddings: &Path,
+    detail: DetailLevel,
+    split_threshold: Option<f32>,
+    merge_threshold: Option<f32>,
+    output: Option<PathBuf>,
+) -> Result<(), Error> {
+    let dump = EmbeddingDump::read(embeddings)?;
+    let detail = resegment::resolve_detail(&dump, detail);
+    let mut segmenter = SemanticSegmenter::new(detail);
+    if let Some(threshold) = split_threshold {
+        segmenter = segmenter.with_similarity_threshold(threshold);
+    }
+    if let Some(threshold) = merge_threshold {
+        segmenter = segmenter.with_merge_threshold(threshold);
+    }
+    let detail_label = format!("{:?}", detail).to_lowercase();
+    let metadata = resegment::resegment(&dump, &segmenter, &detail_label);
+
+    match output {
+        Some(dir) => {
+            let writer = OutputWriter::new(Some(dir));
+            writer.prepare()?;
+            let path = writer.write_metadata(&metadata)?;
+            println!(
+                "{} segments written to {}",
+                metadata.extracted_frames,
+                path.display()
+            );
+        }
+        None => {
+            let json = serde_json::to_string_pretty(&metadata)
+                .map_err(|e| Error::Output(format!("Failed to encode metadata: {}", e)))?;
+            println!("{}", json);
+        }
+    }
+    Ok(())
+}
+
+/// Write a filmstrip of one segment of a previous run.
+fn run_filmstrip(
+    video: &Path,
+    run: &Path,
+    segment: usize,
+    frames: usize,
+    output: &Path,
+) -> Result<(), Error> {
+    let metadata = compare::load_metadata(run)?;
+    filmstrip::write_filmstrip(video, &metadata, segment, frames, output)?;
+    println!("Filmstrip written to {}", output.display());
+    Ok(())
+}
+
+/// Print diagnostics for support requests.
+fn run_info() -> Result<(), Error> {
+    println!("scenesplit {}", env!("CARGO_PKG_VERSION"));
+    println!("Supported formats: {}", SUPPORTED_FORMATS.join(", "));
+    match model_cache_path() {
+        Some(path) if path.is_file() => println!("Model: {} (cached)", path.display()),
+        Some(path) => println!("Model: {} (not downloaded yet)", path.display()),
+        None => println!("Model: no cache directory available"),
+    }
+    println!();
+    println!("OpenCV build:");
+    println!("{}", video::opencv_video_io()?);
     Ok(())
 }
 
 fn main() -> ExitCode {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    match args.command.take() {
+        Some(Command::Compare {
+            before,
+            after,
+            tolerance,
+        }) => {
+            return match run_compare(&before, &after, tolerance) {
+                Ok(true) => ExitCode::SUCCESS,
+                Ok(false) => ExitCode::from(1),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    ExitCode::from(2)
+                }
+            };
+        }
+        Some(Command::Tune {
+            video,
+            model,
+            detail,
+            quality,
+        }) => {
+            return match run_tune(&video, model, detail, quality) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Some(Command::Segment {
+            embeddings,
+            detail,
+            split_threshold,
+            merge_threshold,
+            output,
+        }) => {
+            return match run_segment(
+                &embeddings,
+                detail,
+                split_threshold,
+                merge_threshold,
+                output,
+            ) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Some(Command::Info) => {
+            return match run_info() {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Some(Command::Filmstrip {
+            video,
+            run,
+            segment,
+            frames,
+            output,
+        }) => {
+            return match run_filmstrip(&video, &run, segment, frames as usize, &output) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        None => {}
+    }
 
-    match run(args) {
+    match run(args, &matches) {
         Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
             eprintln!("Error: {}", e);
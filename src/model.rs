@@ -1,20 +1,55 @@
 //! Model download and caching module.
 
 use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
 
+use crate::config::Verbosity;
 use crate::error::{Error, Result};
 
-/// ResNet50 ONNX model from ONNX Model Zoo (feature extraction variant).
-/// This is the standard ResNet50 with the final classification layer removed.
-const MODEL_URL: &str = "https://github.com/onnx/models/raw/main/validated/vision/classification/resnet/model/resnet50-v2-7.onnx";
+/// Revision of github.com/onnx/models that [`builtin_model_url`] reads
+/// `resnet50-v2-7.onnx` (ResNet-50 v2, opset 7) from.
+///
+/// Currently the `main` branch, which can change upstream. Pin it to a
+/// commit SHA and set [`EXPECTED_SHA256`] to the file's digest at that
+/// commit in the same change.
+const MODEL_REVISION: &str = "main";
+
+/// Path of the model file within github.com/onnx/models at [`MODEL_REVISION`].
+const MODEL_REPO_PATH: &str = "validated/vision/classification/resnet/model/resnet50-v2-7.onnx";
+
+/// SHA-256 of the model at [`MODEL_REVISION`], checked after every download
+/// of the built-in model (mirrors included). `None` skips the check.
+///
+/// Downloads from `--model-url` or [`MODEL_URL_ENV`] aren't checked: they
+/// may serve a different model on purpose.
+const EXPECTED_SHA256: Option<&str> = None;
+
+/// Environment variable replacing [`builtin_model_url`]; `--model-url` wins over it.
+const MODEL_URL_ENV: &str = "SCENESPLIT_MODEL_URL";
+
+/// Environment variable with extra mirror URLs (comma or whitespace separated).
+const MODEL_URLS_ENV: &str = "SCENESPLIT_MODEL_URLS";
 
 /// Expected model filename.
 const MODEL_FILENAME: &str = "resnet50-v2-7.onnx";
 
+/// ResNet50 ONNX model from ONNX Model Zoo (feature extraction variant).
+/// This is the standard ResNet50 with the final classification layer removed.
+///
+/// Runs that must reproduce byte-identical embeddings can override it with
+/// `--model-url` or [`MODEL_URL_ENV`] pointing at a copy they control.
+fn builtin_model_url() -> String {
+    format!(
+        "https://github.com/onnx/models/raw/{}/{}",
+        MODEL_REVISION, MODEL_REPO_PATH
+    )
+}
+
 /// Get the cache directory for SceneSplit.
 fn cache_dir() -> Result<PathBuf> {
     let base = dirs::cache_dir()
@@ -24,8 +59,15 @@ fn cache_dir() -> Result<PathBuf> {
 
 /// Get the path to the cached model, downloading if necessary.
 ///
-/// Returns the path to the ONNX model file, downloading it on first run.
-pub fn ensure_model(quiet: bool) -> Result<PathBuf> {
+/// Returns the path to the ONNX model file, downloading it on first run
+/// from `model_url`, else `SCENESPLIT_MODEL_URL`, else the built-in URL.
+/// Each download attempt gives up after `timeout` without connecting or
+/// without receiving data.
+pub fn ensure_model(
+    verbosity: Verbosity,
+    timeout: Duration,
+    model_url: Option<&str>,
+) -> Result<PathBuf> {
     let cache = cache_dir()?;
     let model_path = cache.join(MODEL_FILENAME);
 
@@ -37,27 +79,130 @@ pub fn ensure_model(quiet: bool) -> Result<PathBuf> {
     fs::create_dir_all(&cache)
         .map_err(|e| Error::ModelLoad(format!("Failed to create cache directory: {}", e)))?;
 
-    if !quiet {
+    if verbosity.shows_warnings() {
         eprintln!("Downloading model (one-time, ~100MB)...");
     }
 
-    download_model(MODEL_URL, &model_path, quiet)?;
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .build();
+    let urls = download_urls(model_url);
+    let expected_sha256 = EXPECTED_SHA256.filter(|_| !overrides_builtin_url(model_url));
+    let mut failures = Vec::new();
+    for (attempt, url) in urls.iter().enumerate() {
+        let downloaded =
+            download_model(&agent, url, &model_path, timeout, verbosity).and_then(|()| {
+                match expected_sha256 {
+                    Some(expected) => verify_sha256(&model_path, expected, url),
+                    None => Ok(()),
+                }
+            });
+        match downloaded {
+            Ok(()) => {
+                if verbosity.shows_warnings() && urls.len() > 1 {
+                    eprintln!("Downloaded model from {}", url);
+                }
+                return Ok(model_path);
+            }
+            Err(e) => {
+                let failure = format!("attempt {}/{}: {}", attempt + 1, urls.len(), e);
+                if verbosity.shows_warnings() && attempt + 1 < urls.len() {
+                    eprintln!("Warning: {}; trying next mirror", failure);
+                }
+                failures.push(failure);
+            }
+        }
+    }
+
+    Err(Error::ModelLoad(format!(
+        "All {} model download locations failed ({}). Set {} to a reachable mirror \
+         or use --model to point at a local copy.",
+        urls.len(),
+        failures.join("; "),
+        MODEL_URLS_ENV
+    )))
+}
+
+/// Download locations for the model, in the order they are tried, after
+/// applying `SCENESPLIT_MODEL_URL` and `SCENESPLIT_MODEL_URLS`.
+pub fn download_urls(model_url: Option<&str>) -> Vec<String> {
+    let env_url = std::env::var(MODEL_URL_ENV).ok();
+    let primary = model_url
+        .or(env_url.as_deref().filter(|url| !url.is_empty()))
+        .map(str::to_string)
+        .unwrap_or_else(builtin_model_url);
+    model_urls(&primary, std::env::var(MODEL_URLS_ENV).ok().as_deref())
+}
 
-    Ok(model_path)
+/// Whether `--model-url` or [`MODEL_URL_ENV`] replaces the built-in model.
+fn overrides_builtin_url(model_url: Option<&str>) -> bool {
+    model_url.is_some() || std::env::var(MODEL_URL_ENV).is_ok_and(|url| !url.is_empty())
+}
+
+/// Check a downloaded model against `expected` (hex SHA-256), deleting it
+/// on a mismatch so a bad copy is never used or cached.
+fn verify_sha256(path: &Path, expected: &str, url: &str) -> Result<()> {
+    let mut file = File::open(path)
+        .map_err(|e| Error::ModelLoad(format!("Failed to read downloaded model: {}", e)))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .map_err(|e| Error::ModelLoad(format!("Failed to read downloaded model: {}", e)))?;
+    let actual = format!("{:x}", hasher.finalize());
+    if actual.eq_ignore_ascii_case(expected) {
+        return Ok(());
+    }
+    let _ = fs::remove_file(path);
+    Err(Error::ModelLoad(format!(
+        "Model downloaded from {} has SHA-256 {}, expected {}; the file was deleted",
+        url, actual, expected
+    )))
+}
+
+/// Download locations in the order they are tried: mirrors from the
+/// environment first, then `primary`, without duplicates.
+fn model_urls(primary: &str, env: Option<&str>) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let mirrors = env
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|url| !url.is_empty());
+    for url in mirrors.chain([primary]) {
+        if !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
 }
 
 /// Download the model file with progress indication.
-fn download_model(url: &str, dest: &PathBuf, quiet: bool) -> Result<()> {
-    let response = ureq::get(url)
-        .call()
-        .map_err(|e| Error::ModelLoad(format!("Failed to download model: {}", e)))?;
+fn download_model(
+    agent: &ureq::Agent,
+    url: &str,
+    dest: &PathBuf,
+    timeout: Duration,
+    verbosity: Verbosity,
+) -> Result<()> {
+    let response = match agent.get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, _)) => return Err(http_status_error(url, status)),
+        Err(ureq::Error::Transport(e)) if is_timeout(&e) => {
+            return Err(Error::ModelLoad(format!(
+                "Timed out after {}s connecting to {}",
+                timeout.as_secs_f64(),
+                url
+            )))
+        }
+        Err(e) => return Err(Error::ModelLoad(format!("Failed to download model: {}", e))),
+    };
+    check_response(url, response.status(), response.header("Content-Type"))?;
 
     let total_size = response
         .header("Content-Length")
         .and_then(|s| s.parse::<u64>().ok())
         .unwrap_or(0);
 
-    let pb = if !quiet && total_size > 0 {
+    let pb = if verbosity.shows_progress() && total_size > 0 {
         let pb = ProgressBar::new(total_size);
         pb.set_style(
             ProgressStyle::default_bar()
@@ -80,14 +225,40 @@ fn download_model(url: &str, dest: &PathBuf, quiet: bool) -> Result<()> {
     let mut downloaded: u64 = 0;
 
     loop {
-        let bytes_read = reader
-            .read(&mut buffer)
-            .map_err(|e| Error::ModelLoad(format!("Failed to read response: {}", e)))?;
+        let bytes_read = match reader.read(&mut buffer) {
+            Ok(n) => n,
+            Err(e) => {
+                drop(file);
+                let _ = fs::remove_file(&temp_path);
+                if is_timeout(&e) {
+                    return Err(Error::ModelLoad(format!(
+                        "Timed out after {}s waiting for data from {} ({} bytes received)",
+                        timeout.as_secs_f64(),
+                        url,
+                        downloaded
+                    )));
+                }
+                return Err(Error::ModelLoad(format!(
+                    "Failed to read response from {}: {}",
+                    url, e
+                )));
+            }
+        };
 
         if bytes_read == 0 {
             break;
         }
 
+        // Servers without a Content-Type can still hand back an error page
+        if downloaded == 0 && looks_like_html(&buffer[..bytes_read]) {
+            drop(file);
+            let _ = fs::remove_file(&temp_path);
+            return Err(Error::ModelLoad(format!(
+                "Model download from {} returned an HTML page instead of an ONNX model",
+                url
+            )));
+        }
+
         file.write_all(&buffer[..bytes_read])
             .map_err(|e| Error::ModelLoad(format!("Failed to write to file: {}", e)))?;
 
@@ -102,19 +273,162 @@ fn download_model(url: &str, dest: &PathBuf, quiet: bool) -> Result<()> {
         pb.finish_with_message("Download complete");
     }
 
+    // A dropped connection can end the body early without an error
+    if total_size > 0 && downloaded != total_size {
+        drop(file);
+        let _ = fs::remove_file(&temp_path);
+        return Err(Error::ModelLoad(format!(
+            "Model download from {} was truncated ({} of {} bytes)",
+            url, downloaded, total_size
+        )));
+    }
+
     // Atomic rename
     fs::rename(&temp_path, dest)
         .map_err(|e| Error::ModelLoad(format!("Failed to move model to cache: {}", e)))?;
 
-    if !quiet {
+    if verbosity.shows_warnings() {
         eprintln!("Model cached at: {}", dest.display());
     }
 
     Ok(())
 }
 
+/// Whether an error, or anything it wraps, is an I/O timeout.
+fn is_timeout(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(e) = source {
+        if let Some(io_err) = e.downcast_ref::<io::Error>() {
+            if matches!(
+                io_err.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ) {
+                return true;
+            }
+            // io::Error::source skips the error it wraps
+            if let Some(inner) = io_err.get_ref() {
+                source = Some(inner);
+                continue;
+            }
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// Reject responses that cannot be a model file.
+///
+/// A moved or deleted upstream file yields a 404 or an HTML error page,
+/// which must not be cached as the model.
+fn check_response(url: &str, status: u16, content_type: Option<&str>) -> Result<()> {
+    if status != 200 {
+        return Err(http_status_error(url, status));
+    }
+
+    if let Some(content_type) = content_type {
+        let mime = content_type.to_ascii_lowercase();
+        if mime.starts_with("text/") || mime.contains("html") || mime.contains("json") {
+            return Err(Error::ModelLoad(format!(
+                "Model download from {} returned '{}' instead of a binary model file",
+                url, content_type
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Error for a model download that returned a non-200 status.
+fn http_status_error(url: &str, status: u16) -> Error {
+    Error::ModelLoad(format!(
+        "Model download from {} failed with HTTP {}. The file may have moved upstream; \
+         use --model to point at a local copy.",
+        url, status
+    ))
+}
+
+/// Heuristic check for an HTML document at the start of a response body.
+fn looks_like_html(bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(64)]).to_ascii_lowercase();
+    let text = text.trim_start();
+    text.starts_with("<!doctype") || text.starts_with("<html")
+}
+
 /// Get the path where the model would be cached (for display purposes).
-#[allow(dead_code)]
 pub fn model_cache_path() -> Option<PathBuf> {
     cache_dir().ok().map(|c| c.join(MODEL_FILENAME))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_response() {
+        let url = "https://example.com/model.onnx";
+        assert!(check_response(url, 200, Some("application/octet-stream")).is_ok());
+        assert!(check_response(url, 200, None).is_ok());
+        assert!(check_response(url, 404, Some("text/plain")).is_err());
+        assert!(check_response(url, 200, Some("text/html; charset=utf-8")).is_err());
+    }
+
+    #[test]
+    fn test_model_urls() {
+        let builtin = builtin_model_url();
+        assert_eq!(model_urls(&builtin, None), vec![builtin.clone()]);
+
+        let urls = model_urls(
+            &builtin,
+            Some("https://mirror.example/a.onnx, https://mirror.example/b.onnx"),
+        );
+        assert_eq!(urls.len(), 3);
+        assert_eq!(urls[0], "https://mirror.example/a.onnx");
+        assert_eq!(urls[2], builtin);
+
+        assert_eq!(model_urls(&builtin, Some(&builtin)), vec![builtin.clone()]);
+
+        // An overridden URL replaces the built-in one, after the mirrors
+        let pinned = "https://models.example/resnet50-v2-7@abc123.onnx";
+        assert_eq!(
+            model_urls(pinned, Some("https://mirror.example/a.onnx")),
+            vec![
+                "https://mirror.example/a.onnx".to_string(),
+                pinned.to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_timeout() {
+        let timed_out = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert!(is_timeout(&timed_out));
+        let wrapped = io::Error::other(timed_out);
+        assert!(is_timeout(&wrapped));
+        assert!(!is_timeout(&io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            "refused"
+        )));
+    }
+
+    #[test]
+    fn test_looks_like_html() {
+        assert!(looks_like_html(b"<!DOCTYPE html><html>"));
+        assert!(looks_like_html(b"\n  <html lang=\"en\">"));
+        assert!(!looks_like_html(&[0x08, 0x07, 0x12, 0x07]));
+    }
+
+    #[test]
+    fn test_verify_sha256_deletes_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+        fs::write(&path, b"model").unwrap();
+        let digest = format!("{:x}", Sha256::digest(b"model"));
+
+        verify_sha256(&path, &digest, "https://example.com/model.onnx").unwrap();
+        assert!(path.exists());
+
+        let err = verify_sha256(&path, &"0".repeat(64), "https://example.com/model.onnx");
+        assert!(err.unwrap_err().to_string().contains("SHA-256"));
+        assert!(!path.exists());
+    }
+}
@@ -1,29 +1,124 @@
 //! Output generation module for extracted frames and metadata.
 
 use std::fs::{self, File};
-use std::io::BufWriter;
+use std::io::{self, BufReader, BufWriter, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
-use image::{ImageBuffer, Rgb};
-use serde::Serialize;
+use base64::Engine;
+use image::{ImageBuffer, ImageOutputFormat, Rgb};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tiff::encoder::{colortype, TiffEncoder};
+use tiff::tags::Tag;
 
-use crate::config::{DEFAULT_OUTPUT_DIR, OUTPUT_IMAGE_FORMAT, OUTPUT_IMAGE_QUALITY};
+use crate::config::{
+    JpegSubsampling, PosterStrategy, CHECKSUMS_FILENAME, DEFAULT_OUTPUT_DIR,
+    ESTIMATED_JPEG_BYTES_PER_PIXEL, METADATA_FILENAME, OUTPUT_IMAGE_FORMAT, OUTPUT_IMAGE_QUALITY,
+};
+use crate::embeddings::{cosine_similarity, ChannelStats, EmbeddedFrame};
 use crate::error::{Error, Result};
-use crate::segmentation::SemanticSegment;
-use crate::video::VideoMetadata;
+use crate::profile;
+use crate::segmentation::{
+    poster_segment, AnchorSample, BoundaryKind, Relocation, SceneNode, SemanticSegment,
+};
+use crate::timestamp::Timestamp;
+use crate::video::{dominant_colors, Frame, VideoMetadata};
 
 /// Metadata for a single extracted frame.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FrameMetadata {
     pub filename: String,
     pub segment_index: usize,
     pub frame_index: usize,
-    pub timestamp_seconds: f64,
+    pub timestamp_seconds: Timestamp,
     pub timestamp_formatted: String,
+    pub start_frame_timestamp: Timestamp,
+    pub end_frame_timestamp: Timestamp,
+    pub duration_seconds: f64,
+    /// Variance of the Laplacian; low values indicate a blurry still.
+    #[serde(default)]
+    pub sharpness: f64,
+    /// Fraction of pixels not clipped to black or white.
+    #[serde(default)]
+    pub exposure: f64,
+    /// Present when this representative replaced the frame first selected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relocation: Option<Relocation>,
+    /// Whether the segment opened on a hard cut or a gradual transition;
+    /// absent for the first segment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boundary: Option<BoundaryKind>,
+    /// Short video clip around the segment's representative, with `--clips`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clip: Option<String>,
+    /// SHA-256 of the still's decoded pixels, with `--pixel-hash`; see
+    /// [`pixel_sha256`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pixel_sha256: Option<String>,
+    /// Dominant colors of the still as `#rrggbb`, most common first, with
+    /// `--palette`; see [`dominant_colors`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Vec<String>>,
+    /// Page of the `--multipage-tiff` file holding the still, counted from
+    /// 0; `filename` is then the TIFF's absolute path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
 }
 
-/// Complete metadata for an extraction run.
+impl FrameMetadata {
+    /// Metadata for `frame`, a still of `segment` saved as `filename`, with
+    /// sharpness and exposure left at 0.
+    pub fn new(segment: &SemanticSegment, frame: &Frame, filename: String) -> Self {
+        Self {
+            filename,
+            segment_index: segment.index,
+            frame_index: frame.index,
+            timestamp_seconds: frame.timestamp_seconds,
+            timestamp_formatted: frame.timestamp_seconds.to_string(),
+            start_frame_timestamp: segment.start_timestamp_seconds,
+            end_frame_timestamp: segment.end_timestamp_seconds,
+            duration_seconds: segment.duration_seconds(),
+            sharpness: 0.0,
+            exposure: 0.0,
+            relocation: segment
+                .relocation
+                .filter(|_| frame.index == segment.representative_frame.index()),
+            boundary: segment.boundary_kind,
+            clip: None,
+            pixel_sha256: None,
+            palette: None,
+            page: None,
+        }
+    }
+}
+
+/// A still's metadata with its encoded image bytes.
+pub type EncodedFrame = (FrameMetadata, Vec<u8>);
+
+/// A still returned inline instead of being written to disk.
 #[derive(Debug, Clone, Serialize)]
+pub struct InlineFrame {
+    #[serde(flatten)]
+    pub metadata: FrameMetadata,
+    /// Base64-encoded JPEG data.
+    pub image: String,
+}
+
+impl InlineFrame {
+    /// Wrap encoded image bytes for JSON output.
+    pub fn new(metadata: FrameMetadata, image: &[u8]) -> Self {
+        Self {
+            metadata,
+            image: base64::engine::general_purpose::STANDARD.encode(image),
+        }
+    }
+}
+
+/// Complete metadata for an extraction run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OutputMetadata {
     pub source_video: String,
     pub video_duration_seconds: f64,
@@ -31,22 +126,142 @@ pub struct OutputMetadata {
     pub extracted_frames: usize,
     pub detail_level: String,
     pub quality_preset: String,
+    /// Index of the first frame considered for sampling.
+    #[serde(default)]
+    pub sample_offset: usize,
+    /// Sampled frames left out because they failed to embed.
+    #[serde(default)]
+    pub skipped_frames: Vec<usize>,
+    /// Canonical description of the input and settings, used by `--resume`.
+    #[serde(default)]
+    pub settings_fingerprint: String,
+    /// False while an incremental run is still writing frames. Metadata
+    /// from before the field existed was only written by finished runs.
+    #[serde(default = "complete_default")]
+    pub complete: bool,
+    /// True when only a sample of the video was analyzed with
+    /// `--preview-sample`.
+    #[serde(default)]
+    pub preview: bool,
+    /// Seconds of intro left out with `--trim-start`.
+    #[serde(default)]
+    pub trim_start_seconds: f64,
+    /// Seconds of outro left out with `--trim-end`.
+    #[serde(default)]
+    pub trim_end_seconds: f64,
+    /// Frames that actually decoded, when fewer than `video_frame_count`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded_frame_count: Option<u32>,
+    /// Super-scenes with their scenes as children, with `--hierarchical`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenes: Option<Vec<SceneNode>>,
+    /// Channel statistics the model input was normalized with, with
+    /// `--calibrate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_stats: Option<ChannelStats>,
     pub frames: Vec<FrameMetadata>,
 }
 
+fn complete_default() -> bool {
+    true
+}
+
+impl OutputMetadata {
+    /// Build metadata for a run over the given video.
+    pub fn new(
+        video_metadata: &VideoMetadata,
+        frames: Vec<FrameMetadata>,
+        detail_level: &str,
+        quality_preset: &str,
+    ) -> Self {
+        Self {
+            source_video: video_metadata.path.to_string_lossy().to_string(),
+            video_duration_seconds: video_metadata.duration_seconds,
+            video_frame_count: video_metadata.frame_count,
+            extracted_frames: frames.len(),
+            detail_level: detail_level.to_string(),
+            quality_preset: quality_preset.to_string(),
+            sample_offset: 0,
+            skipped_frames: Vec::new(),
+            settings_fingerprint: String::new(),
+            complete: true,
+            preview: false,
+            trim_start_seconds: 0.0,
+            trim_end_seconds: 0.0,
+            decoded_frame_count: None,
+            scenes: None,
+            channel_stats: None,
+            frames,
+        }
+    }
+}
+
 /// Write extracted frames and metadata to disk.
 pub struct OutputWriter {
     output_dir: PathBuf,
+    bucket_minutes: Option<u32>,
+    checksums: bool,
+    pixel_hashes: bool,
+    palette_size: Option<usize>,
+    jpeg_subsampling: JpegSubsampling,
+    multipage_tiff: Option<PathBuf>,
 }
 
 impl OutputWriter {
     /// Create a new output writer.
     pub fn new(output_dir: Option<PathBuf>) -> Self {
         let output_dir = output_dir.unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT_DIR));
-        Self { output_dir }
+        Self {
+            output_dir,
+            bucket_minutes: None,
+            checksums: false,
+            pixel_hashes: false,
+            palette_size: None,
+            jpeg_subsampling: JpegSubsampling::default(),
+            multipage_tiff: None,
+        }
+    }
+
+    /// Write a `SHA256SUMS` manifest of every still and the metadata file
+    /// once the final (complete) metadata is written.
+    pub fn with_checksums(mut self, enabled: bool) -> Self {
+        self.checksums = enabled;
+        self
     }
 
-    /// Create the output directory if it doesn't exist.
+    /// Record each still's [`pixel_sha256`] in its metadata.
+    pub fn with_pixel_hashes(mut self, enabled: bool) -> Self {
+        self.pixel_hashes = enabled;
+        self
+    }
+
+    /// Record this many of each still's [`dominant_colors`] in its metadata.
+    pub fn with_palette(mut self, colors: Option<usize>) -> Self {
+        self.palette_size = colors;
+        self
+    }
+
+    /// Encode JPEG stills with this chroma subsampling.
+    pub fn with_jpeg_subsampling(mut self, subsampling: JpegSubsampling) -> Self {
+        self.jpeg_subsampling = subsampling;
+        self
+    }
+
+    /// Write every still as a page of one TIFF at `path` instead of a file
+    /// each in the output directory. Metadata stays in the output directory.
+    pub fn with_multipage_tiff(mut self, path: Option<PathBuf>) -> Self {
+        self.multipage_tiff = path;
+        self
+    }
+
+    /// Group stills into subdirectories spanning this many minutes each
+    /// (`00-10/`, `10-20/`, ...). Metadata stays in the output root.
+    pub fn with_bucket_minutes(mut self, minutes: Option<u32>) -> Self {
+        self.bucket_minutes = minutes;
+        self
+    }
+
+    /// Create the output directory if it doesn't exist and check that it's writable.
     pub fn prepare(&self) -> Result<&Path> {
         fs::create_dir_all(&self.output_dir).map_err(|e| {
             Error::Output(format!(
@@ -55,124 +270,1083 @@ impl OutputWriter {
                 e
             ))
         })?;
+
+        // Catch read-only mounts before any frames are decoded or encoded
+        let probe = self.output_dir.join(".scenesplit-write-test");
+        fs::write(&probe, b"")
+            .and_then(|_| fs::remove_file(&probe))
+            .map_err(|e| {
+                Error::Output(format!(
+                    "Output directory '{}' is not writable: {}. Check its permissions or choose another --output directory",
+                    self.output_dir.display(),
+                    e
+                ))
+            })?;
         Ok(&self.output_dir)
     }
 
-    /// Write a frame image to disk.
+    /// Fail early if the output volume clearly can't hold `bytes` more data.
+    ///
+    /// Does nothing where free space can't be queried.
+    pub fn check_free_space(&self, bytes: u64) -> Result<()> {
+        match available_space(&self.output_dir) {
+            Some(available) if available < bytes => Err(Error::Output(format!(
+                "Not enough free space in '{}': about {} MB needed, {} MB available. Free up space or choose another --output directory",
+                self.output_dir.display(),
+                bytes.div_ceil(1 << 20),
+                available >> 20
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Turn a "disk full" write error into an actionable message.
+    pub fn explain_write_error(&self, err: Error, frames_written: usize) -> Error {
+        match err {
+            Error::Io(e) if e.kind() == io::ErrorKind::StorageFull => Error::Output(format!(
+                "Disk full after writing {} frames to '{}'. Free up space or choose another --output directory",
+                frames_written,
+                self.output_dir.display()
+            )),
+            other => other,
+        }
+    }
+
+    /// Write a segment's selected frame(s) to disk.
+    ///
+    /// Stills are numbered consecutively across the run as `NNNN.jpg`,
+    /// starting at `first_number`. When the segment carries alternate frames,
+    /// all of them are written in timeline order, one number each.
+    pub fn write_segment(
+        &self,
+        segment: &SemanticSegment,
+        first_number: usize,
+    ) -> Result<Vec<FrameMetadata>> {
+        segment_stills(segment, first_number)
+            .into_iter()
+            .map(|(filename, frame)| self.write_frame(segment, frame, filename))
+            .collect()
+    }
+
+    /// Write a single frame image of a segment to disk.
     pub fn write_frame(
         &self,
         segment: &SemanticSegment,
-        frame_number: usize,
+        frame: &Frame,
+        filename: String,
     ) -> Result<FrameMetadata> {
-        let filename = format!("{:04}.{}", frame_number, OUTPUT_IMAGE_FORMAT);
-        let filepath = self.output_dir.join(&filename);
-
-        let frame = &segment.representative_frame.frame;
-
-        // Create image from RGB data
-        let img: ImageBuffer<Rgb<u8>, Vec<u8>> =
-            ImageBuffer::from_raw(frame.width, frame.height, frame.data.clone()).ok_or_else(
-                || {
-                    Error::Output(format!(
-                        "Failed to create image buffer for frame {}",
-                        frame_number
-                    ))
-                },
-            )?;
+        let filename = match self.bucket_minutes {
+            Some(minutes) => {
+                let bucket = bucket_dir(frame.timestamp_seconds, minutes);
+                fs::create_dir_all(self.output_dir.join(&bucket))?;
+                format!("{}/{}", bucket, filename)
+            }
+            None => filename,
+        };
 
-        // Save as JPEG with quality setting
-        let file = File::create(&filepath)?;
-        let writer = BufWriter::new(file);
+        let (mut metadata, bytes) =
+            Self::encode_frame(segment, frame, filename, self.jpeg_subsampling)?;
+        self.describe(&mut metadata, frame)?;
+        let path = self.output_dir.join(&metadata.filename);
+        let _span = profile::span("write");
+        if let Err(e) = fs::write(&path, bytes) {
+            // Don't leave a truncated image behind
+            let _ = fs::remove_file(&path);
+            return Err(e.into());
+        }
+        Ok(metadata)
+    }
 
-        let mut encoder =
-            image::codecs::jpeg::JpegEncoder::new_with_quality(writer, OUTPUT_IMAGE_QUALITY as u8);
-        encoder
-            .encode_image(&img)
-            .map_err(|e| Error::Output(format!("Failed to encode frame: {}", e)))?;
+    /// Add the optional per-still details (pixel hash, palette) to
+    /// `metadata`.
+    fn describe(&self, metadata: &mut FrameMetadata, frame: &Frame) -> Result<()> {
+        if self.pixel_hashes {
+            metadata.pixel_sha256 = Some(pixel_sha256(frame));
+        }
+        if let Some(colors) = self.palette_size {
+            let palette = dominant_colors(&frame.data, colors)?;
+            metadata.palette = Some(
+                palette
+                    .iter()
+                    .map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b))
+                    .collect(),
+            );
+        }
+        Ok(())
+    }
 
-        Ok(FrameMetadata {
-            filename,
-            segment_index: segment.index,
-            frame_index: frame.index,
-            timestamp_seconds: frame.timestamp_seconds,
-            timestamp_formatted: format_timestamp(frame.timestamp_seconds),
+    /// Write every segment's stills as pages of one TIFF at `path`, in
+    /// segment order, each page described by its still's timestamp.
+    ///
+    /// 16-bit frames keep their depth. A partly written file is removed.
+    fn write_multipage_tiff<F>(
+        &self,
+        path: &Path,
+        segments: &[SemanticSegment],
+        mut progress_callback: Option<F>,
+    ) -> Result<Vec<FrameMetadata>>
+    where
+        F: FnMut(usize, usize),
+    {
+        let filename = std::path::absolute(path)?.to_string_lossy().into_owned();
+        let file = File::create(path)
+            .map_err(|e| Error::Output(format!("Failed to create '{}': {}", path.display(), e)))?;
+        let tiff_error =
+            |e| Error::Output(format!("Failed to write TIFF '{}': {}", path.display(), e));
+
+        let write_pages = || -> Result<Vec<FrameMetadata>> {
+            let mut tiff = TiffEncoder::new(BufWriter::new(file)).map_err(tiff_error)?;
+            let mut written = Vec::new();
+            for (i, segment) in segments.iter().enumerate() {
+                for (_, frame) in segment_stills(segment, written.len() + 1) {
+                    let mut metadata = still_metadata(segment, frame, filename.clone())?;
+                    metadata.page = Some(written.len());
+                    self.describe(&mut metadata, frame)?;
+                    let _span = profile::span("write");
+                    write_tiff_page(&mut tiff, frame, &metadata.timestamp_formatted)
+                        .map_err(tiff_error)?;
+                    written.push(metadata);
+                }
+                if let Some(cb) = progress_callback.as_mut() {
+                    cb(i + 1, segments.len());
+                }
+            }
+            Ok(written)
+        };
+
+        write_pages().inspect_err(|_| {
+            let _ = fs::remove_file(path);
         })
     }
 
+    /// Encode every segment's stills in memory, in segment order.
+    ///
+    /// Filenames in the metadata are the ones `write_frames` would use.
+    pub fn encode_frames(
+        segments: &[SemanticSegment],
+        subsampling: JpegSubsampling,
+    ) -> Result<Vec<EncodedFrame>> {
+        let encoded = segments
+            .par_iter()
+            .zip(first_still_numbers(segments))
+            .map(|(segment, first_number)| {
+                segment_stills(segment, first_number)
+                    .into_iter()
+                    .map(|(filename, frame)| {
+                        Self::encode_frame(segment, frame, filename, subsampling)
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(encoded.into_iter().flatten().collect())
+    }
+
+    /// Encode a single frame of a segment as JPEG with the given chroma
+    /// subsampling.
+    pub fn encode_frame(
+        segment: &SemanticSegment,
+        frame: &Frame,
+        filename: String,
+        subsampling: JpegSubsampling,
+    ) -> Result<EncodedFrame> {
+        let _span = profile::span("encode");
+
+        let mut bytes = Vec::new();
+        let filename = match &frame.data16 {
+            // Full-precision frames are saved losslessly as 16-bit PNG
+            Some(data16) => {
+                let img16: ImageBuffer<Rgb<u16>, Vec<u16>> =
+                    ImageBuffer::from_raw(frame.width, frame.height, data16.clone()).ok_or_else(
+                        || {
+                            Error::Output(format!(
+                                "Failed to create 16-bit image buffer for frame {}",
+                                filename
+                            ))
+                        },
+                    )?;
+                image::DynamicImage::ImageRgb16(img16)
+                    .write_to(&mut io::Cursor::new(&mut bytes), ImageOutputFormat::Png)
+                    .map_err(|e| Error::Output(format!("Failed to encode frame: {}", e)))?;
+                Path::new(&filename)
+                    .with_extension("png")
+                    .to_string_lossy()
+                    .into_owned()
+            }
+            // The image crate's encoder never subsamples chroma, so it
+            // covers 4:4:4; OpenCV writes the subsampled variants
+            None if subsampling != JpegSubsampling::Chroma444 => {
+                bytes = frame.encode_jpeg(OUTPUT_IMAGE_QUALITY, subsampling)?;
+                filename
+            }
+            None => {
+                // Borrow the RGB data rather than copying the frame
+                let img: ImageBuffer<Rgb<u8>, &[u8]> =
+                    ImageBuffer::from_raw(frame.width, frame.height, frame.data.as_slice())
+                        .ok_or_else(|| {
+                            Error::Output(format!(
+                                "Failed to create image buffer for frame {}",
+                                filename
+                            ))
+                        })?;
+                let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
+                    &mut bytes,
+                    OUTPUT_IMAGE_QUALITY as u8,
+                );
+                encoder
+                    .encode_image(&img)
+                    .map_err(|e| Error::Output(format!("Failed to encode frame: {}", e)))?;
+                filename
+            }
+        };
+
+        Ok((still_metadata(segment, frame, filename)?, bytes))
+    }
+
     /// Write all segment representative frames to disk.
+    ///
+    /// Segments are encoded in parallel. Metadata is returned in segment
+    /// order regardless of which finishes first, and the first write error
+    /// fails the whole call.
     pub fn write_frames<F>(
         &self,
         segments: &[SemanticSegment],
-        mut progress_callback: Option<F>,
+        progress_callback: Option<F>,
     ) -> Result<Vec<FrameMetadata>>
     where
-        F: FnMut(usize, usize),
+        F: FnMut(usize, usize) + Send,
     {
         self.prepare()?;
-        let mut frame_metadata = Vec::with_capacity(segments.len());
+        if let Some(path) = &self.multipage_tiff {
+            return self.write_multipage_tiff(path, segments, progress_callback);
+        }
+        let estimated_bytes: f64 = segments
+            .iter()
+            .flat_map(|segment| segment_stills(segment, 0))
+            .map(|(_, frame)| frame.width as f64 * frame.height as f64)
+            .sum::<f64>()
+            * ESTIMATED_JPEG_BYTES_PER_PIXEL;
+        self.check_free_space(estimated_bytes as u64)?;
+
+        let completed = AtomicUsize::new(0);
+        let frames_written = AtomicUsize::new(0);
+        let progress = Mutex::new(progress_callback);
+
+        let written = segments
+            .par_iter()
+            .zip(first_still_numbers(segments))
+            .map(|(segment, first_number)| {
+                let metadata = self.write_segment(segment, first_number).map_err(|e| {
+                    self.explain_write_error(e, frames_written.load(Ordering::SeqCst))
+                })?;
+                frames_written.fetch_add(metadata.len(), Ordering::SeqCst);
+
+                // Count under the lock so reported progress never goes backwards
+                let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+                let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Some(cb) = progress.as_mut() {
+                    cb(done, segments.len());
+                }
+
+                Ok(metadata)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(written.into_iter().flatten().collect())
+    }
+
+    /// Write extraction metadata to a JSON file.
+    ///
+    /// With checksums enabled, complete metadata is followed by the
+    /// `SHA256SUMS` manifest.
+    pub fn write_metadata(&self, output_meta: &OutputMetadata) -> Result<PathBuf> {
+        let metadata_path = self.metadata_path();
+        let json = serde_json::to_vec_pretty(output_meta)
+            .map_err(|e| Error::Output(format!("Failed to write metadata: {}", e)))?;
 
-        for (i, segment) in segments.iter().enumerate() {
-            let metadata = self.write_frame(segment, i + 1)?;
-            frame_metadata.push(metadata);
+        if let Err(e) = fs::write(&metadata_path, json) {
+            let _ = fs::remove_file(&metadata_path);
+            return Err(self.explain_write_error(e.into(), output_meta.frames.len()));
+        }
+
+        if self.checksums && output_meta.complete {
+            self.write_checksums(&output_meta.frames)?;
+        }
+
+        Ok(metadata_path)
+    }
+
+    /// Hash every still and the metadata file into a `sha256sum -c`
+    /// compatible manifest in the output directory.
+    pub fn write_checksums(&self, frames: &[FrameMetadata]) -> Result<PathBuf> {
+        let mut manifest = String::new();
+        let filenames = frames
+            .iter()
+            .map(|f| f.filename.as_str())
+            .chain(std::iter::once(METADATA_FILENAME));
+        for filename in filenames {
+            let mut file = File::open(self.output_dir.join(filename)).map_err(|e| {
+                Error::Output(format!("Failed to read '{}' for hashing: {}", filename, e))
+            })?;
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            manifest.push_str(&format!("{:x}  {}\n", hasher.finalize(), filename));
+        }
+
+        let path = self.output_dir.join(CHECKSUMS_FILENAME);
+        fs::write(&path, manifest)?;
+        Ok(path)
+    }
+
+    /// Path of the metadata file inside the output directory.
+    pub fn metadata_path(&self) -> PathBuf {
+        self.output_dir.join(METADATA_FILENAME)
+    }
 
-            if let Some(ref mut cb) = progress_callback {
-                cb(i + 1, segments.len());
+    /// Read a previously written metadata file.
+    pub fn read_metadata(path: &Path) -> Result<OutputMetadata> {
+        let file = File::open(path)
+            .map_err(|e| Error::Output(format!("Failed to open '{}': {}", path.display(), e)))?;
+
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Error::Output(format!("Failed to parse '{}': {}", path.display(), e)))
+    }
+
+    /// Get the output directory path.
+    pub fn output_dir(&self) -> &Path {
+        &self.output_dir
+    }
+}
+
+/// Write the pairwise cosine similarity of all frames to `path`.
+///
+/// Rows and columns follow the order of `frames`. A `.csv` path gets one
+/// comma-separated row per frame; any other path gets N×N little-endian
+/// float32 values in row-major order. Rows are streamed, so only the file
+/// grows quadratically.
+pub fn write_similarity_matrix(path: &Path, frames: &[EmbeddedFrame]) -> Result<()> {
+    let csv = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    let file = File::create(path)
+        .map_err(|e| Error::Output(format!("Failed to create '{}': {}", path.display(), e)))?;
+    let mut writer = BufWriter::new(file);
+
+    for a in frames {
+        let row = frames
+            .iter()
+            .map(|b| cosine_similarity(&a.embedding, &b.embedding));
+        if csv {
+            let line: Vec<String> = row.map(|v| v.to_string()).collect();
+            writeln!(writer, "{}", line.join(","))?;
+        } else {
+            for value in row {
+                writer.write_all(&value.to_le_bytes())?;
             }
         }
+    }
 
-        Ok(frame_metadata)
+    writer.flush()?;
+    Ok(())
+}
+
+/// Number of each segment's first still when all stills of `segments` are
+/// numbered consecutively from 1.
+fn first_still_numbers(segments: &[SemanticSegment]) -> Vec<usize> {
+    segments
+        .iter()
+        .scan(1, |next, segment| {
+            let first = *next;
+            *next += 1 + segment.alternate_frames.len();
+            Some(first)
+        })
+        .collect()
+}
+
+/// Output filename and frame for each still of a segment, named as
+/// described on [`OutputWriter::write_segment`].
+fn segment_stills(segment: &SemanticSegment, first_number: usize) -> Vec<(String, &Frame)> {
+    let mut frames: Vec<&Frame> = std::iter::once(&segment.representative_frame)
+        .chain(segment.alternate_frames.iter())
+        .map(|f| &f.frame)
+        .collect();
+    frames.sort_by_key(|f| f.index);
+
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(offset, frame)| {
+            let filename = format!("{:04}.{}", first_number + offset, OUTPUT_IMAGE_FORMAT);
+            (filename, frame)
+        })
+        .collect()
+}
+
+/// Metadata for `frame`, a still of `segment` saved as `filename`.
+fn still_metadata(
+    segment: &SemanticSegment,
+    frame: &Frame,
+    filename: String,
+) -> Result<FrameMetadata> {
+    let quality = frame.quality()?;
+    Ok(FrameMetadata {
+        sharpness: quality.sharpness,
+        exposure: quality.exposure,
+        ..FrameMetadata::new(segment, frame, filename)
+    })
+}
+
+/// Append `frame` to `tiff` as a page whose description tag is
+/// `description`.
+fn write_tiff_page<W: Write + Seek>(
+    tiff: &mut TiffEncoder<W>,
+    frame: &Frame,
+    description: &str,
+) -> tiff::TiffResult<()> {
+    match &frame.data16 {
+        Some(data16) => {
+            let mut page = tiff.new_image::<colortype::RGB16>(frame.width, frame.height)?;
+            page.encoder()
+                .write_tag(Tag::ImageDescription, description)?;
+            page.write_data(data16)
+        }
+        None => {
+            let mut page = tiff.new_image::<colortype::RGB8>(frame.width, frame.height)?;
+            page.encoder()
+                .write_tag(Tag::ImageDescription, description)?;
+            page.write_data(&frame.data)
+        }
     }
+}
 
-    /// Write extraction metadata to a JSON file.
-    pub fn write_metadata(
-        &self,
+/// Save the representative of the segment `strategy` picks to `path` as a
+/// poster for the whole video, in the format its extension names.
+///
+/// Does nothing without segments.
+pub fn write_poster(
+    path: &Path,
+    segments: &[SemanticSegment],
+    strategy: PosterStrategy,
+) -> Result<()> {
+    let Some(segment) = poster_segment(segments, strategy) else {
+        return Ok(());
+    };
+    let frame = &segment.representative_frame.frame;
+    let img: ImageBuffer<Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_raw(frame.width, frame.height, frame.data.clone()).ok_or_else(|| {
+            Error::Output(format!(
+                "Failed to create image buffer for frame {}",
+                frame.index
+            ))
+        })?;
+    img.save(path).map_err(|e| {
+        Error::Output(format!(
+            "Failed to write poster '{}': {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// One sampled frame's embedding in an [`EmbeddingDump`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DumpedEmbedding {
+    pub frame_index: usize,
+    pub timestamp_seconds: Timestamp,
+    pub embedding: Vec<f32>,
+}
+
+/// The embeddings of every sampled frame, with enough about their source to
+/// segment them again without the video or model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingDump {
+    pub source_video: String,
+    pub video_duration_seconds: f64,
+    pub video_frame_count: u32,
+    pub fps: f64,
+    /// Quality preset the frames were embedded with.
+    pub quality_preset: String,
+    pub frames: Vec<DumpedEmbedding>,
+}
+
+impl EmbeddingDump {
+    /// Dump `frames`, embedded from `video_metadata`, without pixel data.
+    pub fn new(
         video_metadata: &VideoMetadata,
-        frame_metadata: Vec<FrameMetadata>,
-        detail_level: &str,
         quality_preset: &str,
-    ) -> Result<PathBuf> {
-        let output_meta = OutputMetadata {
+        frames: &[EmbeddedFrame],
+    ) -> Self {
+        Self {
             source_video: video_metadata.path.to_string_lossy().to_string(),
             video_duration_seconds: video_metadata.duration_seconds,
             video_frame_count: video_metadata.frame_count,
-            extracted_frames: frame_metadata.len(),
-            detail_level: detail_level.to_string(),
+            fps: video_metadata.fps,
             quality_preset: quality_preset.to_string(),
-            frames: frame_metadata,
-        };
+            frames: frames
+                .iter()
+                .map(|f| DumpedEmbedding {
+                    frame_index: f.index(),
+                    timestamp_seconds: f.timestamp_seconds(),
+                    embedding: f.embedding.clone(),
+                })
+                .collect(),
+        }
+    }
 
-        let metadata_path = self.output_dir.join("metadata.json");
-        let file = File::create(&metadata_path)?;
-        let writer = BufWriter::new(file);
+    /// The dumped frames as embedded frames with empty pixel data.
+    pub fn embedded_frames(&self) -> Vec<EmbeddedFrame> {
+        self.frames
+            .iter()
+            .map(|f| EmbeddedFrame {
+                frame: Frame {
+                    index: f.frame_index,
+                    timestamp_seconds: f.timestamp_seconds,
+                    data: Vec::new(),
+                    width: 0,
+                    height: 0,
+                    data16: None,
+                },
+                embedding: f.embedding.clone(),
+            })
+            .collect()
+    }
 
-        serde_json::to_writer_pretty(writer, &output_meta)
-            .map_err(|e| Error::Output(format!("Failed to write metadata: {}", e)))?;
+    /// Metadata of the source video, as far as the dump records it.
+    pub fn video_metadata(&self) -> VideoMetadata {
+        VideoMetadata {
+            path: PathBuf::from(&self.source_video),
+            width: 0,
+            height: 0,
+            fps: self.fps,
+            frame_count: self.video_frame_count,
+            duration_seconds: self.video_duration_seconds,
+            codec: String::new(),
+            decoded_frame_count: None,
+        }
+    }
 
-        Ok(metadata_path)
+    /// Write the dump to `path` as JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .map_err(|e| Error::Output(format!("Failed to create '{}': {}", path.display(), e)))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)
+            .map_err(|e| Error::Output(format!("Failed to write embeddings: {}", e)))?;
+        writer.flush()?;
+        Ok(())
     }
 
-    /// Get the output directory path.
-    pub fn output_dir(&self) -> &Path {
-        &self.output_dir
+    /// Read a dump written by [`write`](Self::write).
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| Error::Output(format!("Failed to open '{}': {}", path.display(), e)))?;
+
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Error::Output(format!("Failed to parse '{}': {}", path.display(), e)))
     }
 }
 
-/// Format a timestamp as HH:MM:SS.mmm.
-fn format_timestamp(seconds: f64) -> String {
-    let hours = (seconds / 3600.0) as u32;
-    let minutes = ((seconds % 3600.0) / 60.0) as u32;
-    let secs = seconds % 60.0;
-    format!("{:02}:{:02}:{:06.3}", hours, minutes, secs)
+/// Write the segmenter's per-frame anchor trace to `path`.
+///
+/// Paths ending in `.csv` get one row per frame under a header; anything
+/// else gets a JSON array.
+pub fn write_anchor_trace(path: &Path, trace: &[AnchorSample]) -> Result<()> {
+    let csv = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    let file = File::create(path)
+        .map_err(|e| Error::Output(format!("Failed to create '{}': {}", path.display(), e)))?;
+    let mut writer = BufWriter::new(file);
+
+    if csv {
+        writeln!(
+            writer,
+            "frame_index,timestamp_seconds,similarity,boundary,anchor_norm"
+        )?;
+        for sample in trace {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                sample.frame_index,
+                sample.timestamp_seconds.as_seconds(),
+                sample.similarity,
+                sample.boundary,
+                sample.anchor_norm
+            )?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut writer, trace)
+            .map_err(|e| Error::Output(format!("Failed to write anchor trace: {}", e)))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Bytes available to unprivileged users on the volume holding `path`.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn available_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read if statvfs succeeds
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stat = unsafe { stat.assume_init() };
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Name of the time bucket containing `timestamp`, e.g. `10-20`.
+fn bucket_dir(timestamp: Timestamp, minutes: u32) -> String {
+    let minutes = minutes.max(1) as u64;
+    let start = (timestamp.as_seconds().max(0.0) / 60.0) as u64 / minutes * minutes;
+    format!("{:02}-{:02}", start, start + minutes)
+}
+
+/// Hex SHA-256 of a frame's pixels before encoding: row-major RGB, 8 bits
+/// per sample, or big-endian 16 bits for 16-bit stills.
+///
+/// Unlike a hash of the file, this doesn't change with the JPEG encoder, so
+/// it stays stable across `image` crate versions.
+pub fn pixel_sha256(frame: &Frame) -> String {
+    let mut hasher = Sha256::new();
+    match &frame.data16 {
+        Some(data16) => {
+            for sample in data16 {
+                hasher.update(sample.to_be_bytes());
+            }
+        }
+        None => hasher.update(&frame.data),
+    }
+    format!("{:x}", hasher.finalize())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn segment(index: usize) -> SemanticSegment {
+        let frame = Frame {
+            index: index * 30,
+            timestamp_seconds: Timestamp::from_seconds(index as f64),
+            data: vec![128; 4 * 4 * 3],
+            width: 4,
+            height: 4,
+            data16: None,
+        };
+        SemanticSegment {
+            index,
+            start_frame_idx: index * 30,
+            end_frame_idx: index * 30 + 29,
+            start_timestamp_seconds: Timestamp::from_seconds(index as f64),
+            end_timestamp_seconds: Timestamp::from_seconds(index as f64 + 1.0),
+            representative_frame: EmbeddedFrame {
+                frame,
+                embedding: vec![1.0],
+            },
+            alternate_frames: Vec::new(),
+            frame_count: 30,
+            boundary_drop: None,
+            boundary_kind: None,
+            relocation: None,
+            stride_seconds: 0.0,
+        }
+    }
+
     #[test]
-    fn test_format_timestamp() {
-        assert_eq!(format_timestamp(0.0), "00:00:00.000");
-        assert_eq!(format_timestamp(61.5), "00:01:01.500");
-        assert_eq!(format_timestamp(3661.123), "01:01:01.123");
+    fn test_write_frames_keeps_segment_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = OutputWriter::new(Some(dir.path().to_path_buf()));
+        let segments: Vec<_> = (0..12).map(segment).collect();
+
+        let mut reported = Vec::new();
+        let metadata = writer
+            .write_frames(&segments, Some(|done, _total| reported.push(done)))
+            .unwrap();
+
+        let filenames: Vec<_> = metadata.iter().map(|m| m.filename.clone()).collect();
+        let expected: Vec<_> = (1..=12).map(|n| format!("{:04}.jpg", n)).collect();
+        assert_eq!(filenames, expected);
+        assert_eq!(reported, (1..=12).collect::<Vec<_>>());
+        assert!(dir.path().join("0012.jpg").exists());
+    }
+
+    #[test]
+    fn test_alternate_stills_are_numbered_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = OutputWriter::new(Some(dir.path().to_path_buf()));
+        let mut segments: Vec<_> = (0..3).map(segment).collect();
+        let mut alternate = segments[1].representative_frame.clone();
+        alternate.frame.index += 15;
+        segments[1].alternate_frames.push(alternate);
+
+        let metadata = writer
+            .write_frames::<fn(usize, usize)>(&segments, None)
+            .unwrap();
+
+        let filenames: Vec<_> = metadata.iter().map(|m| m.filename.as_str()).collect();
+        assert_eq!(filenames, ["0001.jpg", "0002.jpg", "0003.jpg", "0004.jpg"]);
+        assert_eq!(metadata[2].segment_index, 1);
+        assert_eq!(metadata[3].segment_index, 2);
+
+        let stills = OutputWriter::encode_frames(&segments, JpegSubsampling::default()).unwrap();
+        assert_eq!(stills[3].0.filename, "0004.jpg");
+    }
+
+    #[test]
+    fn test_preflight_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = OutputWriter::new(Some(dir.path().join("out")));
+        writer.prepare().unwrap();
+        assert!(!dir.path().join("out/.scenesplit-write-test").exists());
+
+        assert!(writer.check_free_space(0).is_ok());
+        #[cfg(unix)]
+        assert!(writer.check_free_space(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_explain_write_error() {
+        let writer = OutputWriter::new(Some(PathBuf::from("out")));
+        let full = Error::Io(io::Error::from(io::ErrorKind::StorageFull));
+        let message = writer.explain_write_error(full, 12).to_string();
+        assert!(message.contains("Disk full after writing 12 frames"));
+
+        let other = Error::Io(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(writer.explain_write_error(other, 0), Error::Io(_)));
+    }
+
+    #[test]
+    fn test_bucket_dir() {
+        assert_eq!(bucket_dir(Timestamp::ZERO, 10), "00-10");
+        assert_eq!(bucket_dir(Timestamp::from_seconds(599.9), 10), "00-10");
+        assert_eq!(bucket_dir(Timestamp::from_seconds(600.0), 10), "10-20");
+        assert_eq!(bucket_dir(Timestamp::from_seconds(7260.0), 30), "120-150");
+    }
+
+    #[test]
+    fn test_write_frames_into_buckets() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = OutputWriter::new(Some(dir.path().to_path_buf())).with_bucket_minutes(Some(1));
+        // Segment i's still sits at i seconds; move two past the first minute
+        let mut segments: Vec<_> = (0..3).map(segment).collect();
+        segments[2].representative_frame.frame.timestamp_seconds = Timestamp::from_seconds(75.0);
+
+        let metadata = writer
+            .write_frames::<fn(usize, usize)>(&segments, None)
+            .unwrap();
+
+        assert_eq!(metadata[0].filename, "00-01/0001.jpg");
+        assert_eq!(metadata[2].filename, "01-02/0003.jpg");
+        assert!(dir.path().join("01-02/0003.jpg").exists());
+    }
+
+    #[test]
+    fn test_encode_frames_in_memory() {
+        let segments: Vec<_> = (0..3).map(segment).collect();
+        let stills = OutputWriter::encode_frames(&segments, JpegSubsampling::default()).unwrap();
+
+        assert_eq!(stills.len(), 3);
+        assert_eq!(stills[2].0.filename, "0003.jpg");
+        assert_eq!(&stills[0].1[..2], &[0xFF, 0xD8]);
+
+        let inline = InlineFrame::new(stills[0].0.clone(), &[1, 2, 3]);
+        let json = serde_json::to_value(&inline).unwrap();
+        assert_eq!(json["filename"], "0001.jpg");
+        assert_eq!(json["image"], "AQID");
+    }
+
+    #[test]
+    fn test_encode_frame_keeps_full_chroma_resolution() {
+        let segment = segment(0);
+        let (_, bytes) = OutputWriter::encode_frame(
+            &segment,
+            &segment.representative_frame.frame,
+            "0001.jpg".to_string(),
+            JpegSubsampling::Chroma444,
+        )
+        .unwrap();
+
+        // Baseline start-of-frame: marker, length, precision, height,
+        // width, component count, then (id, HxV sampling, table) per component
+        let sof = bytes.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        let components = bytes[sof + 9] as usize;
+        assert_eq!(components, 3);
+        for c in 0..components {
+            assert_eq!(
+                bytes[sof + 10 + c * 3 + 1],
+                0x11,
+                "component {} is subsampled",
+                c
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_frame_preserves_16_bit_depth() {
+        let mut segment = segment(0);
+        segment.representative_frame.frame.data16 = Some(vec![1000; 4 * 4 * 3]);
+        let (metadata, bytes) = OutputWriter::encode_frame(
+            &segment,
+            &segment.representative_frame.frame,
+            "00-10/0001.jpg".to_string(),
+            JpegSubsampling::Chroma420,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.filename, "00-10/0001.png");
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!(decoded.to_rgb16().get_pixel(0, 0), &Rgb([1000, 1000, 1000]));
+    }
+
+    #[test]
+    fn test_write_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = OutputWriter::new(Some(dir.path().to_path_buf())).with_checksums(true);
+        let segments: Vec<_> = (0..2).map(segment).collect();
+        let frames = writer
+            .write_frames::<fn(usize, usize)>(&segments, None)
+            .unwrap();
+
+        let video_meta = VideoMetadata {
+            path: PathBuf::from("video.mp4"),
+            width: 4,
+            height: 4,
+            fps: 30.0,
+            frame_count: 60,
+            duration_seconds: 2.0,
+            codec: "avc1".to_string(),
+            decoded_frame_count: None,
+        };
+
+        let mut partial = OutputMetadata::new(&video_meta, frames.clone(), "all", "fast");
+        partial.complete = false;
+        writer.write_metadata(&partial).unwrap();
+        assert!(!dir.path().join("SHA256SUMS").exists());
+
+        let complete = OutputMetadata::new(&video_meta, frames, "all", "fast");
+        writer.write_metadata(&complete).unwrap();
+        let manifest = fs::read_to_string(dir.path().join("SHA256SUMS")).unwrap();
+        let lines: Vec<_> = manifest.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("  0001.jpg"));
+        assert!(lines[2].ends_with("  metadata.json"));
+
+        let expected = Sha256::digest(fs::read(dir.path().join("0001.jpg")).unwrap());
+        assert!(lines[0].starts_with(&format!("{:x}", expected)));
+    }
+
+    #[test]
+    fn test_pixel_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = OutputWriter::new(Some(dir.path().to_path_buf())).with_pixel_hashes(true);
+        let frames = writer
+            .write_frames::<fn(usize, usize)>(&[segment(0)], None)
+            .unwrap();
+
+        let expected = format!("{:x}", Sha256::digest([128u8; 4 * 4 * 3]));
+        assert_eq!(frames[0].pixel_sha256.as_deref(), Some(expected.as_str()));
+
+        // 16-bit samples are hashed big-endian
+        let mut frame = segment(0).representative_frame.frame;
+        frame.data16 = Some(vec![0x0102; 2]);
+        assert_eq!(
+            pixel_sha256(&frame),
+            format!("{:x}", Sha256::digest([1, 2, 1, 2]))
+        );
+
+        let plain = OutputWriter::new(Some(dir.path().to_path_buf()));
+        let frames = plain
+            .write_frames::<fn(usize, usize)>(&[segment(0)], None)
+            .unwrap();
+        assert_eq!(frames[0].pixel_sha256, None);
+    }
+
+    #[test]
+    fn test_palette() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = OutputWriter::new(Some(dir.path().to_path_buf())).with_palette(Some(3));
+        let frames = writer
+            .write_frames::<fn(usize, usize)>(&[segment(0)], None)
+            .unwrap();
+
+        // A flat gray still has a single color
+        assert_eq!(frames[0].palette, Some(vec!["#808080".to_string()]));
+    }
+
+    #[test]
+    fn test_multipage_tiff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stills.tif");
+        let writer =
+            OutputWriter::new(Some(dir.path().join("out"))).with_multipage_tiff(Some(path.clone()));
+        let frames = writer
+            .write_frames::<fn(usize, usize)>(&[segment(0), segment(1)], None)
+            .unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].page, Some(1));
+        assert_eq!(frames[1].filename, path.to_string_lossy());
+        // No stills of their own in the output directory
+        assert_eq!(fs::read_dir(writer.output_dir()).unwrap().count(), 0);
+
+        let mut decoder = tiff::decoder::Decoder::new(File::open(&path).unwrap()).unwrap();
+        let mut descriptions = Vec::new();
+        loop {
+            assert_eq!(decoder.dimensions().unwrap(), (4, 4));
+            descriptions.push(decoder.get_tag_ascii_string(Tag::ImageDescription).unwrap());
+            if !decoder.more_images() {
+                break;
+            }
+            decoder.next_image().unwrap();
+        }
+        assert_eq!(
+            descriptions,
+            vec![
+                frames[0].timestamp_formatted.clone(),
+                frames[1].timestamp_formatted.clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_embedding_dump_round_trips() {
+        let video_meta = VideoMetadata {
+            path: PathBuf::from("/footage/talk.mp4"),
+            width: 1920,
+            height: 1080,
+            fps: 29.97,
+            frame_count: 900,
+            duration_seconds: 30.03,
+            codec: "avc1".to_string(),
+            decoded_frame_count: None,
+        };
+        let mut frame = segment(1).representative_frame;
+        frame.embedding = vec![0.1, -0.333_333_34, 1e-7];
+        let dump = EmbeddingDump::new(&video_meta, "balanced", &[frame.clone()]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("embeddings.json");
+        dump.write(&path).unwrap();
+        let read = EmbeddingDump::read(&path).unwrap();
+        assert_eq!(read, dump);
+
+        let frames = read.embedded_frames();
+        assert_eq!(frames[0].index(), frame.index());
+        assert_eq!(frames[0].timestamp_seconds(), frame.timestamp_seconds());
+        assert_eq!(frames[0].embedding, frame.embedding);
+        assert!(frames[0].frame.data.is_empty());
+        assert_eq!(read.video_metadata().fps, 29.97);
+    }
+
+    #[test]
+    fn test_write_anchor_trace() {
+        let trace = [AnchorSample {
+            frame_index: 30,
+            timestamp_seconds: Timestamp::from_seconds(1.0),
+            similarity: 0.5,
+            boundary: true,
+            anchor_norm: 1.0,
+        }];
+        let dir = tempfile::tempdir().unwrap();
+
+        let csv_path = dir.path().join("anchor.csv");
+        write_anchor_trace(&csv_path, &trace).unwrap();
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(csv.lines().nth(1), Some("30,1,0.5,true,1"));
+
+        let json_path = dir.path().join("anchor.json");
+        write_anchor_trace(&json_path, &trace).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(json[0]["boundary"], true);
+    }
+
+    #[test]
+    fn test_write_similarity_matrix() {
+        let frames: Vec<_> = [[1.0, 0.0], [0.0, 1.0], [1.0, 0.0]]
+            .iter()
+            .enumerate()
+            .map(|(i, e)| EmbeddedFrame {
+                frame: segment(i).representative_frame.frame,
+                embedding: e.to_vec(),
+            })
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+
+        let csv_path = dir.path().join("matrix.csv");
+        write_similarity_matrix(&csv_path, &frames).unwrap();
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(csv, "1,0,1\n0,1,0\n1,0,1\n");
+
+        let bin_path = dir.path().join("matrix.f32");
+        write_similarity_matrix(&bin_path, &frames).unwrap();
+        let values: Vec<f32> = fs::read(&bin_path)
+            .unwrap()
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, vec![1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let metadata = OutputMetadata {
+            source_video: "video.mp4".to_string(),
+            video_duration_seconds: 12.5,
+            video_frame_count: 375,
+            extracted_frames: 1,
+            detail_level: "summary".to_string(),
+            quality_preset: "balanced".to_string(),
+            sample_offset: 3,
+            skipped_frames: vec![90],
+            settings_fingerprint: "detail=summary".to_string(),
+            complete: true,
+            preview: false,
+            trim_start_seconds: 0.0,
+            trim_end_seconds: 0.0,
+            decoded_frame_count: None,
+            scenes: None,
+            channel_stats: None,
+            frames: vec![FrameMetadata {
+                filename: "0001.jpg".to_string(),
+                segment_index: 0,
+                frame_index: 45,
+                timestamp_seconds: Timestamp::from_seconds(1.5),
+                timestamp_formatted: "00:00:01.500".to_string(),
+                start_frame_timestamp: Timestamp::ZERO,
+                end_frame_timestamp: Timestamp::from_seconds(3.0),
+                duration_seconds: 3.0,
+                sharpness: 152.4,
+                exposure: 0.98,
+                relocation: None,
+                boundary: None,
+                clip: None,
+                pixel_sha256: None,
+                palette: None,
+                page: None,
+            }],
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(METADATA_FILENAME);
+        serde_json::to_writer_pretty(File::create(&path).unwrap(), &metadata).unwrap();
+
+        assert_eq!(OutputWriter::read_metadata(&path).unwrap(), metadata);
+
+        // Metadata written before `complete` existed describes a finished run
+        let mut json = serde_json::to_value(&metadata).unwrap();
+        json.as_object_mut().unwrap().remove("complete");
+        fs::write(&path, json.to_string()).unwrap();
+        assert!(OutputWriter::read_metadata(&path).unwrap().complete);
     }
 }
@@ -1,36 +1,351 @@
 //! Main processing pipeline for SceneSplit.
 
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
-use crate::config::{DetailLevel, QualityPreset};
-use crate::embeddings::EmbeddingModel;
-use crate::error::Result;
-use crate::output::OutputWriter;
-use crate::segmentation::SemanticSegmenter;
-use crate::video::{VideoLoader, VideoMetadata};
+use image::DynamicImage;
+use serde::Serialize;
+
+use crate::config::{
+    DetailLevel, JpegSubsampling, MaxFramesStrategy, PoolingMode, PosterStrategy, PreprocessMode,
+    QualityPreset, SampleStrategy, Verbosity, AUTO_DETAIL_PROBE_FRAMES, CALIBRATION_PROBE_FRAMES,
+    DEFAULT_ANCHOR_ALPHA, DEFAULT_ORT_OPT_LEVEL, INTERLACED_COMBING_SHARE, INTERLACE_PROBE_FRAMES,
+    MEMORY_CHECK_INTERVAL_FRAMES, SAMPLE_COUNT_TOLERANCE, SIMILARITY_MATRIX_WARN_FRAMES,
+};
+use crate::edl::write_edl;
+use crate::embeddings::{
+    capped_batch_size, channel_stats, ChannelStats, EmbeddedFrame, EmbeddingModel,
+};
+use crate::error::{Error, Result};
+use crate::events::{Event, EventLog};
+use crate::filmstrip::{boundary_frame_pairs, write_boundaries};
+use crate::output::{
+    write_anchor_trace, write_poster, write_similarity_matrix, EmbeddingDump, EncodedFrame,
+    FrameMetadata, OutputMetadata, OutputWriter,
+};
+use crate::segmentation::{
+    bisect_boundaries, change_share, every_nth_segment, include_endpoints, limit_segments,
+    merge_near_duplicates, scene_tree, space_out_segments, uniform_segments, SceneNode,
+    SemanticSegment, SemanticSegmenter,
+};
+use crate::timeline::{timeline_entries, write_timeline};
+use crate::timestamp::Timestamp;
+use crate::video::{
+    clip_frame_range, expected_sample_count, preview_windows, scaled_size, Frame, Region,
+    VideoLoader, VideoMetadata,
+};
+
+/// `source_video` recorded for frames passed to `process_frames`.
+const IN_MEMORY_SOURCE: &str = "<memory>";
 
 /// Result of video processing.
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct ProcessingResult {
     pub video_metadata: VideoMetadata,
+    /// Sampled frames analyzed by this run; an incremental run continued
+    /// with `--resume` leaves out those before where it picked up.
     pub total_frames_processed: usize,
     pub segments_detected: usize,
     pub frames_extracted: usize,
     pub output_dir: PathBuf,
     pub metadata_path: PathBuf,
+    /// True when a complete previous run was reused via `--resume`.
+    pub resumed: bool,
+    /// Settings the run was made with.
+    pub settings: EffectiveSettings,
+    /// Stills dropped as near-duplicates by `--ssim-threshold`.
+    pub near_duplicates_removed: usize,
+}
+
+impl ProcessingResult {
+    /// Counts and settings for the final report.
+    pub fn summary(&self) -> RunSummary {
+        RunSummary::new(
+            &self.video_metadata,
+            self.total_frames_processed,
+            self.segments_detected,
+            self.frames_extracted,
+            self.near_duplicates_removed,
+            Some(self.metadata_path.clone()),
+            self.settings.clone(),
+        )
+    }
+}
+
+/// A pipeline milestone, reported to the callback passed to `process`.
+#[derive(Debug, Clone)]
+pub enum ProgressEvent {
+    /// The video was opened and its metadata read.
+    VideoLoaded { metadata: VideoMetadata },
+    /// Frames decoded so far out of the video's frame count.
+    FramesExtracted { done: usize, total: usize },
+    /// Sampled frames embedded so far.
+    EmbeddingProgress { done: usize, total: usize },
+    /// Segment `index` of the output will not change any more. Reported
+    /// once segments are thinned, so `index` matches the stills' metadata.
+    SegmentFinalized { index: usize },
+    /// A still, clip, EDL or metadata file was written.
+    OutputWritten { path: PathBuf },
+}
+
+/// What a run did, as printed at the end and included with `--json-stdout`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub video_duration_seconds: f64,
+    pub total_frames_processed: usize,
+    /// Segments found before `--every-nth-segment` thinning.
+    pub segments_detected: usize,
+    /// Video duration divided evenly over the detected segments.
+    pub average_segment_seconds: f64,
+    pub frames_extracted: usize,
+    pub near_duplicates_removed: usize,
+    /// `None` when nothing was written to disk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_path: Option<PathBuf>,
+    pub settings: EffectiveSettings,
+}
+
+impl RunSummary {
+    fn new(
+        video_meta: &VideoMetadata,
+        total_frames_processed: usize,
+        segments_detected: usize,
+        frames_extracted: usize,
+        near_duplicates_removed: usize,
+        metadata_path: Option<PathBuf>,
+        settings: EffectiveSettings,
+    ) -> Self {
+        let average_segment_seconds = if segments_detected == 0 {
+            0.0
+        } else {
+            video_meta.duration_seconds / segments_detected as f64
+        };
+        Self {
+            video_duration_seconds: video_meta.duration_seconds,
+            total_frames_processed,
+            segments_detected,
+            average_segment_seconds,
+            frames_extracted,
+            near_duplicates_removed,
+            metadata_path,
+            settings,
+        }
+    }
+}
+
+/// The model, detail level, quality preset and thresholds a run will use,
+/// after defaults, overrides and `--detail auto` are resolved.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveSettings {
+    pub model: PathBuf,
+    pub detail: String,
+    pub quality: String,
+    /// `None` in uniform mode, or while `auto` is unresolved and no
+    /// `--split-threshold` is given.
+    pub split_threshold: Option<f64>,
+    pub merge_threshold: Option<f64>,
+}
+
+impl fmt::Display for EffectiveSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "detail {}, quality {}", self.detail, self.quality)?;
+        if let (Some(split), Some(merge)) = (self.split_threshold, self.merge_threshold) {
+            write!(f, ", split threshold {}, merge threshold {}", split, merge)?;
+        }
+        write!(f, ", model {}", self.model.display())
+    }
 }
 
 /// Progress callback type for processing stages.
 #[allow(dead_code)]
 pub type ProgressCallback = Box<dyn FnMut(&str, usize, usize)>;
 
+/// A video split into segments, before any output is written.
+struct Analysis {
+    video_meta: VideoMetadata,
+    frames_analyzed: usize,
+    segments: Vec<SemanticSegment>,
+    /// Sampled frames dropped because they failed to embed.
+    skipped_frames: Vec<usize>,
+    /// Super-scenes over scenes, with `--hierarchical`.
+    scenes: Option<Vec<SceneNode>>,
+    /// Frames left after trimming; side outputs end where they do.
+    frame_range: Range<usize>,
+}
+
+/// Where `frames`, the part of the video analyzed, ends in seconds.
+fn range_end_seconds(video_meta: &VideoMetadata, frames: &Range<usize>) -> f64 {
+    if video_meta.fps > 0.0 {
+        (frames.end as f64 / video_meta.fps).min(video_meta.duration_seconds)
+    } else {
+        video_meta.duration_seconds
+    }
+}
+
+/// Up to `count` frames spread evenly over the `span` frames from `first`.
+///
+/// Frames `decode` fails on are left out.
+fn probe_frames<F>(first: usize, span: usize, count: usize, mut decode: F) -> Vec<Frame>
+where
+    F: FnMut(usize) -> Result<Frame>,
+{
+    (0..count)
+        .filter_map(|i| decode(first + i * span / count).ok())
+        .collect()
+}
+
+/// Stand-in video metadata for frames supplied in memory.
+///
+/// The frame rate is the average over the frames' timestamps, for tools
+/// that convert timestamps back to frame numbers.
+fn in_memory_metadata(frames: &[Frame]) -> VideoMetadata {
+    let (width, height) = frames.first().map_or((0, 0), |f| (f.width, f.height));
+    let duration_seconds = frames
+        .last()
+        .map_or(0.0, |f| f.timestamp_seconds.as_seconds());
+    let fps = if duration_seconds > 0.0 {
+        frames.len().saturating_sub(1) as f64 / duration_seconds
+    } else {
+        0.0
+    };
+    VideoMetadata {
+        path: PathBuf::from(IN_MEMORY_SOURCE),
+        width,
+        height,
+        fps,
+        frame_count: frames.len() as u32,
+        duration_seconds,
+        codec: String::new(),
+        decoded_frame_count: None,
+    }
+}
+
+/// Whether `sampled` is further from `expected` than frame count estimates
+/// explain: [`SAMPLE_COUNT_TOLERANCE`] of it, and never less than one frame.
+fn sample_count_deviates(expected: usize, sampled: usize) -> bool {
+    let tolerance = (expected as f64 * SAMPLE_COUNT_TOLERANCE).max(1.0);
+    expected.abs_diff(sampled) as f64 > tolerance
+}
+
+/// `value` widened to `f64` and rounded to six places, so thresholds print
+/// as 0.85 and 0.9 rather than 0.8500000238 and 0.90000004.
+fn decimal(value: f32) -> f64 {
+    (f64::from(value) * 1e6).round() / 1e6
+}
+
+/// Bytes of memory available for new allocations without swapping, or
+/// `None` where that can't be queried.
+fn available_memory() -> Option<u64> {
+    if !sysinfo::IS_SUPPORTED_SYSTEM {
+        return None;
+    }
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    Some(system.available_memory()).filter(|&bytes| bytes > 0)
+}
+
 /// Main processing pipeline for semantic keyframe extraction.
+///
+/// Serialized, the settings form the run's fingerprint, so a new setting
+/// affects resuming unless it is marked `#[serde(skip)]` for not changing
+/// which stills are extracted.
+#[derive(Serialize)]
 pub struct SceneSplitProcessor {
     detail: DetailLevel,
     quality: QualityPreset,
+    #[serde(skip)]
     output_dir: Option<PathBuf>,
     model_path: PathBuf,
+    anchor_alpha: f32,
+    min_drop: f32,
+    split_threshold: Option<f32>,
+    merge_threshold: Option<f32>,
+    uniform_interval: Option<f64>,
+    normalize_color: bool,
+    preprocess: PreprocessMode,
+    pooling: PoolingMode,
+    sample_offset: usize,
+    frame_budget: Option<usize>,
+    preview_seconds: Option<f64>,
+    bisect_cuts: bool,
+    include_endpoints: bool,
+    sample_strategy: SampleStrategy,
+    sample_seed: u64,
+    #[serde(skip)]
+    similarity_matrix_path: Option<PathBuf>,
+    embeddings_path: Option<PathBuf>,
+    #[serde(skip)]
+    anchor_trace_path: Option<PathBuf>,
+    #[serde(skip)]
+    event_log_path: Option<PathBuf>,
+    #[serde(skip)]
+    edl_path: Option<PathBuf>,
+    #[serde(skip)]
+    poster_path: Option<PathBuf>,
+    multipage_tiff: Option<PathBuf>,
+    #[serde(skip)]
+    poster_strategy: PosterStrategy,
+    #[serde(skip)]
+    timeline_path: Option<PathBuf>,
+    #[serde(skip)]
+    boundaries_dir: Option<PathBuf>,
+    hierarchy: Option<(f32, f32)>,
+    clip_seconds: Option<f64>,
+    every_nth_segment: usize,
+    ssim_threshold: Option<f64>,
+    min_output_spacing: Option<f64>,
+    max_frames: Option<usize>,
+    max_frames_strategy: MaxFramesStrategy,
+    bucket_minutes: Option<u32>,
+    skip_bad_frames: bool,
+    #[serde(skip)]
+    verify_model: bool,
+    #[serde(skip)]
+    ort_opt_level: u8,
+    #[serde(skip)]
+    max_batch: Option<usize>,
+    calibrate: bool,
+    #[serde(skip)]
+    channel_stats: Option<ChannelStats>,
+    preserve_depth: bool,
+    crop: Option<Region>,
+    deinterlace: bool,
+    decode_scale: Option<u32>,
+    trim_start: f64,
+    trim_end: f64,
+    masks: Vec<Region>,
+    input_size: Option<u32>,
+    checksums: bool,
+    pixel_hashes: bool,
+    palette_size: Option<usize>,
+    jpeg_subsampling: JpegSubsampling,
+    #[serde(skip)]
+    require_codecs: Vec<String>,
+    #[serde(skip)]
+    reject_codecs: Vec<String>,
+    #[serde(skip)]
+    incremental: bool,
+    #[serde(skip)]
+    max_frames_in_memory: Option<usize>,
+    #[serde(skip)]
+    min_free_memory: Option<u64>,
+    per_segment: usize,
+    representative_percentile: u8,
+    reject_blur: Option<f64>,
+    #[serde(skip)]
+    resume: bool,
+    #[serde(skip)]
+    min_output_dimension: Option<u32>,
+    #[serde(skip)]
+    strict: bool,
+    #[serde(skip)]
+    allow_empty: bool,
+    #[serde(skip)]
+    verbosity: Verbosity,
 }
 
 impl SceneSplitProcessor {
@@ -53,68 +368,2527 @@ impl SceneSplitProcessor {
             quality,
             output_dir,
             model_path,
+            anchor_alpha: DEFAULT_ANCHOR_ALPHA,
+            min_drop: 0.0,
+            split_threshold: None,
+            merge_threshold: None,
+            uniform_interval: None,
+            normalize_color: false,
+            preprocess: PreprocessMode::default(),
+            pooling: PoolingMode::default(),
+            sample_offset: 0,
+            frame_budget: None,
+            preview_seconds: None,
+            bisect_cuts: false,
+            include_endpoints: false,
+            sample_strategy: SampleStrategy::default(),
+            sample_seed: 0,
+            similarity_matrix_path: None,
+            embeddings_path: None,
+            anchor_trace_path: None,
+            event_log_path: None,
+            edl_path: None,
+            poster_path: None,
+            multipage_tiff: None,
+            poster_strategy: PosterStrategy::default(),
+            timeline_path: None,
+            boundaries_dir: None,
+            hierarchy: None,
+            clip_seconds: None,
+            every_nth_segment: 1,
+            ssim_threshold: None,
+            min_output_spacing: None,
+            max_frames: None,
+            max_frames_strategy: MaxFramesStrategy::default(),
+            bucket_minutes: None,
+            skip_bad_frames: false,
+            verify_model: true,
+            ort_opt_level: DEFAULT_ORT_OPT_LEVEL,
+            max_batch: None,
+            calibrate: false,
+            channel_stats: None,
+            preserve_depth: false,
+            crop: None,
+            deinterlace: false,
+            decode_scale: None,
+            trim_start: 0.0,
+            trim_end: 0.0,
+            masks: Vec::new(),
+            input_size: None,
+            checksums: false,
+            pixel_hashes: false,
+            palette_size: None,
+            jpeg_subsampling: JpegSubsampling::default(),
+            require_codecs: Vec::new(),
+            reject_codecs: Vec::new(),
+            incremental: false,
+            max_frames_in_memory: None,
+            min_free_memory: None,
+            per_segment: 1,
+            representative_percentile: 50,
+            reject_blur: None,
+            resume: false,
+            min_output_dimension: None,
+            strict: false,
+            allow_empty: false,
+            verbosity: Verbosity::default(),
+        }
+    }
+
+    /// Set the EMA weight used when updating the segment anchor.
+    pub fn with_anchor_alpha(mut self, alpha: f32) -> Self {
+        self.anchor_alpha = alpha;
+        self
+    }
+
+    /// Set the minimum similarity drop required to accept a boundary.
+    pub fn with_min_drop(mut self, min_drop: f32) -> Self {
+        self.min_drop = min_drop;
+        self
+    }
+
+    /// Override the detail level's split threshold: similarity to the
+    /// segment anchor below which a frame may open a new segment.
+    pub fn with_split_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.split_threshold = threshold;
+        self
+    }
+
+    /// Override the detail level's merge threshold: similarity to the old
+    /// anchor at which the frame after a candidate boundary cancels it.
+    pub fn with_merge_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.merge_threshold = threshold;
+        self
+    }
+
+    /// Extract one frame every `seconds` instead of analyzing content.
+    ///
+    /// Skips embedding and segmentation entirely, so no model is loaded.
+    pub fn with_uniform_interval(mut self, seconds: Option<f64>) -> Self {
+        self.uniform_interval = seconds;
+        self
+    }
+
+    /// Normalize color casts before embedding (output stills are unaffected).
+    pub fn with_color_normalization(mut self, enabled: bool) -> Self {
+        self.normalize_color = enabled;
+        self
+    }
+
+    /// Start sampling at this frame index instead of frame 0.
+    pub fn with_sample_offset(mut self, frames: usize) -> Self {
+        self.sample_offset = frames;
+        self
+    }
+
+    /// Analyze about this many evenly spaced frames in total, whatever the
+    /// video's length, instead of the quality preset's fixed stride.
+    pub fn with_frame_budget(mut self, frames: Option<usize>) -> Self {
+        self.frame_budget = frames;
+        self
+    }
+
+    /// Analyze only about this many seconds of the video, in short windows
+    /// spread evenly over it, for a quick approximate result.
+    ///
+    /// The metadata is marked as a preview.
+    pub fn with_preview_sample(mut self, seconds: Option<f64>) -> Self {
+        self.preview_seconds = seconds;
+        self
+    }
+
+    /// Leave a branded intro and outro of these many seconds out of analysis
+    /// and output.
+    ///
+    /// The metadata records the trim.
+    pub fn with_trim(mut self, start_seconds: f64, end_seconds: f64) -> Self {
+        self.trim_start = start_seconds;
+        self.trim_end = end_seconds;
+        self
+    }
+
+    /// Always give the first sampled frame and the video's final frame
+    /// their own segments.
+    ///
+    /// Applies to batch processing only.
+    pub fn with_include_endpoints(mut self, enabled: bool) -> Self {
+        self.include_endpoints = enabled;
+        self
+    }
+
+    /// Pinpoint each cut to the exact frame by bisecting between samples.
+    ///
+    /// Applies to batch processing; incremental and uniform runs keep the
+    /// sampled boundaries.
+    pub fn with_bisect_cuts(mut self, enabled: bool) -> Self {
+        self.bisect_cuts = enabled;
+        self
+    }
+
+    /// Choose which frame of each stride is sampled.
+    ///
+    /// `Keyframe` embeds only the container's keyframes instead of sampling
+    /// at the stride; it falls back to uniform sampling, with a warning, when
+    /// the backend doesn't report keyframes, and applies to batch processing
+    /// only.
+    pub fn with_sample_strategy(mut self, strategy: SampleStrategy) -> Self {
+        self.sample_strategy = strategy;
+        self
+    }
+
+    /// Seed for the `Random` sample strategy's picks.
+    pub fn with_sample_seed(mut self, seed: u64) -> Self {
+        self.sample_seed = seed;
+        self
+    }
+
+    /// Keep only segments `0, k, 2k, ...` after segmentation.
+    ///
+    /// Applies to batch processing; incremental runs keep every segment.
+    pub fn with_every_nth_segment(mut self, k: usize) -> Self {
+        self.every_nth_segment = k;
+        self
+    }
+
+    /// Drop stills whose SSIM to the previous kept still is at least
+    /// `threshold`, merging their segments into it.
+    ///
+    /// Applies to batch processing; incremental runs keep every segment.
+    pub fn with_ssim_threshold(mut self, threshold: Option<f64>) -> Self {
+        self.ssim_threshold = threshold;
+        self
+    }
+
+    /// Drop stills less than `seconds` after the previous kept still, once
+    /// segmentation and the other thinning are done.
+    ///
+    /// Applies to batch processing; incremental runs keep every segment.
+    pub fn with_min_output_spacing(mut self, seconds: Option<f64>) -> Self {
+        self.min_output_spacing = seconds;
+        self
+    }
+
+    /// Write at most `n` stills, chosen by the max-frames strategy once the
+    /// other thinning is done.
+    ///
+    /// Applies to batch processing; incremental runs keep every segment.
+    pub fn with_max_frames(mut self, n: Option<usize>) -> Self {
+        self.max_frames = n;
+        self
+    }
+
+    /// How `with_max_frames` chooses which segments to keep.
+    pub fn with_max_frames_strategy(mut self, strategy: MaxFramesStrategy) -> Self {
+        self.max_frames_strategy = strategy;
+        self
+    }
+
+    /// Group stills into subdirectories spanning this many minutes each.
+    pub fn with_bucket_minutes(mut self, minutes: Option<u32>) -> Self {
+        self.bucket_minutes = minutes;
+        self
+    }
+
+    /// Drop frames that fail to embed, with a warning, instead of aborting.
+    ///
+    /// Dropped frames are left out of segmentation and listed in the
+    /// metadata's `skipped_frames`.
+    pub fn with_skip_bad_frames(mut self, enabled: bool) -> Self {
+        self.skip_bad_frames = enabled;
+        self
+    }
+
+    /// ONNX Runtime graph optimization level, from 0 (off) to 3 (the
+    /// default). Lower levels work around models that load slowly or crash
+    /// when fully optimized.
+    pub fn with_ort_opt_level(mut self, level: u8) -> Self {
+        self.ort_opt_level = level;
+        self
+    }
+
+    /// Normalize model input with channel statistics measured on the video
+    /// instead of ImageNet's, for footage unlike natural photos.
+    ///
+    /// Takes effect in [`resolve_calibration`](Self::resolve_calibration).
+    pub fn with_calibration(mut self, enabled: bool) -> Self {
+        self.calibrate = enabled;
+        self
+    }
+
+    /// Embed at most this many frames per model run, for devices with little
+    /// memory. Batches still halve if one runs out of memory.
+    pub fn with_max_batch(mut self, max: Option<usize>) -> Self {
+        self.max_batch = max;
+        self
+    }
+
+    /// Run a test inference as soon as the model loads (on by default), so
+    /// a model that can't execute fails before any frames are decoded.
+    pub fn with_verify_model(mut self, enabled: bool) -> Self {
+        self.verify_model = enabled;
+        self
+    }
+
+    /// Save stills of 16-bit sources as 16-bit PNG instead of 8-bit JPEG.
+    ///
+    /// Embedding still works on the 8-bit version of each frame.
+    pub fn with_preserve_depth(mut self, enabled: bool) -> Self {
+        self.preserve_depth = enabled;
+        self
+    }
+
+    /// Crop every frame to this region before embedding and writing.
+    pub fn with_crop(mut self, crop: Option<Region>) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    /// Deinterlace every decoded frame before embedding and writing.
+    pub fn with_deinterlace(mut self, enabled: bool) -> Self {
+        self.deinterlace = enabled;
+        self
+    }
+
+    /// Decode frames shrunk to this long edge in pixels for analysis, then
+    /// re-read only the chosen stills at full resolution.
+    pub fn with_decode_scale(mut self, long_edge: Option<u32>) -> Self {
+        self.decode_scale = long_edge;
+        self
+    }
+
+    /// Blank these regions (in cropped frame pixels) of the copy of each
+    /// frame that is embedded. Written stills keep them.
+    pub fn with_masks(mut self, masks: Vec<Region>) -> Self {
+        self.masks = masks;
+        self
+    }
+
+    /// Feed the model square inputs of this many pixels instead of the size
+    /// declared by the model (224 if it declares none).
+    pub fn with_input_size(mut self, size: Option<u32>) -> Self {
+        self.input_size = size;
+        self
+    }
+
+    /// Write a `SHA256SUMS` manifest of the finished output.
+    pub fn with_checksums(mut self, enabled: bool) -> Self {
+        self.checksums = enabled;
+        self
+    }
+
+    /// Record a SHA-256 of each still's pixels, independent of the image
+    /// encoder, in its metadata.
+    pub fn with_pixel_hashes(mut self, enabled: bool) -> Self {
+        self.pixel_hashes = enabled;
+        self
+    }
+
+    /// Record each still's `colors` dominant colors in its metadata.
+    pub fn with_palette(mut self, colors: Option<usize>) -> Self {
+        self.palette_size = colors;
+        self
+    }
+
+    /// Encode JPEG stills with this chroma subsampling (default 4:4:4).
+    pub fn with_jpeg_subsampling(mut self, subsampling: JpegSubsampling) -> Self {
+        self.jpeg_subsampling = subsampling;
+        self
+    }
+
+    /// Dump the pairwise similarity of all sampled frames to this path.
+    ///
+    /// Written during batch processing only; see [`write_similarity_matrix`]
+    /// for the file format.
+    pub fn with_similarity_matrix(mut self, path: Option<PathBuf>) -> Self {
+        self.similarity_matrix_path = path;
+        self
+    }
+
+    /// Dump the embedding of every sampled frame to this path, for
+    /// segmenting again offline with `scenesplit segment`.
+    ///
+    /// Written during batch processing only; see [`EmbeddingDump`].
+    pub fn with_embedding_dump(mut self, path: Option<PathBuf>) -> Self {
+        self.embeddings_path = path;
+        self
+    }
+
+    /// Record how the segmenter's anchor evolved and write it to this path.
+    ///
+    /// Written during batch processing only; see [`write_anchor_trace`] for
+    /// the file format.
+    pub fn with_anchor_trace(mut self, path: Option<PathBuf>) -> Self {
+        self.anchor_trace_path = path;
+        self
+    }
+
+    /// Export the final segments as a CMX3600 EDL to this path.
+    ///
+    /// Written during batch processing only; see [`write_edl`].
+    pub fn with_edl(mut self, path: Option<PathBuf>) -> Self {
+        self.edl_path = path;
+        self
+    }
+
+    /// Write the segments with their stills as a JSON timeline to this path.
+    ///
+    /// Written during batch processing only; see [`timeline_entries`].
+    pub fn with_timeline(mut self, path: Option<PathBuf>) -> Self {
+        self.timeline_path = path;
+        self
+    }
+
+    /// Also segment at a coarse and a fine split threshold, recording the
+    /// fine scenes nested in the coarse super-scenes in the metadata.
+    ///
+    /// Stills still come from the regular segmentation. Batch processing
+    /// only; see [`scene_tree`].
+    pub fn with_hierarchy(mut self, thresholds: Option<(f32, f32)>) -> Self {
+        self.hierarchy = thresholds;
+        self
+    }
+
+    /// Save the two adjacent frames at each segment boundary side by side in
+    /// this directory.
+    ///
+    /// Written during batch processing only; see [`write_boundaries`].
+    pub fn with_boundary_export(mut self, dir: Option<PathBuf>) -> Self {
+        self.boundaries_dir = dir;
+        self
+    }
+
+    /// Write the stills as the pages of one TIFF at this path instead of
+    /// image files in the output directory.
+    ///
+    /// Written during batch processing only.
+    pub fn with_multipage_tiff(mut self, path: Option<PathBuf>) -> Self {
+        self.multipage_tiff = path;
+        self
+    }
+
+    /// Save the still most representative of the whole video to this path,
+    /// chosen by the poster strategy.
+    ///
+    /// Written during batch processing only.
+    pub fn with_poster(mut self, path: Option<PathBuf>) -> Self {
+        self.poster_path = path;
+        self
+    }
+
+    /// Choose the poster by `strategy`; see
+    /// [`poster_segment`](crate::segmentation::poster_segment).
+    pub fn with_poster_strategy(mut self, strategy: PosterStrategy) -> Self {
+        self.poster_strategy = strategy;
+        self
+    }
+
+    /// Save a clip this many seconds long, centered on each segment's
+    /// representative, next to its still.
+    ///
+    /// Written during batch processing only.
+    pub fn with_clips(mut self, seconds: Option<f64>) -> Self {
+        self.clip_seconds = seconds;
+        self
+    }
+
+    /// Append a JSON line per pipeline milestone to this path as processing
+    /// proceeds.
+    pub fn with_event_log(mut self, path: Option<PathBuf>) -> Self {
+        self.event_log_path = path;
+        self
+    }
+
+    /// Only accept videos whose codec FourCC is in `required` (if non-empty)
+    /// and not in `rejected`. Matching is case-insensitive.
+    pub fn with_codec_filter(mut self, required: Vec<String>, rejected: Vec<String>) -> Self {
+        self.require_codecs = required;
+        self.reject_codecs = rejected;
+        self
+    }
+
+    /// Set how non-square frames are fitted to the model input.
+    pub fn with_preprocess(mut self, mode: PreprocessMode) -> Self {
+        self.preprocess = mode;
+        self
+    }
+
+    /// Set how models that output a spatial feature map are pooled.
+    pub fn with_pooling(mut self, mode: PoolingMode) -> Self {
+        self.pooling = mode;
+        self
+    }
+
+    /// Write each still (and updated metadata) as soon as its segment closes.
+    ///
+    /// Output appears on disk progressively instead of after the whole
+    /// embedding pass. The final result is identical to batch mode.
+    pub fn with_incremental_output(mut self, enabled: bool) -> Self {
+        self.incremental = enabled;
+        self
+    }
+
+    /// Pick each segment's representative at this timeline percentile (0-100).
+    pub fn with_representative_percentile(mut self, percentile: u8) -> Self {
+        self.representative_percentile = percentile;
+        self
+    }
+
+    /// Replace representatives blurrier than this sharpness (variance of
+    /// the Laplacian) with the nearest sharp enough frame of the segment.
+    pub fn with_reject_blur(mut self, threshold: Option<f64>) -> Self {
+        self.reject_blur = threshold;
+        self
+    }
+
+    /// Keep up to `n` maximally distinct frames per segment.
+    pub fn with_per_segment(mut self, n: usize) -> Self {
+        self.per_segment = n;
+        self
+    }
+
+    /// Stream frames through embedding instead of buffering them all when
+    /// the video would sample more than `max` frames.
+    ///
+    /// Streaming uses the incremental pipeline, so features that need random
+    /// access to every frame are unavailable for such videos.
+    pub fn with_max_frames_in_memory(mut self, max: Option<usize>) -> Self {
+        self.max_frames_in_memory = max;
+        self
+    }
+
+    /// Stream frames through embedding when buffering them would leave less
+    /// than `bytes` of memory available, checking again as frames are
+    /// buffered and switching over if it runs low.
+    pub fn with_min_free_memory(mut self, bytes: Option<u64>) -> Self {
+        self.min_free_memory = bytes;
+        self
+    }
+
+    /// Reuse a previous run in the output directory when its settings match.
+    ///
+    /// A complete run is returned as-is. A partial incremental run continues
+    /// after its last finished segment when incremental output is enabled.
+    pub fn with_resume(mut self, enabled: bool) -> Self {
+        self.resume = enabled;
+        self
+    }
+
+    /// Warn when stills would be smaller than `pixels` on their short side.
+    pub fn with_min_output_dimension(mut self, pixels: Option<u32>) -> Self {
+        self.min_output_dimension = pixels;
+        self
+    }
+
+    /// Turn output quality warnings into errors.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Accept a run that extracts no stills, with a warning, instead of
+    /// failing with [`Error::NoStillsExtracted`].
+    pub fn with_allow_empty(mut self, allow: bool) -> Self {
+        self.allow_empty = allow;
+        self
+    }
+
+    /// Set which warnings are printed while processing.
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Measure the channel statistics used for `--calibrate` on an evenly
+    /// spaced sample of frames; see [`channel_stats`].
+    pub fn resolve_calibration(mut self, video_path: &Path) -> Result<Self> {
+        if !self.calibrate || self.uniform_interval.is_some() {
+            return Ok(self);
+        }
+
+        let (mut video, _) = self.open_video(video_path)?;
+        let range = video.frame_range()?;
+        let first = self.sample_offset.max(range.start);
+        let span = self.sampled_span(&range);
+        let count = CALIBRATION_PROBE_FRAMES.min(span);
+        let frames = probe_frames(first, span, count, |i| video.get_frame_at(i));
+
+        let stats = channel_stats(&frames);
+        if self.verbosity.shows_progress() {
+            println!(
+                "Calibrated on {} frames: mean {:.3?}, std {:.3?}",
+                frames.len(),
+                stats.mean,
+                stats.std
+            );
+        }
+        self.channel_stats = Some(stats);
+        Ok(self)
+    }
+
+    /// Replace `--detail auto` with a level chosen from a quick probe of the
+    /// video's pacing.
+    ///
+    /// Embeds a sparse, evenly spaced sample of frames and measures how
+    /// often consecutive samples differ; see [`DetailLevel::for_change_share`].
+    /// Probe frames that fail to decode are left out; it is an error only
+    /// when none decode.
+    pub fn resolve_detail(mut self, video_path: &Path) -> Result<Self> {
+        if self.detail != DetailLevel::Auto || self.uniform_interval.is_some() {
+            return Ok(self);
+        }
+
+        let (mut video, video_meta) = self.open_video(video_path)?;
+        let range = video.frame_range()?;
+        let first = self.sample_offset.max(range.start);
+        let span = self.sampled_span(&range);
+        let count = AUTO_DETAIL_PROBE_FRAMES.min(span);
+        let frames = probe_frames(first, span, count, |i| video.get_frame_at(i));
+        if frames.is_empty() {
+            return Err(Error::VideoDecode {
+                path: video_path.to_path_buf(),
+                reason: format!("none of the {} --detail auto probe frames decoded", count),
+            });
+        }
+        let mut embedding_model = self.embedding_model(Some(self.cropped_size(&video_meta)))?;
+        let embedded = self.embed(&mut embedding_model, &frames, &mut Vec::new(), &mut |_| {})?;
+
+        let share = change_share(&embedded, DetailLevel::Summary.similarity_threshold());
+        self.detail = DetailLevel::for_change_share(share);
+        if self.verbosity.shows_progress() {
+            println!(
+                "Auto detail: {} ({:.0}% of {} probe gaps show a scene change)",
+                self.detail_label(),
+                share * 100.0,
+                embedded.len().saturating_sub(1)
+            );
         }
+        Ok(self)
+    }
+
+    /// The detail level, `Auto` until [`Self::resolve_detail`] runs.
+    pub fn detail(&self) -> DetailLevel {
+        self.detail
     }
 
     /// Process a video file and extract semantic keyframes.
-    pub fn process<F>(
-        &self,
-        video_path: &Path,
-        mut progress_callback: Option<F>,
-    ) -> Result<ProcessingResult>
+    ///
+    /// `progress` is called with each milestone as it happens.
+    pub fn process<F>(&self, video_path: &Path, mut progress: F) -> Result<ProcessingResult>
     where
-        F: FnMut(&str, usize, usize),
+        F: FnMut(ProgressEvent),
     {
-        // Stage 1: Load video
-        Self::report_progress(&mut progress_callback, "Loading video", 0, 4);
-        let mut video = VideoLoader::new(video_path)?;
-        let video_meta = video.metadata()?.clone();
+        let mut previous = if self.resume {
+            self.find_previous_run(video_path)
+        } else {
+            None
+        };
+        if let Some(finished) = previous.as_ref().filter(|p| p.complete) {
+            let side_outputs = self.side_outputs();
+            if side_outputs.is_empty() {
+                return self.resumed_result(video_path, finished);
+            }
+            self.warn(&format!(
+                "not reusing the finished run: {} need a fresh analysis; starting over",
+                side_outputs.join(", ")
+            ));
+            previous = None;
+        }
 
-        // Stage 2: Extract frames
-        Self::report_progress(&mut progress_callback, "Extracting frames", 1, 4);
-        let frames = video.extract_frames::<fn(usize, usize)>(self.quality, None)?;
+        let events = self.event_log()?;
+        // A preview samples too few frames to need streaming
+        if self.uniform_interval.is_none()
+            && self.preview_seconds.is_none()
+            && (self.incremental || self.exceeds_frame_budget(video_path)?)
+        {
+            return self.process_incremental(video_path, previous, &events, &mut progress);
+        }
+        if previous.is_some() {
+            self.warn(
+                "previous run is incomplete; use --incremental to continue it, starting over",
+            );
+        }
 
-        // Stage 3: Compute embeddings
-        Self::report_progress(&mut progress_callback, "Computing embeddings", 1, 4);
-        let mut embedding_model = EmbeddingModel::new(&self.model_path, self.quality)?;
-        let embedded_frames =
-            embedding_model.compute_embeddings_batch::<fn(usize, usize)>(&frames, None)?;
+        let analysis = match self.detect_segments(video_path, &events, &mut progress) {
+            Err(Error::LowMemory { .. }) => {
+                // The buffered frames are gone; decode again without keeping them
+                self.warn_streaming("free memory fell below --min-free-mem while buffering frames");
+                return self.process_incremental(video_path, None, &events, &mut progress);
+            }
+            analysis => analysis?,
+        };
+        self.write_analysis(video_path, analysis, &events, &mut progress)
+    }
 
-        // Stage 4: Segment by semantic similarity
-        Self::report_progress(&mut progress_callback, "Detecting semantic changes", 2, 4);
-        let segmenter = SemanticSegmenter::new(self.detail);
-        let segments = segmenter.segment::<fn(usize, usize)>(&embedded_frames, None);
+    /// Process frames decoded elsewhere, without opening a video.
+    ///
+    /// Each image comes with its timestamp in seconds, and frames are
+    /// numbered in the order given. Every frame is embedded and segmented by
+    /// semantic similarity, then output is written as by `process`. Sampling,
+    /// trimming, decode options such as `deinterlace` and options that
+    /// re-read the video (`bisect_cuts`, `include_endpoints`) don't apply,
+    /// and clips and boundary frames can't be written.
+    #[allow(dead_code)]
+    pub fn process_frames<F>(
+        &self,
+        frames: Vec<(DynamicImage, f64)>,
+        mut progress: F,
+    ) -> Result<ProcessingResult>
+    where
+        F: FnMut(ProgressEvent),
+    {
+        if self.clip_seconds.is_some() {
+            return Err(Error::Config(
+                "clips need a video file to be cut from".to_string(),
+            ));
+        }
+        if self.boundaries_dir.is_some() {
+            return Err(Error::Config(
+                "boundary frames need a video file to be decoded from".to_string(),
+            ));
+        }
+        if let (Some(crop), Some((image, _))) = (self.crop, frames.first()) {
+            crop.check_fits("crop", image.width(), image.height())?;
+        }
+        let frames: Vec<Frame> = frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, (image, seconds))| {
+                let image = match self.crop {
+                    Some(c) => image.crop_imm(c.x, c.y, c.width, c.height),
+                    None => image,
+                };
+                Frame::from_image(image, index, Timestamp::from_seconds(seconds))
+            })
+            .collect();
+        let video_meta = in_memory_metadata(&frames);
+        for mask in &self.masks {
+            mask.check_fits("mask", video_meta.width, video_meta.height)?;
+        }
 
-        // Stage 5: Write output
-        Self::report_progress(&mut progress_callback, "Writing output", 3, 4);
-        let writer = OutputWriter::new(self.output_dir.clone());
-        let frame_metadata = writer.write_frames::<fn(usize, usize)>(&segments, None)?;
+        let events = self.event_log()?;
+        Self::emit_video_opened(&events, &video_meta)?;
+        progress(ProgressEvent::VideoLoaded {
+            metadata: video_meta.clone(),
+        });
+        events.emit(Event::FramesExtracted {
+            count: frames.len(),
+        })?;
 
-        let metadata_path = writer.write_metadata(
-            &video_meta,
-            frame_metadata,
-            &format!("{:?}", self.detail).to_lowercase(),
-            &format!("{:?}", self.quality).to_lowercase(),
+        let mut embedding_model = self.embedding_model(None)?;
+        let mut skipped_frames = Vec::new();
+        let embedded_frames = self.embed(
+            &mut embedding_model,
+            &frames,
+            &mut skipped_frames,
+            &mut progress,
         )?;
+        let segments = self
+            .segmenter()
+            .segment::<fn(usize, usize)>(&embedded_frames, None);
+
+        let video_path = video_meta.path.clone();
+        let analysis = Analysis {
+            frame_range: 0..video_meta.frame_count as usize,
+            video_meta,
+            frames_analyzed: frames.len(),
+            segments,
+            skipped_frames,
+            scenes: self.scene_tree(&embedded_frames),
+        };
+        self.write_analysis(&video_path, analysis, &events, &mut progress)
+    }
+
+    /// Thin the analyzed segments and write their stills and every
+    /// requested side output.
+    fn write_analysis(
+        &self,
+        video_path: &Path,
+        mut analysis: Analysis,
+        events: &EventLog,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<ProcessingResult> {
+        let segments_detected = analysis.segments.len();
+        // Boundaries are the detected cuts, including those of segments
+        // thinning drops
+        let boundaries = boundary_frame_pairs(&analysis.segments);
+        let (segments, near_duplicates_removed) =
+            self.thin_segments(std::mem::take(&mut analysis.segments))?;
+        analysis.segments = segments;
+        let segments = &analysis.segments;
+        // Only segments that make it into the output are reported
+        Self::emit_segments(events, segments, progress)?;
+        self.check_not_empty(video_path, segments.len())?;
 
-        Self::report_progress(&mut progress_callback, "Complete", 4, 4);
+        if let Some(path) = &self.edl_path {
+            write_edl(
+                path,
+                segments,
+                &analysis.video_meta,
+                analysis.frame_range.end,
+            )?;
+            progress(ProgressEvent::OutputWritten { path: path.clone() });
+        }
+        if let Some(path) = &self.poster_path {
+            write_poster(path, segments, self.poster_strategy)?;
+            progress(ProgressEvent::OutputWritten { path: path.clone() });
+        }
+        if let Some(dir) = &self.boundaries_dir {
+            let mut video = VideoLoader::new(video_path)?
+                .with_crop(self.crop)
+                .with_deinterlace(self.deinterlace);
+            for path in write_boundaries(&mut video, &boundaries, dir)? {
+                progress(ProgressEvent::OutputWritten { path });
+            }
+        }
+        if let Some(first) = segments.first() {
+            self.check_output_size(&first.representative_frame.frame)?;
+            self.check_depth(&first.representative_frame.frame);
+        }
+        let (output_dir, metadata_path, frames_extracted) =
+            self.write_output(&analysis, events, progress)?;
+        events.emit(Event::Finished { frames_extracted })?;
 
         Ok(ProcessingResult {
-            video_metadata: video_meta,
-            total_frames_processed: frames.len(),
-            segments_detected: segments.len(),
-            frames_extracted: segments.len(),
-            output_dir: writer.output_dir().to_path_buf(),
+            video_metadata: analysis.video_meta,
+            total_frames_processed: analysis.frames_analyzed,
+            segments_detected,
+            frames_extracted,
+            output_dir,
             metadata_path,
+            resumed: false,
+            settings: self.effective_settings(),
+            near_duplicates_removed,
         })
     }
 
-    fn report_progress<F>(callback: &mut Option<F>, stage: &str, current: usize, total: usize)
+    /// Process a video without touching disk.
+    ///
+    /// Returns a summary of the run and each still's metadata together with
+    /// its JPEG bytes, in the order `process` would write them.
+    pub fn process_in_memory<F>(
+        &self,
+        video_path: &Path,
+        mut progress: F,
+    ) -> Result<(RunSummary, Vec<EncodedFrame>)>
     where
-        F: FnMut(&str, usize, usize),
+        F: FnMut(ProgressEvent),
     {
-        if let Some(ref mut cb) = callback {
-            cb(stage, current, total);
+        let events = self.event_log()?;
+        let analysis = self.detect_segments(video_path, &events, &mut progress)?;
+        let segments_detected = analysis.segments.len();
+        let (segments, near_duplicates_removed) = self.thin_segments(analysis.segments)?;
+        // Only segments that make it into the output are reported
+        Self::emit_segments(&events, &segments, &mut progress)?;
+        self.check_not_empty(video_path, segments.len())?;
+
+        if let Some(first) = segments.first() {
+            self.check_output_size(&first.representative_frame.frame)?;
+            self.check_depth(&first.representative_frame.frame);
         }
+        let stills = OutputWriter::encode_frames(&segments, self.jpeg_subsampling)?;
+        events.emit(Event::Finished {
+            frames_extracted: stills.len(),
+        })?;
+
+        let summary = RunSummary::new(
+            &analysis.video_meta,
+            analysis.frames_analyzed,
+            segments_detected,
+            stills.len(),
+            near_duplicates_removed,
+            None,
+            self.effective_settings(),
+        );
+        Ok((summary, stills))
+    }
+
+    /// Sample and embed a video's frames without segmenting them.
+    ///
+    /// Embeddings don't depend on the segmentation settings, so callers can
+    /// resegment the result as often as they like.
+    pub fn embed_frames(&self, video_path: &Path) -> Result<Vec<EmbeddedFrame>> {
+        let (mut video, video_meta) = self.open_video(video_path)?;
+        let mut embedding_model = self.embedding_model(Some(self.cropped_size(&video_meta)))?;
+        let range = video.frame_range()?;
+        let frames = video.extract_frames::<fn(usize, usize)>(
+            self.sample_rate(&range),
+            self.sample_offset,
+            None,
+        )?;
+        self.embed(&mut embedding_model, &frames, &mut Vec::new(), &mut |_| {})
+    }
+
+    /// Load the video and split it into segments, reporting progress up to
+    /// segmentation. Segments are reported once thinned, by the caller.
+    ///
+    /// Uniform mode samples on a time grid; otherwise frames are embedded and
+    /// segmented by semantic similarity.
+    fn detect_segments(
+        &self,
+        video_path: &Path,
+        events: &EventLog,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Analysis> {
+        // Stage 1: Load video
+        let (mut video, video_meta) = self.open_video(video_path)?;
+        Self::emit_video_opened(events, &video_meta)?;
+        progress(ProgressEvent::VideoLoaded {
+            metadata: video_meta.clone(),
+        });
+
+        // Stage 2: Extract frames
+        if let Some(interval) = self.uniform_interval {
+            let frames = video.extract_frames_uniform(
+                interval,
+                self.sample_offset,
+                Some(|done, total| progress(ProgressEvent::FramesExtracted { done, total })),
+            )?;
+            let frames_analyzed = frames.len();
+            events.emit(Event::FramesExtracted {
+                count: frames_analyzed,
+            })?;
+            let frame_range = video.frame_range()?;
+            let mut segments =
+                uniform_segments(frames, range_end_seconds(&video_meta, &frame_range));
+            self.restore_segments(video_path, &mut segments)?;
+            return Ok(Analysis {
+                video_meta,
+                frames_analyzed,
+                segments,
+                skipped_frames: Vec::new(),
+                scenes: None,
+                frame_range,
+            });
+        }
+        // Load (and verify) the model before the expensive decode, so a
+        // broken model fails fast
+        let mut embedding_model = self.embedding_model(Some(self.cropped_size(&video_meta)))?;
+        let frames = self.sample_frames(&mut video, progress)?;
+        events.emit(Event::FramesExtracted {
+            count: frames.len(),
+        })?;
+        self.check_interlacing(&frames)?;
+        // Picks up how many frames decoded if the stream ended early
+        let video_meta = video.metadata()?.clone();
+
+        // Stage 3: Compute embeddings
+        let mut skipped_frames = Vec::new();
+        let mut embedded_frames =
+            self.embed(&mut embedding_model, &frames, &mut skipped_frames, progress)?;
+        if self.include_endpoints {
+            self.append_final_frame(&mut video, &mut embedding_model, &mut embedded_frames)?;
+        }
+
+        if let Some(path) = &self.similarity_matrix_path {
+            let n = embedded_frames.len();
+            if n > SIMILARITY_MATRIX_WARN_FRAMES {
+                self.warn(&format!(
+                    "similarity matrix for {} frames has {} entries and will be large",
+                    n,
+                    n * n
+                ));
+            }
+            write_similarity_matrix(path, &embedded_frames)?;
+        }
+        if let Some(path) = &self.embeddings_path {
+            let quality = format!("{:?}", self.quality).to_lowercase();
+            EmbeddingDump::new(&video_meta, &quality, &embedded_frames).write(path)?;
+        }
+
+        // Stage 4: Segment by semantic similarity
+        let segmenter = self
+            .segmenter()
+            .with_anchor_trace(self.anchor_trace_path.is_some());
+        let (mut segments, trace) =
+            segmenter.segment_with_trace::<fn(usize, usize)>(&embedded_frames, None);
+        if let Some(path) = &self.anchor_trace_path {
+            write_anchor_trace(path, &trace)?;
+        }
+        if self.bisect_cuts {
+            bisect_boundaries(&mut segments, &embedded_frames, video_meta.fps, |index| {
+                let embedded = video
+                    .get_frame_at(index)
+                    .and_then(|frame| embedding_model.compute_embedding(&frame));
+                match embedded {
+                    Ok(embedded) => Ok(Some(embedded.embedding)),
+                    // A bad frame ends bisection of this cut where it got to
+                    Err(e) if self.skip_bad_frames => {
+                        self.warn(&format!("skipping frame {}: {}", index, e));
+                        Ok(None)
+                    }
+                    Err(e) => Err(e),
+                }
+            })?;
+        }
+        if self.include_endpoints {
+            segments = include_endpoints(segments, &embedded_frames);
+        }
+        self.restore_segments(video_path, &mut segments)?;
+
+        Ok(Analysis {
+            video_meta,
+            frames_analyzed: frames.len(),
+            segments,
+            skipped_frames,
+            scenes: self.scene_tree(&embedded_frames),
+            frame_range: video.frame_range()?,
+        })
+    }
+
+    /// Re-read every segment's stills at full resolution when frames were
+    /// downscaled at decode.
+    fn restore_segments(&self, video_path: &Path, segments: &mut [SemanticSegment]) -> Result<()> {
+        if let Some(mut video) = self.full_resolution_loader(video_path)? {
+            for segment in segments {
+                Self::restore_full_resolution(&mut video, segment)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Embed, segment and write output in lockstep, one batch at a time.
+    ///
+    /// With a partial `previous` run, frames up to its last finished segment
+    /// are skipped and numbering continues where it stopped.
+    fn process_incremental(
+        &self,
+        video_path: &Path,
+        previous: Option<OutputMetadata>,
+        events: &EventLog,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<ProcessingResult> {
+        // Decoding, embedding and writing all report from nested closures
+        let progress = RefCell::new(progress);
+        let report = |event| (*progress.borrow_mut())(event);
+
+        // Stage 1: Load video
+        let (mut video, video_meta) = self.open_video(video_path)?;
+        Self::emit_video_opened(events, &video_meta)?;
+        report(ProgressEvent::VideoLoaded {
+            metadata: video_meta.clone(),
+        });
+
+        // Stage 2: Load model
+        let mut embedding_model = self.embedding_model(Some(self.cropped_size(&video_meta)))?;
+        let segmenter = self.segmenter();
+        let mut state = segmenter.start();
+
+        let writer = self.writer();
+        writer.prepare()?;
+        let mut full_resolution = self.full_resolution_loader(video_path)?;
+
+        // Continue after the last segment a previous partial run finished
+        let (mut frame_metadata, mut skipped_frames, resume_after) = match previous {
+            Some(previous) => {
+                let resume_after = previous
+                    .frames
+                    .iter()
+                    .map(|f| f.end_frame_timestamp)
+                    .reduce(Timestamp::max);
+                (previous.frames, previous.skipped_frames, resume_after)
+            }
+            None => (Vec::new(), Vec::new(), None),
+        };
+        let mut segments_detected = frame_metadata
+            .last()
+            .map(|f| f.segment_index + 1)
+            .unwrap_or(0);
+        let mut write_segment = |mut segment: SemanticSegment,
+                                 skipped_frames: &[usize]|
+         -> Result<()> {
+            segment.index = segments_detected;
+            if let Some(video) = full_resolution.as_mut() {
+                Self::restore_full_resolution(video, &mut segment)?;
+            }
+            Self::emit_segments(events, std::slice::from_ref(&segment), &mut |e| report(e))?;
+            let written = writer
+                .write_segment(&segment, frame_metadata.len() + 1)
+                .map_err(|e| writer.explain_write_error(e, frame_metadata.len()))?;
+            Self::emit_written(events, &written)?;
+            for still in &written {
+                report(ProgressEvent::OutputWritten {
+                    path: writer.output_dir().join(&still.filename),
+                });
+            }
+            frame_metadata.extend(written);
+            let mut metadata =
+                self.build_metadata(&video_meta, frame_metadata.clone(), skipped_frames.to_vec());
+            metadata.complete = false;
+            writer.write_metadata(&metadata)?;
+            segments_detected += 1;
+            Ok(())
+        };
+
+        let range = video.frame_range()?;
+        let sample_rate = self.sample_rate(&range);
+        let frames_expected = expected_sample_count(&range, self.sample_offset, sample_rate);
+        let mut frames_sampled = 0;
+        let mut embed_batch = |batch: &mut Vec<Frame>| -> Result<()> {
+            if batch.is_empty() {
+                return Ok(());
+            }
+            if frames_sampled == 0 {
+                self.check_interlacing(batch)?;
+            }
+            frames_sampled += batch.len();
+            events.emit(Event::FramesExtracted {
+                count: frames_sampled,
+            })?;
+            let embedded = self.embed(
+                &mut embedding_model,
+                batch,
+                &mut skipped_frames,
+                &mut |_| {},
+            )?;
+            report(ProgressEvent::EmbeddingProgress {
+                done: frames_sampled,
+                total: frames_expected.max(frames_sampled),
+            });
+            batch.clear();
+            for frame in embedded {
+                if let Some(segment) = state.push(frame) {
+                    write_segment(segment, &skipped_frames)?;
+                }
+            }
+            Ok(())
+        };
+
+        // Stage 3: Decode, embed and segment one batch at a time, writing
+        // each segment as it closes. Only the open segment's frames and the
+        // current batch are held in memory.
+        let batch_size = capped_batch_size(self.quality, self.max_batch)
+            .min(self.max_frames_in_memory.unwrap_or(usize::MAX))
+            .max(1);
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut frames_decoded = 0;
+        let mut total_frames_processed = 0;
+        let mut checked_first = false;
+        video.for_each_frame(
+            sample_rate,
+            self.sample_offset,
+            Some(|done, total| report(ProgressEvent::FramesExtracted { done, total })),
+            |frame| {
+                frames_decoded += 1;
+                // Continue after the last segment a previous partial run finished
+                if resume_after.is_some_and(|t| frame.timestamp_seconds <= t) {
+                    return Ok(());
+                }
+                total_frames_processed += 1;
+                // Every still shares the video's resolution
+                if !checked_first {
+                    let full = match self.full_resolution_loader(video_path)? {
+                        Some(mut video) => video.get_frame_at(frame.index)?,
+                        None => frame.clone(),
+                    };
+                    self.check_output_size(&full)?;
+                    self.check_depth(&full);
+                    checked_first = true;
+                }
+                batch.push(frame);
+                if batch.len() >= batch_size {
+                    embed_batch(&mut batch)?;
+                }
+                Ok(())
+            },
+        )?;
+        embed_batch(&mut batch)?;
+        self.check_truncation(&mut video)?;
+        self.check_sample_count(
+            expected_sample_count(&video.frame_range()?, self.sample_offset, sample_rate),
+            frames_decoded,
+        );
+
+        for segment in state.finish() {
+            write_segment(segment, &skipped_frames)?;
+        }
+        let video_meta = video.metadata()?.clone();
+
+        // Always leave a metadata file behind, even for an empty video
+        let frames_extracted = frame_metadata.len();
+        let metadata_path = writer.write_metadata(&self.build_metadata(
+            &video_meta,
+            frame_metadata,
+            skipped_frames,
+        ))?;
+        report(ProgressEvent::OutputWritten {
+            path: metadata_path.clone(),
+        });
+        events.emit(Event::Finished { frames_extracted })?;
+        self.check_not_empty(video_path, frames_extracted)?;
+
+        Ok(ProcessingResult {
+            video_metadata: video_meta,
+            total_frames_processed,
+            segments_detected,
+            frames_extracted,
+            output_dir: writer.output_dir().to_path_buf(),
+            metadata_path,
+            resumed: false,
+            settings: self.effective_settings(),
+            near_duplicates_removed: 0,
+        })
+    }
+
+    /// Apply, in order, `--every-nth-segment`, the `--ssim-threshold`
+    /// near-duplicate pass, `--min-output-spacing` and `--max-frames`,
+    /// returning the remaining segments and how many the SSIM pass removed.
+    ///
+    /// `--max-frames` runs last, so it has the final say on the count.
+    fn thin_segments(
+        &self,
+        segments: Vec<SemanticSegment>,
+    ) -> Result<(Vec<SemanticSegment>, usize)> {
+        let segments = every_nth_segment(segments, self.every_nth_segment);
+        let (segments, near_duplicates_removed) = match self.ssim_threshold {
+            Some(threshold) => merge_near_duplicates(segments, threshold)?,
+            None => (segments, 0),
+        };
+        let segments = match self.min_output_spacing {
+            Some(seconds) => space_out_segments(segments, seconds),
+            None => segments,
+        };
+        let segments = match self.max_frames {
+            Some(n) => limit_segments(segments, n, self.max_frames_strategy),
+            None => segments,
+        };
+        Ok((segments, near_duplicates_removed))
+    }
+
+    /// Whether buffering every sampled frame would exceed
+    /// `max_frames_in_memory` or leave less than `min_free_memory` free,
+    /// warning about the switch to streaming.
+    fn exceeds_frame_budget(&self, video_path: &Path) -> Result<bool> {
+        if self.max_frames_in_memory.is_none() && self.min_free_memory.is_none() {
+            return Ok(false);
+        }
+        let mut video = VideoLoader::new(video_path)?.with_trim(self.trim_start, self.trim_end);
+        let range = video.frame_range()?;
+        let sampled = expected_sample_count(&range, self.sample_offset, self.sample_rate(&range));
+
+        if let Some(max) = self.max_frames_in_memory.filter(|&max| sampled > max) {
+            self.warn_streaming(&format!(
+                "about {} sampled frames exceed --max-frames-in-memory {}",
+                sampled, max
+            ));
+            return Ok(true);
+        }
+        if let (Some(min), Some(available)) = (self.min_free_memory, available_memory()) {
+            let (width, height) = self.cropped_size(video.metadata()?);
+            let (width, height) = self
+                .decode_scale()
+                .and_then(|long_edge| scaled_size(width, height, long_edge))
+                .unwrap_or((width, height));
+            let needed = sampled as u64 * width as u64 * height as u64 * 3;
+            if available.saturating_sub(needed) < min {
+                self.warn_streaming(&format!(
+                    "buffering about {} sampled frames ({} MiB) would leave less than \
+                     --min-free-mem {} MiB free",
+                    sampled,
+                    needed >> 20,
+                    min >> 20
+                ));
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Warn that a video is being streamed through embedding because of
+    /// `reason`, naming the requested features that streaming drops.
+    fn warn_streaming(&self, reason: &str) {
+        let dropped = self.random_access_features();
+        if dropped.is_empty() {
+            self.warn(&format!(
+                "{}; streaming frames through embedding instead",
+                reason
+            ));
+        } else {
+            self.warn(&format!(
+                "{}; streaming frames through embedding instead. {} need random frame access \
+                 and are disabled for this video",
+                reason,
+                dropped.join(", ")
+            ));
+        }
+    }
+
+    /// Flags of the requested features that need random frame access or
+    /// the whole analysis, which streaming can't provide.
+    fn random_access_features(&self) -> Vec<&'static str> {
+        let mut features: Vec<_> = [
+            ("--bisect-cuts", self.bisect_cuts),
+            ("--include-endpoints", self.include_endpoints),
+            ("--every-nth-segment", self.every_nth_segment > 1),
+            ("--hierarchical", self.hierarchy.is_some()),
+            ("--ssim-threshold", self.ssim_threshold.is_some()),
+            ("--min-output-spacing", self.min_output_spacing.is_some()),
+            ("--max-frames", self.max_frames.is_some()),
+            ("--multipage-tiff", self.multipage_tiff.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, requested)| *requested)
+        .map(|(flag, _)| flag)
+        .collect();
+        features.extend(self.side_outputs());
+        features
+    }
+
+    /// Fail with [`Error::LowMemory`] if free memory has dropped below
+    /// `min_free_memory`.
+    fn check_free_memory(&self) -> Result<()> {
+        if let (Some(min), Some(available)) = (self.min_free_memory, available_memory()) {
+            if available < min {
+                return Err(Error::LowMemory {
+                    available_mib: available >> 20,
+                    min_mib: min >> 20,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Sample every Nth frame: the quality preset's stride, or with a frame
+    /// budget whatever stride spreads the budget over the `range` of frames
+    /// left after trimming.
+    fn sample_rate(&self, range: &Range<usize>) -> usize {
+        match self.frame_budget {
+            Some(budget) => self.sampled_span(range).div_ceil(budget.max(1)).max(1),
+            None => self.quality.frame_sample_rate(),
+        }
+    }
+
+    /// Number of frames of `range` from the sample offset on.
+    fn sampled_span(&self, range: &Range<usize>) -> usize {
+        range
+            .end
+            .saturating_sub(self.sample_offset.max(range.start))
+    }
+
+    /// Sample frames for batch analysis, honoring the sample strategy.
+    fn sample_frames(
+        &self,
+        video: &mut VideoLoader,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Vec<Frame>> {
+        let mut report = |done, total| progress(ProgressEvent::FramesExtracted { done, total });
+        let range = video.frame_range()?;
+        if let Some(seconds) = self.preview_seconds {
+            let fps = video.metadata()?.fps;
+            let windows =
+                preview_windows(range.end, fps, seconds, self.sample_offset.max(range.start));
+            return video.extract_windows(&windows, self.sample_rate(&range), Some(report));
+        }
+        if self.sample_strategy == SampleStrategy::Keyframe {
+            if let Some(frames) = video.extract_keyframes(Some(&mut report))? {
+                self.check_truncation(video)?;
+                return Ok(frames);
+            }
+            self.warn("keyframe flags are not available for this video; sampling frames instead");
+        }
+        let sample_rate = self.sample_rate(&range);
+        let mut frames = Vec::new();
+        video.for_each_frame(sample_rate, self.sample_offset, Some(report), |frame| {
+            frames.push(frame);
+            if frames.len().is_multiple_of(MEMORY_CHECK_INTERVAL_FRAMES) {
+                self.check_free_memory()?;
+            }
+            Ok(())
+        })?;
+        self.check_truncation(video)?;
+        self.check_sample_count(
+            expected_sample_count(&video.frame_range()?, self.sample_offset, sample_rate),
+            frames.len(),
+        );
+        Ok(frames)
+    }
+
+    /// Warn when decoding ended before the container's frame count. Output
+    /// comes from the frames that did decode.
+    fn check_truncation(&self, video: &mut VideoLoader) -> Result<()> {
+        let video_meta = video.metadata()?;
+        if let Some(decoded) = video_meta.decoded_frame_count {
+            self.warn(&format!(
+                "'{}' reports {} frames but only {} could be decoded; using those",
+                video_meta.path.display(),
+                video_meta.frame_count,
+                decoded
+            ));
+        }
+        Ok(())
+    }
+
+    /// Warn when frames look interlaced but `--deinterlace` is off.
+    ///
+    /// Looks for combs in up to [`INTERLACE_PROBE_FRAMES`] frames spread
+    /// over `frames`; see [`combing_share`](crate::video::combing_share).
+    fn check_interlacing(&self, frames: &[Frame]) -> Result<()> {
+        if self.deinterlace || frames.is_empty() || !self.verbosity.shows_warnings() {
+            return Ok(());
+        }
+        let step = frames.len().div_ceil(INTERLACE_PROBE_FRAMES);
+        let mut shares = frames
+            .iter()
+            .step_by(step)
+            .map(Frame::combing)
+            .collect::<Result<Vec<_>>>()?;
+        shares.sort_by(f64::total_cmp);
+        let median = shares[shares.len() / 2];
+        if median >= INTERLACED_COMBING_SHARE {
+            self.warn(&format!(
+                "the video looks interlaced ({:.0}% of pixels show combing); \
+                 use --deinterlace to keep combs from splitting scenes",
+                median * 100.0
+            ));
+        }
+        Ok(())
+    }
+
+    /// Warn when decoding sampled noticeably more or fewer frames than the
+    /// container's frame count implies, which usually means the file is
+    /// truncated or corrupt.
+    fn check_sample_count(&self, expected: usize, sampled: usize) {
+        if sample_count_deviates(expected, sampled) {
+            self.warn(&format!(
+                "sampled {} frames where the container's frame count implies {}; \
+                 the video may be truncated or corrupt",
+                sampled, expected
+            ));
+        }
+    }
+
+    /// Decode and embed the video's final frame if sampling skipped it.
+    ///
+    /// Falls back to the last sampled frame, with a warning, if the frame
+    /// can't be read (container frame counts are sometimes estimates).
+    fn append_final_frame(
+        &self,
+        video: &mut VideoLoader,
+        model: &mut EmbeddingModel,
+        frames: &mut Vec<EmbeddedFrame>,
+    ) -> Result<()> {
+        let Some(last_index) = video.frame_range()?.end.checked_sub(1) else {
+            return Ok(());
+        };
+        if frames.last().is_none_or(|f| f.index() >= last_index) {
+            return Ok(());
+        }
+
+        match video
+            .get_frame_at(last_index)
+            .and_then(|frame| model.compute_embedding(&frame))
+        {
+            Ok(frame) => frames.push(frame),
+            Err(e) => self.warn(&format!(
+                "cannot read final frame {}: {}; ending on the last sampled frame",
+                last_index, e
+            )),
+        }
+        Ok(())
+    }
+
+    /// Load the embedding model with the configured preprocessing, checking
+    /// that it runs unless verification is off.
+    ///
+    /// `frame_size` is the size masks are given for, when frames may be
+    /// decoded smaller.
+    fn embedding_model(&self, frame_size: Option<(u32, u32)>) -> Result<EmbeddingModel> {
+        let mut model = EmbeddingModel::new(&self.model_path, self.quality, self.ort_opt_level)?
+            .with_color_normalization(self.normalize_color)
+            .with_preprocess(self.preprocess)
+            .with_pooling(self.pooling)
+            .with_masks(self.masks.clone())
+            .with_mask_frame_size(frame_size)
+            .with_input_size(self.input_size)
+            .with_max_batch(self.max_batch)
+            .with_skip_bad_frames(self.skip_bad_frames);
+        if let Some(stats) = self.channel_stats {
+            model = model.with_channel_stats(stats);
+        }
+        if self.verify_model {
+            model.verify()?;
+        }
+        Ok(model)
+    }
+
+    /// Embed frames, warning about and recording any that had to be dropped.
+    fn embed(
+        &self,
+        model: &mut EmbeddingModel,
+        frames: &[Frame],
+        skipped_frames: &mut Vec<usize>,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Vec<EmbeddedFrame>> {
+        let batch_size = model.batch_size();
+        let embedded = model.compute_embeddings_batch(
+            frames,
+            Some(|done, total| progress(ProgressEvent::EmbeddingProgress { done, total })),
+        )?;
+        if model.batch_size() < batch_size {
+            self.warn(&format!(
+                "embedding batches of {} ran out of device memory; using batches of {}",
+                batch_size,
+                model.batch_size()
+            ));
+        }
+        for (index, reason) in model.take_skipped() {
+            self.warn(&format!("skipping frame {}: {}", index, reason));
+            skipped_frames.push(index);
+        }
+        Ok(embedded)
+    }
+
+    /// Segment coarsely then finely into a scene tree, with `--hierarchical`.
+    fn scene_tree(&self, frames: &[EmbeddedFrame]) -> Option<Vec<SceneNode>> {
+        let (coarse, fine) = self.hierarchy?;
+        Some(scene_tree(
+            &self.segmenter().with_similarity_threshold(coarse),
+            &self.segmenter().with_similarity_threshold(fine),
+            frames,
+        ))
+    }
+
+    /// Build a segmenter from the configured detail level and tuning.
+    fn segmenter(&self) -> SemanticSegmenter {
+        let mut segmenter = SemanticSegmenter::new(self.detail);
+        if let Some(threshold) = self.split_threshold {
+            segmenter = segmenter.with_similarity_threshold(threshold);
+        }
+        if let Some(threshold) = self.merge_threshold {
+            segmenter = segmenter.with_merge_threshold(threshold);
+        }
+        segmenter
+            .with_anchor_alpha(self.anchor_alpha)
+            .with_min_drop(self.min_drop)
+            .with_per_segment(self.per_segment)
+            .with_representative_percentile(self.representative_percentile)
+            .with_reject_blur(self.reject_blur)
+    }
+
+    /// Write the analysis' representative frames and metadata, once its
+    /// segments are thinned.
+    ///
+    /// Returns the output directory, metadata path and number of stills written.
+    fn write_output(
+        &self,
+        analysis: &Analysis,
+        events: &EventLog,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<(PathBuf, PathBuf, usize)> {
+        let Analysis {
+            video_meta,
+            segments,
+            frame_range,
+            ..
+        } = analysis;
+        let writer = self.writer();
+        let mut frame_metadata = writer.write_frames::<fn(usize, usize)>(segments, None)?;
+        Self::emit_written(events, &frame_metadata)?;
+        match &self.multipage_tiff {
+            Some(path) => progress(ProgressEvent::OutputWritten { path: path.clone() }),
+            None => {
+                for still in &frame_metadata {
+                    progress(ProgressEvent::OutputWritten {
+                        path: writer.output_dir().join(&still.filename),
+                    });
+                }
+            }
+        }
+        if let Some(seconds) = self.clip_seconds {
+            self.write_clips(
+                analysis,
+                seconds,
+                writer.output_dir(),
+                &mut frame_metadata,
+                progress,
+            )?;
+        }
+        if let Some(path) = &self.timeline_path {
+            let entries = timeline_entries(
+                segments,
+                &frame_metadata,
+                range_end_seconds(video_meta, frame_range),
+            );
+            write_timeline(path, &entries)?;
+            progress(ProgressEvent::OutputWritten { path: path.clone() });
+        }
+        let frames_written = frame_metadata.len();
+
+        let mut metadata =
+            self.build_metadata(video_meta, frame_metadata, analysis.skipped_frames.clone());
+        metadata.scenes = analysis.scenes.clone();
+        let metadata_path = writer.write_metadata(&metadata)?;
+        progress(ProgressEvent::OutputWritten {
+            path: metadata_path.clone(),
+        });
+
+        Ok((
+            writer.output_dir().to_path_buf(),
+            metadata_path,
+            frames_written,
+        ))
+    }
+
+    /// Write a clip per segment named after its representative still, and
+    /// record it on each of the segment's stills.
+    ///
+    /// Clips stay within the analyzed part of the video.
+    fn write_clips(
+        &self,
+        analysis: &Analysis,
+        seconds: f64,
+        output_dir: &Path,
+        frame_metadata: &mut [FrameMetadata],
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<()> {
+        let mut video = VideoLoader::new(&analysis.video_meta.path)?
+            .with_crop(self.crop)
+            .with_deinterlace(self.deinterlace);
+        for segment in &analysis.segments {
+            let representative = segment.representative_frame.index();
+            let Some(still) = frame_metadata
+                .iter()
+                .find(|f| f.segment_index == segment.index && f.frame_index == representative)
+            else {
+                continue;
+            };
+            let clip = Path::new(&still.filename)
+                .with_extension("mp4")
+                .to_string_lossy()
+                .into_owned();
+
+            let (start, end) = clip_frame_range(
+                representative,
+                seconds,
+                analysis.video_meta.fps,
+                &analysis.frame_range,
+            );
+            let path = output_dir.join(&clip);
+            video.write_clip(start, end, &path)?;
+            progress(ProgressEvent::OutputWritten { path });
+
+            for still in frame_metadata
+                .iter_mut()
+                .filter(|f| f.segment_index == segment.index)
+            {
+                still.clip = Some(clip.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a video with the configured depth and crop, failing early if its
+    /// codec is not allowed or the crop or a mask doesn't fit its frames.
+    fn open_video(&self, video_path: &Path) -> Result<(VideoLoader, VideoMetadata)> {
+        let mut video = VideoLoader::new(video_path)?
+            .with_preserve_depth(self.preserve_depth)
+            .with_crop(self.crop)
+            .with_deinterlace(self.deinterlace)
+            .with_decode_scale(self.decode_scale())
+            .with_trim(self.trim_start, self.trim_end)
+            .with_sample_jitter(
+                (self.sample_strategy == SampleStrategy::Random).then_some(self.sample_seed),
+            );
+        let video_meta = video.metadata()?.clone();
+        self.check_codec(&video_meta)?;
+        if video.frame_range()?.is_empty() {
+            return Err(Error::Config(format!(
+                "trimming {}s from the start and {}s from the end leaves nothing of the {:.1}s video",
+                self.trim_start, self.trim_end, video_meta.duration_seconds
+            )));
+        }
+        if let Some(crop) = self.crop {
+            crop.check_fits("crop", video_meta.width, video_meta.height)?;
+        }
+        let (width, height) = self.cropped_size(&video_meta);
+        for mask in &self.masks {
+            mask.check_fits("mask", width, height)?;
+        }
+        Ok((video, video_meta))
+    }
+
+    /// Long edge frames are shrunk to at decode: `--decode-scale`, or the
+    /// quality preset's analysis size.
+    fn decode_scale(&self) -> Option<u32> {
+        self.decode_scale.or(self.quality.analysis_long_edge())
+    }
+
+    /// Size of the video's frames after cropping, before any decode scale;
+    /// the size masks are given for.
+    fn cropped_size(&self, video_meta: &VideoMetadata) -> (u32, u32) {
+        self.crop
+            .map_or((video_meta.width, video_meta.height), |c| {
+                (c.width, c.height)
+            })
+    }
+
+    /// A loader for re-reading stills at full resolution, when frames are
+    /// downscaled at decode.
+    fn full_resolution_loader(&self, video_path: &Path) -> Result<Option<VideoLoader>> {
+        if self.decode_scale().is_none() {
+            return Ok(None);
+        }
+        let video = VideoLoader::new(video_path)?
+            .with_preserve_depth(self.preserve_depth)
+            .with_crop(self.crop)
+            .with_deinterlace(self.deinterlace);
+        Ok(Some(video))
+    }
+
+    /// Replace a segment's downscaled stills with full-resolution decodes.
+    fn restore_full_resolution(
+        video: &mut VideoLoader,
+        segment: &mut SemanticSegment,
+    ) -> Result<()> {
+        let stills =
+            std::iter::once(&mut segment.representative_frame).chain(&mut segment.alternate_frames);
+        for still in stills {
+            // Keep the timestamp the frame was sampled at
+            let timestamp = still.frame.timestamp_seconds;
+            still.frame = video.get_frame_at(still.frame.index)?;
+            still.frame.timestamp_seconds = timestamp;
+        }
+        Ok(())
+    }
+
+    /// Fail before extraction if the video's codec is not allowed.
+    fn check_codec(&self, video_meta: &VideoMetadata) -> Result<()> {
+        let codec = &video_meta.codec;
+        let listed = |list: &[String]| list.iter().any(|c| c.eq_ignore_ascii_case(codec));
+
+        let reason = if listed(&self.reject_codecs) {
+            "rejected by --reject-codec".to_string()
+        } else if !self.require_codecs.is_empty() && !listed(&self.require_codecs) {
+            format!("expected one of {}", self.require_codecs.join(", "))
+        } else {
+            return Ok(());
+        };
+
+        Err(Error::CodecRejected {
+            path: video_meta.path.clone(),
+            codec: codec.clone(),
+            reason,
+        })
+    }
+
+    /// Output writer for the configured directory and layout.
+    fn writer(&self) -> OutputWriter {
+        OutputWriter::new(self.output_dir.clone())
+            .with_bucket_minutes(self.bucket_minutes)
+            .with_checksums(self.checksums)
+            .with_pixel_hashes(self.pixel_hashes)
+            .with_palette(self.palette_size)
+            .with_jpeg_subsampling(self.jpeg_subsampling)
+            .with_multipage_tiff(self.multipage_tiff.clone())
+    }
+
+    /// Fail (or warn, with `allow_empty`) when a run produced no stills.
+    fn check_not_empty(&self, video_path: &Path, stills: usize) -> Result<()> {
+        if stills > 0 {
+            return Ok(());
+        }
+        if self.allow_empty {
+            self.warn(&format!(
+                "no stills were extracted from '{}'",
+                video_path.display()
+            ));
+            return Ok(());
+        }
+        Err(Error::NoStillsExtracted(video_path.to_path_buf()))
+    }
+
+    /// Warn when depth preservation was asked for but the source is 8-bit.
+    fn check_depth(&self, frame: &Frame) {
+        if self.preserve_depth && frame.data16.is_none() {
+            self.warn(
+                "--preserve-depth has no effect: the video decodes to 8-bit frames, writing JPEG",
+            );
+        }
+    }
+
+    /// Check a still's resolution against the minimum output dimension.
+    ///
+    /// Too-small stills are a warning, or an error in strict mode.
+    fn check_output_size(&self, frame: &Frame) -> Result<()> {
+        let Some(min) = self.min_output_dimension else {
+            return Ok(());
+        };
+        if frame.width.min(frame.height) >= min {
+            return Ok(());
+        }
+
+        let message = format!(
+            "output stills are {}x{}, below the minimum dimension of {}px",
+            frame.width, frame.height, min
+        );
+        if self.strict {
+            return Err(Error::Output(message));
+        }
+        self.warn(&message);
+        Ok(())
+    }
+
+    /// The model, detail level and thresholds this processor will use.
+    ///
+    /// `detail` is still `auto` unless [`resolve_detail`](Self::resolve_detail)
+    /// has run, and the thresholds are only known once it is.
+    pub fn effective_settings(&self) -> EffectiveSettings {
+        let segmenter = self.segmenter();
+        let known = self.uniform_interval.is_none()
+            && (self.detail != DetailLevel::Auto || self.split_threshold.is_some());
+        EffectiveSettings {
+            model: self.model_path.clone(),
+            detail: self.detail_label(),
+            quality: format!("{:?}", self.quality).to_lowercase(),
+            split_threshold: known.then(|| decimal(segmenter.similarity_threshold())),
+            merge_threshold: known.then(|| decimal(segmenter.merge_threshold())),
+        }
+    }
+
+    /// Detail level as recorded in metadata.
+    fn detail_label(&self) -> String {
+        match self.uniform_interval {
+            Some(_) => "uniform".to_string(),
+            None => format!("{:?}", self.detail).to_lowercase(),
+        }
+    }
+
+    /// Build run metadata stamped with the current settings fingerprint.
+    fn build_metadata(
+        &self,
+        video_meta: &VideoMetadata,
+        frames: Vec<FrameMetadata>,
+        skipped_frames: Vec<usize>,
+    ) -> OutputMetadata {
+        let mut metadata = OutputMetadata::new(
+            video_meta,
+            frames,
+            &self.detail_label(),
+            &format!("{:?}", self.quality).to_lowercase(),
+        );
+        metadata.sample_offset = self.sample_offset;
+        metadata.skipped_frames = skipped_frames;
+        metadata.settings_fingerprint = self.settings_fingerprint(&video_meta.path);
+        metadata.preview = self.preview_seconds.is_some();
+        metadata.trim_start_seconds = self.trim_start;
+        metadata.trim_end_seconds = self.trim_end;
+        metadata.decoded_frame_count = video_meta.decoded_frame_count;
+        metadata.channel_stats = self.channel_stats;
+        metadata
+    }
+
+    /// Canonical description of the input file and every setting that
+    /// affects which frames are extracted; see [`SceneSplitProcessor`].
+    fn settings_fingerprint(&self, video_path: &Path) -> String {
+        let (size, modified) = fs::metadata(video_path)
+            .map(|m| {
+                let modified = m
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                (m.len(), modified)
+            })
+            .unwrap_or((0, 0));
+
+        let settings = serde_json::to_string(self).expect("settings serialize to JSON");
+        format!(
+            "video={};size={};mtime={};settings={}",
+            video_path.display(),
+            size,
+            modified,
+            settings
+        )
+    }
+
+    /// Load a previous run from the output directory if it can be reused.
+    ///
+    /// The run must have been produced with identical settings from the same
+    /// input, and every still it lists must still exist.
+    fn find_previous_run(&self, video_path: &Path) -> Option<OutputMetadata> {
+        let writer = self.writer();
+        let metadata_path = writer.metadata_path();
+        if !metadata_path.exists() {
+            return None;
+        }
+
+        let previous = match OutputWriter::read_metadata(&metadata_path) {
+            Ok(previous) => previous,
+            Err(e) => {
+                self.warn(&format!("cannot resume: {}", e));
+                return None;
+            }
+        };
+
+        if previous.settings_fingerprint != self.settings_fingerprint(video_path) {
+            self.warn("cannot resume: previous run used different input or settings");
+            return None;
+        }
+
+        let missing = previous
+            .frames
+            .iter()
+            .any(|f| !writer.output_dir().join(&f.filename).exists());
+        if missing {
+            self.warn("cannot resume: previous run is missing output files");
+            return None;
+        }
+
+        Some(previous)
+    }
+
+    /// Flags of the requested outputs that a reused run can't provide,
+    /// because they're written from the analysis rather than kept with the
+    /// stills.
+    fn side_outputs(&self) -> Vec<&'static str> {
+        [
+            ("--export-edl", self.edl_path.is_some()),
+            ("--poster", self.poster_path.is_some()),
+            ("--timeline-json", self.timeline_path.is_some()),
+            ("--export-boundaries", self.boundaries_dir.is_some()),
+            ("--clips", self.clip_seconds.is_some()),
+            (
+                "--dump-similarity-matrix",
+                self.similarity_matrix_path.is_some(),
+            ),
+            ("--dump-embeddings", self.embeddings_path.is_some()),
+            ("--debug-anchor", self.anchor_trace_path.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, requested)| *requested)
+        .map(|(flag, _)| flag)
+        .collect()
+    }
+
+    /// Result for a complete previous run that is reused unchanged.
+    fn resumed_result(
+        &self,
+        video_path: &Path,
+        previous: &OutputMetadata,
+    ) -> Result<ProcessingResult> {
+        let mut video = VideoLoader::new(video_path)?;
+        let writer = self.writer();
+
+        Ok(ProcessingResult {
+            video_metadata: video.metadata()?.clone(),
+            total_frames_processed: 0,
+            segments_detected: previous
+                .frames
+                .last()
+                .map(|f| f.segment_index + 1)
+                .unwrap_or(0),
+            frames_extracted: previous.frames.len(),
+            output_dir: writer.output_dir().to_path_buf(),
+            metadata_path: writer.metadata_path(),
+            resumed: true,
+            settings: self.effective_settings(),
+            near_duplicates_removed: 0,
+        })
+    }
+
+    /// Open the configured event log, or a disabled one.
+    fn event_log(&self) -> Result<EventLog> {
+        match &self.event_log_path {
+            Some(path) => EventLog::open(path),
+            None => Ok(EventLog::disabled()),
+        }
+    }
+
+    fn emit_video_opened(events: &EventLog, video_meta: &VideoMetadata) -> Result<()> {
+        events.emit(Event::VideoOpened {
+            path: &video_meta.path,
+            width: video_meta.width,
+            height: video_meta.height,
+            fps: video_meta.fps,
+            frame_count: video_meta.frame_count,
+            duration_seconds: video_meta.duration_seconds,
+        })
+    }
+
+    fn emit_segments(
+        events: &EventLog,
+        segments: &[SemanticSegment],
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<()> {
+        for segment in segments {
+            progress(ProgressEvent::SegmentFinalized {
+                index: segment.index,
+            });
+            events.emit(Event::SegmentFinalized {
+                segment_index: segment.index,
+                start_timestamp_seconds: segment.start_timestamp_seconds,
+                end_timestamp_seconds: segment.end_timestamp_seconds,
+                frame_count: segment.frame_count,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn emit_written(events: &EventLog, frames: &[FrameMetadata]) -> Result<()> {
+        for frame in frames {
+            events.emit(Event::FrameWritten {
+                filename: &frame.filename,
+                frame_index: frame.frame_index,
+                segment_index: frame.segment_index,
+                timestamp_seconds: frame.timestamp_seconds,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Print a warning unless output is silenced.
+    fn warn(&self, message: &str) {
+        if self.verbosity.shows_warnings() {
+            eprintln!("Warning: {}", message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DRAFT_ANALYSIS_LONG_EDGE;
+
+    fn processor(output_dir: &Path) -> SceneSplitProcessor {
+        SceneSplitProcessor::new(
+            DetailLevel::Summary,
+            QualityPreset::Balanced,
+            Some(output_dir.to_path_buf()),
+            PathBuf::from("model.onnx"),
+        )
+        .with_verbosity(Verbosity::Silent)
+    }
+
+    fn write_previous_run(processor: &SceneSplitProcessor, video: &Path, dir: &Path) {
+        let frame = FrameMetadata {
+            filename: "0001.jpg".to_string(),
+            segment_index: 0,
+            frame_index: 0,
+            timestamp_seconds: Timestamp::ZERO,
+            timestamp_formatted: "00:00:00.000".to_string(),
+            start_frame_timestamp: Timestamp::ZERO,
+            end_frame_timestamp: Timestamp::from_seconds(2.0),
+            duration_seconds: 2.0,
+            sharpness: 0.0,
+            exposure: 1.0,
+            relocation: None,
+            boundary: None,
+            clip: None,
+            pixel_sha256: None,
+            palette: None,
+            page: None,
+        };
+        let metadata = OutputMetadata {
+            source_video: video.to_string_lossy().to_string(),
+            video_duration_seconds: 2.0,
+            video_frame_count: 60,
+            extracted_frames: 1,
+            detail_level: "summary".to_string(),
+            quality_preset: "balanced".to_string(),
+            sample_offset: 0,
+            skipped_frames: Vec::new(),
+            settings_fingerprint: processor.settings_fingerprint(video),
+            complete: true,
+            preview: false,
+            trim_start_seconds: 0.0,
+            trim_end_seconds: 0.0,
+            decoded_frame_count: None,
+            scenes: None,
+            channel_stats: None,
+            frames: vec![frame],
+        };
+        OutputWriter::new(Some(dir.to_path_buf()))
+            .write_metadata(&metadata)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_check_codec() {
+        let meta = |codec: &str| VideoMetadata {
+            path: PathBuf::from("clip.mp4"),
+            width: 1920,
+            height: 1080,
+            fps: 30.0,
+            frame_count: 300,
+            duration_seconds: 10.0,
+            codec: codec.to_string(),
+            decoded_frame_count: None,
+        };
+        let dir = tempfile::tempdir().unwrap();
+
+        let open = processor(dir.path());
+        assert!(open.check_codec(&meta("hvc1")).is_ok());
+
+        let h264_only =
+            processor(dir.path()).with_codec_filter(vec!["AVC1".to_string()], Vec::new());
+        assert!(h264_only.check_codec(&meta("avc1")).is_ok());
+        assert!(matches!(
+            h264_only.check_codec(&meta("hvc1")),
+            Err(Error::CodecRejected { .. })
+        ));
+
+        let no_hevc = processor(dir.path()).with_codec_filter(Vec::new(), vec!["hvc1".to_string()]);
+        assert!(no_hevc.check_codec(&meta("avc1")).is_ok());
+        assert!(no_hevc.check_codec(&meta("hvc1")).is_err());
+    }
+
+    #[test]
+    fn test_check_output_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let frame = Frame {
+            index: 0,
+            timestamp_seconds: Timestamp::ZERO,
+            data: vec![0; 160 * 80 * 3],
+            width: 160,
+            height: 80,
+            data16: None,
+        };
+
+        let lenient = processor(dir.path()).with_min_output_dimension(Some(100));
+        assert!(lenient.check_output_size(&frame).is_ok());
+
+        let strict = lenient.with_strict(true);
+        assert!(matches!(
+            strict.check_output_size(&frame),
+            Err(Error::Output(_))
+        ));
+
+        let small_enough = processor(dir.path())
+            .with_min_output_dimension(Some(80))
+            .with_strict(true);
+        assert!(small_enough.check_output_size(&frame).is_ok());
+    }
+
+    #[test]
+    fn test_check_not_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = Path::new("empty.mp4");
+
+        assert!(matches!(
+            processor(dir.path()).check_not_empty(video, 0),
+            Err(Error::NoStillsExtracted(_))
+        ));
+        assert!(processor(dir.path())
+            .with_allow_empty(true)
+            .check_not_empty(video, 0)
+            .is_ok());
+        assert!(processor(dir.path()).check_not_empty(video, 1).is_ok());
+    }
+
+    #[test]
+    fn test_single_frame_video_writes_one_still() {
+        let dir = tempfile::tempdir().unwrap();
+        let processor = processor(dir.path()).with_every_nth_segment(3);
+        let frames = vec![EmbeddedFrame {
+            frame: Frame {
+                index: 0,
+                timestamp_seconds: Timestamp::ZERO,
+                data: vec![0; 8 * 8 * 3],
+                width: 8,
+                height: 8,
+                data16: None,
+            },
+            embedding: vec![1.0, 0.0],
+        }];
+        let segments = every_nth_segment(
+            processor
+                .segmenter()
+                .segment::<fn(usize, usize)>(&frames, None),
+            processor.every_nth_segment,
+        );
+        processor
+            .check_not_empty(Path::new("one.mp4"), segments.len())
+            .unwrap();
+
+        let analysis = Analysis {
+            video_meta: VideoMetadata {
+                path: PathBuf::from("one.mp4"),
+                width: 8,
+                height: 8,
+                fps: 30.0,
+                frame_count: 1,
+                duration_seconds: 1.0 / 30.0,
+                codec: "avc1".to_string(),
+                decoded_frame_count: None,
+            },
+            frames_analyzed: 1,
+            segments,
+            skipped_frames: Vec::new(),
+            scenes: None,
+            frame_range: 0..1,
+        };
+        let mut progress = Vec::new();
+        let (_, metadata_path, written) = processor
+            .write_output(&analysis, &EventLog::disabled(), &mut |event| {
+                progress.push(event)
+            })
+            .unwrap();
+        assert_eq!(written, 1);
+        assert!(dir.path().join("0001.jpg").exists());
+
+        let paths: Vec<PathBuf> = progress
+            .into_iter()
+            .map(|event| match event {
+                ProgressEvent::OutputWritten { path } => path,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(paths, vec![dir.path().join("0001.jpg"), metadata_path]);
+    }
+
+    #[test]
+    fn test_segments_reported_after_thinning() {
+        let dir = tempfile::tempdir().unwrap();
+        let processor = SceneSplitProcessor::new(
+            DetailLevel::All,
+            QualityPreset::Balanced,
+            Some(dir.path().to_path_buf()),
+            PathBuf::from("model.onnx"),
+        )
+        .with_verbosity(Verbosity::Silent)
+        .with_every_nth_segment(2);
+        // Three scenes of ten frames each
+        let frames: Vec<EmbeddedFrame> = (0..30)
+            .map(|i| {
+                let mut embedding = vec![0.0; 3];
+                embedding[i / 10] = 1.0;
+                EmbeddedFrame {
+                    frame: Frame {
+                        index: i,
+                        timestamp_seconds: Timestamp::from_frame(i, 30.0),
+                        data: vec![0; 8 * 8 * 3],
+                        width: 8,
+                        height: 8,
+                        data16: None,
+                    },
+                    embedding,
+                }
+            })
+            .collect();
+        let segments = processor
+            .segmenter()
+            .segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(segments.len(), 3);
+
+        let analysis = Analysis {
+            video_meta: VideoMetadata {
+                path: PathBuf::from("clip.mp4"),
+                width: 8,
+                height: 8,
+                fps: 30.0,
+                frame_count: 30,
+                duration_seconds: 1.0,
+                codec: "avc1".to_string(),
+                decoded_frame_count: None,
+            },
+            frames_analyzed: 30,
+            segments,
+            skipped_frames: Vec::new(),
+            scenes: None,
+            frame_range: 0..30,
+        };
+        let mut finalized = Vec::new();
+        let result = processor
+            .write_analysis(
+                Path::new("clip.mp4"),
+                analysis,
+                &EventLog::disabled(),
+                &mut |event| {
+                    if let ProgressEvent::SegmentFinalized { index } = event {
+                        finalized.push(index);
+                    }
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.segments_detected, 3);
+        assert_eq!(finalized, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_process_frames_in_memory() {
+        let frames: Vec<Frame> = (0..11)
+            .map(|i| {
+                let image = DynamicImage::new_luma8(4, 2);
+                Frame::from_image(image, i, Timestamp::from_seconds(i as f64 * 0.5))
+            })
+            .collect();
+        let video_meta = in_memory_metadata(&frames);
+        assert_eq!((video_meta.width, video_meta.height), (4, 2));
+        assert_eq!(video_meta.frame_count, 11);
+        assert_eq!(video_meta.duration_seconds, 5.0);
+        assert_eq!(video_meta.fps, 2.0);
+        assert_eq!(video_meta.path, Path::new(IN_MEMORY_SOURCE));
+
+        // Clips are cut from a video file, so they're refused up front
+        let dir = tempfile::tempdir().unwrap();
+        let result = processor(dir.path())
+            .with_clips(Some(2.0))
+            .process_frames(vec![(DynamicImage::new_rgb8(4, 2), 0.0)], |_| {});
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_sample_count_deviates() {
+        assert!(!sample_count_deviates(600, 600));
+        // Off by one, or by an estimated frame count's error, is expected
+        assert!(!sample_count_deviates(600, 599));
+        assert!(!sample_count_deviates(600, 590));
+        assert!(!sample_count_deviates(10, 11));
+        // A video that stops decoding partway through is not
+        assert!(sample_count_deviates(600, 300));
+        assert!(sample_count_deviates(600, 620));
+        assert!(sample_count_deviates(10, 0));
+    }
+
+    #[test]
+    fn test_probe_frames_skip_undecodable_frames() {
+        let decode = |i: usize| {
+            if i == 20 {
+                return Err(Error::VideoCapture(format!("frame {} is corrupt", i)));
+            }
+            Ok(Frame {
+                index: i,
+                timestamp_seconds: Timestamp::from_seconds(i as f64),
+                data: Vec::new(),
+                width: 0,
+                height: 0,
+                data16: None,
+            })
+        };
+
+        let frames = probe_frames(10, 40, 4, decode);
+        let indices: Vec<_> = frames.iter().map(|f| f.index).collect();
+        assert_eq!(indices, vec![10, 30, 40]);
+    }
+
+    #[test]
+    fn test_sample_rate_spreads_frame_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let preset = processor(dir.path());
+        assert_eq!(preset.sample_rate(&(0..3000)), 5);
+
+        // 3000 frames on a 100-frame budget: every 30th frame, 100 in total
+        let budgeted = processor(dir.path()).with_frame_budget(Some(100));
+        assert_eq!(budgeted.sample_rate(&(0..3000)), 30);
+        assert_eq!(3000usize.div_ceil(budgeted.sample_rate(&(0..3000))), 100);
+        // Rounds the stride up so the budget is never exceeded
+        assert_eq!(budgeted.sample_rate(&(0..3050)), 31);
+        // Short videos are sampled at every frame
+        assert_eq!(budgeted.sample_rate(&(0..40)), 1);
+        // Trimmed frames don't count toward the spread
+        assert_eq!(budgeted.sample_rate(&(500..2500)), 20);
+
+        let offset = processor(dir.path())
+            .with_frame_budget(Some(100))
+            .with_sample_offset(1000);
+        assert_eq!(offset.sample_rate(&(0..3000)), 20);
+        assert_eq!(offset.sample_rate(&(1500..3000)), 15);
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let video = VideoMetadata {
+            path: PathBuf::from("clip.mp4"),
+            width: 1920,
+            height: 1080,
+            fps: 30.0,
+            frame_count: 300,
+            duration_seconds: 10.0,
+            codec: "avc1".to_string(),
+            decoded_frame_count: None,
+        };
+        let settings = processor(Path::new("out")).effective_settings();
+        let summary = RunSummary::new(&video, 100, 4, 2, 1, None, settings.clone());
+        assert_eq!(summary.average_segment_seconds, 2.5);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["segments_detected"], 4);
+        assert_eq!(json["frames_extracted"], 2);
+        assert!(json.get("metadata_path").is_none());
+        assert_eq!(json["settings"]["detail"], "summary");
+        assert_eq!(json["settings"]["quality"], "balanced");
+        assert_eq!(json["settings"]["split_threshold"], 0.85);
+        assert_eq!(
+            settings.to_string(),
+            "detail summary, quality balanced, split threshold 0.85, merge threshold 0.9, \
+             model model.onnx"
+        );
+
+        let empty = RunSummary::new(&video, 0, 0, 0, 0, None, settings);
+        assert_eq!(empty.average_segment_seconds, 0.0);
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("clip.mp4");
+        fs::write(&video, b"video").unwrap();
+
+        let base = processor(dir.path());
+        let same = processor(dir.path());
+        let changed = processor(dir.path()).with_min_drop(0.1);
+        let skipped = processor(dir.path()).with_strict(true);
+
+        assert_eq!(
+            base.settings_fingerprint(&video),
+            same.settings_fingerprint(&video)
+        );
+        assert_ne!(
+            base.settings_fingerprint(&video),
+            changed.settings_fingerprint(&video)
+        );
+        assert_eq!(
+            base.settings_fingerprint(&video),
+            skipped.settings_fingerprint(&video)
+        );
+    }
+
+    #[test]
+    fn test_find_previous_run_requires_matching_settings_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("clip.mp4");
+        fs::write(&video, b"video").unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+
+        let base = processor(&out);
+        write_previous_run(&base, &video, &out);
+
+        // The listed still is missing
+        assert!(base.find_previous_run(&video).is_none());
+
+        fs::write(out.join("0001.jpg"), b"jpeg").unwrap();
+        assert!(base.find_previous_run(&video).is_some());
+
+        let changed = processor(&out).with_per_segment(2);
+        assert!(changed.find_previous_run(&video).is_none());
+    }
+
+    #[test]
+    fn test_side_outputs_prevent_reuse() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(processor(dir.path()).side_outputs().is_empty());
+
+        let processor = processor(dir.path())
+            .with_edl(Some(dir.path().join("cuts.edl")))
+            .with_poster(Some(dir.path().join("poster.jpg")));
+        assert_eq!(processor.side_outputs(), vec!["--export-edl", "--poster"]);
+    }
+
+    #[test]
+    fn test_random_access_features_follow_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(processor(dir.path()).random_access_features().is_empty());
+
+        let processor = processor(dir.path())
+            .with_max_frames(Some(10))
+            .with_multipage_tiff(Some(dir.path().join("stills.tiff")))
+            .with_poster(Some(dir.path().join("poster.jpg")));
+        assert_eq!(
+            processor.random_access_features(),
+            vec!["--max-frames", "--multipage-tiff", "--poster"]
+        );
+    }
+
+    #[test]
+    fn test_uniform_interval_prevents_reuse() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("clip.mp4");
+        fs::write(&video, b"video").unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        fs::write(out.join("0001.jpg"), b"jpeg").unwrap();
+
+        let every_2s = processor(&out).with_uniform_interval(Some(2.0));
+        write_previous_run(&every_2s, &video, &out);
+        assert!(every_2s.find_previous_run(&video).is_some());
+
+        let every_5s = processor(&out).with_uniform_interval(Some(5.0));
+        assert!(every_5s.find_previous_run(&video).is_none());
+    }
+
+    #[test]
+    fn test_range_end_seconds_follows_trim() {
+        let video = VideoMetadata {
+            path: PathBuf::from("clip.mp4"),
+            width: 8,
+            height: 8,
+            fps: 30.0,
+            frame_count: 300,
+            duration_seconds: 10.0,
+            codec: "avc1".to_string(),
+            decoded_frame_count: None,
+        };
+        assert_eq!(range_end_seconds(&video, &(0..300)), 10.0);
+        // --trim-end 2
+        assert_eq!(range_end_seconds(&video, &(30..240)), 8.0);
+    }
+
+    #[test]
+    fn test_draft_decodes_at_low_resolution() {
+        let out = Path::new("out");
+        assert_eq!(processor(out).decode_scale(), None);
+
+        let draft = SceneSplitProcessor::new(
+            DetailLevel::Summary,
+            QualityPreset::Draft,
+            None,
+            PathBuf::from("model.onnx"),
+        );
+        assert_eq!(draft.decode_scale(), Some(DRAFT_ANALYSIS_LONG_EDGE));
+        assert_eq!(draft.with_decode_scale(Some(512)).decode_scale(), Some(512));
+    }
+
+    #[test]
+    fn test_output_settings_prevent_reuse() {
+        let variants: [fn(SceneSplitProcessor) -> SceneSplitProcessor; 4] = [
+            |p| p.with_checksums(true),
+            |p| p.with_pixel_hashes(true),
+            |p| p.with_palette(Some(5)),
+            |p| p.with_embedding_dump(Some(PathBuf::from("embeddings.json"))),
+        ];
+        for variant in variants {
+            let dir = tempfile::tempdir().unwrap();
+            let video = dir.path().join("clip.mp4");
+            fs::write(&video, b"video").unwrap();
+            let out = dir.path().join("out");
+            fs::create_dir(&out).unwrap();
+            fs::write(out.join("0001.jpg"), b"jpeg").unwrap();
+
+            let before = processor(&out);
+            write_previous_run(&before, &video, &out);
+            assert!(before.find_previous_run(&video).is_some());
+            assert!(variant(processor(&out)).find_previous_run(&video).is_none());
+        }
+    }
+
+    #[test]
+    fn test_effective_settings_resolve_thresholds() {
+        let out = Path::new("out");
+        let defaults = processor(out).effective_settings();
+        assert_eq!(defaults.model, PathBuf::from("model.onnx"));
+        assert_eq!(defaults.detail, "summary");
+        assert_eq!(defaults.quality, "balanced");
+        assert_eq!(defaults.split_threshold, Some(0.85));
+        assert_eq!(defaults.merge_threshold, Some(0.9));
+
+        let split = processor(out)
+            .with_split_threshold(Some(0.8))
+            .effective_settings();
+        assert_eq!(split.split_threshold, Some(0.8));
+        assert_eq!(split.merge_threshold, Some(0.85));
+
+        // Auto isn't resolved yet, so neither are its thresholds
+        let auto = SceneSplitProcessor::new(
+            DetailLevel::Auto,
+            QualityPreset::Balanced,
+            None,
+            PathBuf::from("model.onnx"),
+        )
+        .effective_settings();
+        assert_eq!(auto.detail, "auto");
+        assert_eq!(auto.split_threshold, None);
+        assert_eq!(auto.merge_threshold, None);
     }
 }
@@ -0,0 +1,129 @@
+//! Opt-in timing of named hot-path operations.
+//!
+//! Spans are recorded only after [`enable`] is called; until then starting
+//! and dropping a span costs a single relaxed atomic load.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TOTALS: Mutex<Vec<(&'static str, OpStats)>> = Mutex::new(Vec::new());
+
+/// Accumulated timing for one operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OpStats {
+    pub count: u64,
+    pub total: Duration,
+}
+
+impl OpStats {
+    /// Average time per call.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.count as f64)
+        }
+    }
+}
+
+/// Start recording spans.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Time the enclosing scope as one call of operation `name`.
+#[must_use = "the span is timed until it is dropped"]
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        started: ENABLED.load(Ordering::Relaxed).then(Instant::now),
+    }
+}
+
+/// A running timer, recorded when dropped.
+pub struct Span {
+    name: &'static str,
+    started: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(started) = self.started {
+            record(self.name, started.elapsed());
+        }
+    }
+}
+
+fn record(name: &'static str, elapsed: Duration) {
+    let mut totals = TOTALS.lock().unwrap_or_else(|e| e.into_inner());
+    let index = match totals.iter().position(|(n, _)| *n == name) {
+        Some(index) => index,
+        None => {
+            totals.push((name, OpStats::default()));
+            totals.len() - 1
+        }
+    };
+    let stats = &mut totals[index].1;
+    stats.count += 1;
+    stats.total += elapsed;
+}
+
+/// Recorded operations, most total time first.
+pub fn summary() -> Vec<(&'static str, OpStats)> {
+    let mut totals = TOTALS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    totals.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total));
+    totals
+}
+
+/// Write the summary as an aligned table.
+///
+/// Operations that run on several threads at once (such as `write`) are
+/// summed across threads, so totals can exceed wall-clock time.
+pub fn write_report<W: Write>(mut out: W, summary: &[(&'static str, OpStats)]) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:<12} {:>8} {:>12} {:>12}",
+        "operation", "count", "total (ms)", "mean (ms)"
+    )?;
+    for (name, stats) in summary {
+        writeln!(
+            out,
+            "{:<12} {:>8} {:>12.1} {:>12.3}",
+            name,
+            stats.count,
+            stats.total.as_secs_f64() * 1000.0,
+            stats.mean().as_secs_f64() * 1000.0
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_accumulate_and_report() {
+        // Spans started before enabling are not recorded
+        drop(span("test-disabled"));
+        enable();
+        for _ in 0..3 {
+            let _span = span("test-op");
+        }
+
+        let summary = summary();
+        assert!(summary.iter().all(|(name, _)| *name != "test-disabled"));
+        let (_, stats) = summary.iter().find(|(name, _)| *name == "test-op").unwrap();
+        assert_eq!(stats.count, 3);
+        assert!(stats.mean() <= stats.total);
+
+        let mut out = Vec::new();
+        write_report(&mut out, &[("test-op", *stats)]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("operation"));
+        assert!(out.lines().nth(1).unwrap().starts_with("test-op"));
+    }
+}
@@ -0,0 +1,115 @@
+//! Segmentation of dumped embeddings, without the video or model.
+
+use crate::config::{DetailLevel, AUTO_DETAIL_PROBE_FRAMES};
+use crate::output::{EmbeddingDump, FrameMetadata, OutputMetadata};
+use crate::segmentation::{change_share, SemanticSegmenter};
+
+/// `detail`, with `Auto` resolved from evenly spaced frames of `dump` the
+/// way a run probes the video.
+pub fn resolve_detail(dump: &EmbeddingDump, detail: DetailLevel) -> DetailLevel {
+    if detail != DetailLevel::Auto {
+        return detail;
+    }
+    let frames = dump.embedded_frames();
+    let count = AUTO_DETAIL_PROBE_FRAMES.min(frames.len());
+    let probes: Vec<_> = (0..count)
+        .map(|i| frames[i * frames.len() / count].clone())
+        .collect();
+    DetailLevel::for_change_share(change_share(
+        &probes,
+        DetailLevel::Summary.similarity_threshold(),
+    ))
+}
+
+/// Segment the frames of `dump` and describe the result as run metadata.
+///
+/// The dump has no pixels, so nothing is written but metadata: each
+/// segment's representative is listed with an empty `filename` and zero
+/// `sharpness` and `exposure`.
+pub fn resegment(
+    dump: &EmbeddingDump,
+    segmenter: &SemanticSegmenter,
+    detail_level: &str,
+) -> OutputMetadata {
+    let frames = dump.embedded_frames();
+    let segments = segmenter.segment::<fn(usize, usize)>(&frames, None);
+    let stills = segments
+        .iter()
+        .map(|segment| {
+            FrameMetadata::new(segment, &segment.representative_frame.frame, String::new())
+        })
+        .collect();
+    OutputMetadata::new(
+        &dump.video_metadata(),
+        stills,
+        detail_level,
+        &dump.quality_preset,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::DumpedEmbedding;
+    use crate::timestamp::Timestamp;
+
+    /// A dump of 40 frames, one a second, with `embedding(i)` for frame `i`.
+    fn dump(embedding: impl Fn(usize) -> Vec<f32>) -> EmbeddingDump {
+        let frames = (0..40)
+            .map(|i| DumpedEmbedding {
+                frame_index: i * 30,
+                timestamp_seconds: Timestamp::from_seconds(i as f64),
+                embedding: embedding(i),
+            })
+            .collect();
+        EmbeddingDump {
+            source_video: "talk.mp4".to_string(),
+            video_duration_seconds: 40.0,
+            video_frame_count: 1200,
+            fps: 30.0,
+            quality_preset: "balanced".to_string(),
+            frames,
+        }
+    }
+
+    /// Two flat stretches of twenty frames with an abrupt change between.
+    fn two_stretches(i: usize) -> Vec<f32> {
+        if i < 20 {
+            vec![1.0, 0.0]
+        } else {
+            vec![0.0, 1.0]
+        }
+    }
+
+    #[test]
+    fn test_resegment_dump() {
+        let dump = dump(two_stretches);
+
+        let metadata = resegment(&dump, &SemanticSegmenter::new(DetailLevel::All), "all");
+        assert_eq!(metadata.source_video, "talk.mp4");
+        assert_eq!(metadata.quality_preset, "balanced");
+        assert_eq!(metadata.extracted_frames, 2);
+        assert_eq!(metadata.frames[1].segment_index, 1);
+        assert_eq!(
+            metadata.frames[1].start_frame_timestamp,
+            Timestamp::from_seconds(20.0)
+        );
+        assert!(metadata.frames.iter().all(|f| f.filename.is_empty()));
+    }
+
+    #[test]
+    fn test_resolve_detail_from_dump() {
+        let calm = dump(two_stretches);
+        assert_eq!(resolve_detail(&calm, DetailLevel::Auto), DetailLevel::All);
+        assert_eq!(resolve_detail(&calm, DetailLevel::Key), DetailLevel::Key);
+
+        let busy = dump(|i| {
+            if i % 2 == 0 {
+                vec![1.0, 0.0]
+            } else {
+                vec![0.0, 1.0]
+            }
+        });
+        assert_eq!(resolve_detail(&busy, DetailLevel::Auto), DetailLevel::Key);
+    }
+}
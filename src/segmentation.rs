@@ -1,7 +1,19 @@
 //! Semantic segmentation and frame selection module.
 
-use crate::config::DetailLevel;
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    DetailLevel, MaxFramesStrategy, PosterStrategy, CUT_DROP_SHARE, DEFAULT_ANCHOR_ALPHA,
+    HYSTERESIS_MARGIN, TRANSITION_LEAD_IN_FRAMES,
+};
 use crate::embeddings::{cosine_similarity, EmbeddedFrame};
+use crate::error::Result;
+use crate::timestamp::Timestamp;
+use crate::video::Frame;
 
 /// A segment of semantically similar frames.
 #[derive(Debug, Clone)]
@@ -10,15 +22,71 @@ pub struct SemanticSegment {
     pub index: usize,
     pub start_frame_idx: usize,
     pub end_frame_idx: usize,
+    pub start_timestamp_seconds: Timestamp,
+    pub end_timestamp_seconds: Timestamp,
     pub representative_frame: EmbeddedFrame,
+    /// Additional distinct frames when more than one per segment is requested.
+    pub alternate_frames: Vec<EmbeddedFrame>,
     pub frame_count: usize,
+    /// Similarity drop at the boundary that opened this segment
+    /// (`None` for the first segment).
+    pub boundary_drop: Option<f32>,
+    /// How the boundary that opened this segment came about (`None` for the
+    /// first segment).
+    pub boundary_kind: Option<BoundaryKind>,
+    /// Set when the representative was moved off the frame at the
+    /// selection percentile.
+    pub relocation: Option<Relocation>,
+    /// Time from the last frame to the next sample, which the segment runs
+    /// until; 0 when `end_timestamp_seconds` already marks its end.
+    pub stride_seconds: f64,
+}
+
+/// Shape of the similarity decline at a segment boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoundaryKind {
+    /// Similarity fell in a single frame.
+    Cut,
+    /// Similarity was already falling over the frames before the boundary,
+    /// as in a dissolve or fade.
+    Transition,
+}
+
+impl BoundaryKind {
+    /// Classify a boundary from the similarity `drop` at the boundary frame
+    /// and the decline over the frames leading up to it.
+    pub fn classify(drop: f32, lead_in: f32) -> Self {
+        if drop >= CUT_DROP_SHARE * (drop + lead_in.max(0.0)) {
+            BoundaryKind::Cut
+        } else {
+            BoundaryKind::Transition
+        }
+    }
+}
+
+/// Why a segment's representative is not the frame first selected.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Relocation {
+    /// Frame index at the selection percentile.
+    pub from_frame_index: usize,
+    pub reason: RelocationReason,
+    /// Sharpness of the frame that was passed over.
+    pub from_sharpness: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelocationReason {
+    /// Sharpness was below the `--reject-blur` threshold.
+    Blur,
 }
 
 impl SemanticSegment {
-    /// Start timestamp in seconds.
+    /// Start timestamp.
     #[allow(dead_code)]
-    pub fn start_timestamp(&self) -> f64 {
-        self.representative_frame.timestamp_seconds()
+    pub fn start_timestamp(&self) -> Timestamp {
+        self.start_timestamp_seconds
     }
 
     /// Duration of segment in frames.
@@ -26,12 +94,67 @@ impl SemanticSegment {
     pub fn duration_frames(&self) -> usize {
         self.end_frame_idx - self.start_frame_idx
     }
+
+    /// Wall-clock duration of segment in seconds, through the stride after
+    /// its last frame.
+    pub fn duration_seconds(&self) -> f64 {
+        self.end_timestamp_seconds - self.start_timestamp_seconds + self.stride_seconds
+    }
+}
+
+/// A node of the `--hierarchical` scene tree: a coarse super-scene with the
+/// finer scenes inside it as children, or one of those scenes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneNode {
+    pub start_frame_idx: usize,
+    pub end_frame_idx: usize,
+    pub start_timestamp_seconds: Timestamp,
+    pub end_timestamp_seconds: Timestamp,
+    /// Frame index of the scene's representative.
+    pub representative_frame_index: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SceneNode>,
+}
+
+impl SceneNode {
+    fn new(segment: &SemanticSegment, children: Vec<SceneNode>) -> Self {
+        Self {
+            start_frame_idx: segment.start_frame_idx,
+            end_frame_idx: segment.end_frame_idx,
+            start_timestamp_seconds: segment.start_timestamp_seconds,
+            end_timestamp_seconds: segment.end_timestamp_seconds,
+            representative_frame_index: segment.representative_frame.index(),
+            children,
+        }
+    }
+}
+
+/// Segmenter state recorded for one frame when anchor tracing is on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnchorSample {
+    pub frame_index: usize,
+    pub timestamp_seconds: Timestamp,
+    /// Similarity of the frame to the anchor it was compared against.
+    pub similarity: f32,
+    /// Whether this frame opened a new segment.
+    pub boundary: bool,
+    /// Norm of the EMA anchor before re-normalization; values well below
+    /// 1.0 mean the frame pulled the anchor away from its history.
+    pub anchor_norm: f32,
 }
 
 /// Segment video frames by semantic similarity.
+#[derive(Clone)]
 pub struct SemanticSegmenter {
     similarity_threshold: f32,
+    merge_threshold: f32,
     min_segment_frames: usize,
+    anchor_alpha: f32,
+    min_drop: f32,
+    per_segment: usize,
+    representative_percentile: u8,
+    reject_blur: Option<f64>,
+    trace_anchor: bool,
 }
 
 impl SemanticSegmenter {
@@ -39,10 +162,97 @@ impl SemanticSegmenter {
     pub fn new(detail: DetailLevel) -> Self {
         Self {
             similarity_threshold: detail.similarity_threshold(),
+            merge_threshold: detail.merge_threshold(),
             min_segment_frames: detail.min_segment_frames(),
+            anchor_alpha: DEFAULT_ANCHOR_ALPHA,
+            min_drop: 0.0,
+            per_segment: 1,
+            representative_percentile: 50,
+            reject_blur: None,
+            trace_anchor: false,
         }
     }
 
+    /// Move a representative whose sharpness is below `threshold` to the
+    /// nearest sharp enough frame of its segment.
+    ///
+    /// The selected frame is kept when none of the segment qualifies.
+    pub fn with_reject_blur(mut self, threshold: Option<f64>) -> Self {
+        self.reject_blur = threshold;
+        self
+    }
+
+    /// Pick the representative at this percentile of the segment's timeline
+    /// (0-100) instead of the middle. Values above 100 are clamped.
+    pub fn with_representative_percentile(mut self, percentile: u8) -> Self {
+        self.representative_percentile = percentile.min(100);
+        self
+    }
+
+    /// Record an [`AnchorSample`] for every frame, for debugging.
+    pub fn with_anchor_trace(mut self, enabled: bool) -> Self {
+        self.trace_anchor = enabled;
+        self
+    }
+
+    /// Override the detail level's cosine similarity threshold, below which
+    /// a frame opens a candidate boundary (the split threshold).
+    ///
+    /// The merge threshold moves along to [`HYSTERESIS_MARGIN`] above it.
+    pub fn with_similarity_threshold(mut self, threshold: f32) -> Self {
+        self.similarity_threshold = threshold;
+        self.merge_threshold = (threshold + HYSTERESIS_MARGIN).min(1.0);
+        self
+    }
+
+    /// Override the detail level's merge threshold.
+    ///
+    /// A candidate boundary is dropped when the frame after it is at least
+    /// this similar to the current segment's anchor. Values below the split
+    /// threshold act as the split threshold.
+    pub fn with_merge_threshold(mut self, threshold: f32) -> Self {
+        self.merge_threshold = threshold;
+        self
+    }
+
+    /// The split threshold in effect.
+    pub fn similarity_threshold(&self) -> f32 {
+        self.similarity_threshold
+    }
+
+    /// The merge threshold in effect, never below the split threshold.
+    pub fn merge_threshold(&self) -> f32 {
+        self.merge_threshold.max(self.similarity_threshold)
+    }
+
+    /// Set the EMA weight of the existing anchor (0.0-1.0).
+    ///
+    /// Lower values make the anchor track recent frames more closely, which
+    /// suits fast-paced content. Higher values resist gradual drift.
+    pub fn with_anchor_alpha(mut self, alpha: f32) -> Self {
+        self.anchor_alpha = alpha;
+        self
+    }
+
+    /// Require a minimum similarity drop before a boundary is accepted.
+    ///
+    /// The drop is measured from the previous frame's similarity to the
+    /// anchor down to the similarity of the frame that crossed the
+    /// threshold. Weaker boundaries stay in the preceding segment.
+    pub fn with_min_drop(mut self, min_drop: f32) -> Self {
+        self.min_drop = min_drop;
+        self
+    }
+
+    /// Select up to `n` frames per segment instead of only the middle one.
+    ///
+    /// Extra frames are chosen by farthest-point sampling to maximize the
+    /// embedding spread within the segment.
+    pub fn with_per_segment(mut self, n: usize) -> Self {
+        self.per_segment = n.max(1);
+        self
+    }
+
     /// Segment frames into semantically coherent groups.
     ///
     /// The algorithm:
@@ -51,84 +261,177 @@ impl SemanticSegmenter {
     /// 3. If similarity drops below threshold (semantic change detected),
     ///    finalize current segment and start a new one
     /// 4. Enforce minimum segment length to avoid over-segmentation
-    /// 5. Select the middle frame of each segment as representative
+    /// 5. Discard boundaries whose similarity drop is below `min_drop`
+    /// 6. Confirm a boundary only if the next frame is less similar to the
+    ///    old anchor than the merge threshold (hysteresis), so one-frame
+    ///    flashes and similarity hovering at the cutoff don't split
+    /// 7. Select the middle frame (or the configured percentile) of each
+    ///    segment as representative
+    ///
+    /// Inputs with fewer frames than the minimum segment length relax it
+    /// (see [`min_segment_frames_for`](Self::min_segment_frames_for)).
     pub fn segment<F>(
         &self,
         embedded_frames: &[EmbeddedFrame],
-        mut progress_callback: Option<F>,
+        progress_callback: Option<F>,
     ) -> Vec<SemanticSegment>
     where
         F: FnMut(usize, usize),
     {
-        if embedded_frames.is_empty() {
-            return Vec::new();
-        }
+        self.segment_with_trace(embedded_frames, progress_callback)
+            .0
+    }
 
-        let mut segments = Vec::new();
-        let mut segment_start_idx = 0usize;
-        let mut segment_frames: Vec<&EmbeddedFrame> = vec![&embedded_frames[0]];
-        let mut anchor_embedding = embedded_frames[0].embedding.clone();
-
-        for (i, current_frame) in embedded_frames.iter().enumerate().skip(1) {
-            let similarity = cosine_similarity(&anchor_embedding, &current_frame.embedding);
-
-            // Check if we've crossed the similarity threshold
-            // AND we have enough frames in the current segment
-            let is_semantic_change = similarity < self.similarity_threshold;
-            let has_min_frames = segment_frames.len() >= self.min_segment_frames;
-
-            if is_semantic_change && has_min_frames {
-                // Finalize current segment
-                let segment =
-                    self.create_segment(segments.len(), &segment_frames, segment_start_idx);
-                segments.push(segment);
-
-                // Start new segment
-                segment_start_idx = i;
-                segment_frames = vec![current_frame];
-                anchor_embedding = current_frame.embedding.clone();
-            } else {
-                segment_frames.push(current_frame);
-                // Update anchor using exponential moving average
-                anchor_embedding = self.update_anchor(&anchor_embedding, &current_frame.embedding);
+    /// Like [`segment`](Self::segment), also returning the anchor trace.
+    ///
+    /// The trace is empty unless tracing was enabled with
+    /// [`with_anchor_trace`](Self::with_anchor_trace).
+    pub fn segment_with_trace<F>(
+        &self,
+        embedded_frames: &[EmbeddedFrame],
+        mut progress_callback: Option<F>,
+    ) -> (Vec<SemanticSegment>, Vec<AnchorSample>)
+    where
+        F: FnMut(usize, usize),
+    {
+        let total = embedded_frames.len();
+        let min_segment_frames = self.min_segment_frames_for(total);
+        let relaxed;
+        let segmenter = if min_segment_frames != self.min_segment_frames {
+            relaxed = Self {
+                min_segment_frames,
+                ..self.clone()
+            };
+            &relaxed
+        } else {
+            self
+        };
+        let frames = embedded_frames.iter().enumerate().map(|(i, frame)| {
+            if i > 0 {
+                if let Some(ref mut cb) = progress_callback {
+                    cb(i + 1, total);
+                }
             }
+            frame
+        });
 
-            if let Some(ref mut cb) = progress_callback {
-                cb(i + 1, embedded_frames.len());
-            }
+        let mut stream = segmenter.segment_streaming(frames);
+        let segments = stream.by_ref().collect();
+        (segments, stream.into_trace())
+    }
+
+    /// Minimum segment length for an input of `total` frames.
+    ///
+    /// An input shorter than a level's minimum could never split, so every
+    /// detail level would return a single segment. Instead, inputs shorter
+    /// than the coarsest level's minimum shrink every level's minimum in the
+    /// same proportion, keeping the levels in order: at 10 frames `key`
+    /// still needs all 10, `summary` 5 and `all` 2. Streaming segmentation
+    /// doesn't know the input length and keeps the configured minimum.
+    pub fn min_segment_frames_for(&self, total: usize) -> usize {
+        let coarsest = DetailLevel::Key.min_segment_frames();
+        if total >= coarsest {
+            return self.min_segment_frames;
         }
+        (self.min_segment_frames * total).div_ceil(coarsest)
+    }
 
-        // Don't forget the last segment
-        if !segment_frames.is_empty() {
-            let segment = self.create_segment(segments.len(), &segment_frames, segment_start_idx);
-            segments.push(segment);
+    /// Segment a stream of embedded frames lazily.
+    ///
+    /// Frames are pulled only as far as needed to close the next segment, so
+    /// only the frames of the open segment are held at once. Frames may be
+    /// passed by reference or by value.
+    pub fn segment_streaming<I>(&self, frames: I) -> SegmentStream<'_, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<EmbeddedFrame>,
+    {
+        SegmentStream {
+            frames: frames.into_iter(),
+            state: Some(self.start()),
+            tail: Vec::new().into_iter(),
+            trace: Vec::new(),
         }
+    }
 
-        segments
+    /// Begin incremental segmentation, fed one frame at a time.
+    ///
+    /// Frames may be pushed by reference or by value; segments are returned
+    /// as soon as their closing boundary is seen.
+    pub fn start<B: Borrow<EmbeddedFrame>>(&self) -> SegmentState<'_, B> {
+        SegmentState {
+            segmenter: self,
+            frames: Vec::new(),
+            anchor_embedding: Vec::new(),
+            previous_similarity: 1.0,
+            recent_similarities: VecDeque::with_capacity(TRANSITION_LEAD_IN_FRAMES),
+            boundary: None,
+            candidate: None,
+            next_index: 0,
+            trace: Vec::new(),
+            last_timestamp: None,
+            stride_seconds: 0.0,
+        }
     }
 
-    fn create_segment(
+    fn create_segment<B: Borrow<EmbeddedFrame>>(
         &self,
         index: usize,
-        frames: &[&EmbeddedFrame],
-        _start_idx: usize,
+        frames: &[B],
+        boundary: Option<(f32, BoundaryKind)>,
     ) -> SemanticSegment {
-        // Select middle frame as representative (deterministic selection)
-        let representative_idx = frames.len() / 2;
-        let representative = frames[representative_idx].clone();
+        // Select the frame at the configured timeline percentile (middle by
+        // default) as representative
+        let mut representative_idx =
+            (frames.len() * self.representative_percentile as usize / 100).min(frames.len() - 1);
+        let mut relocation = None;
+        if let Some(threshold) = self.reject_blur {
+            let sharpness = |i: usize| frames[i].borrow().frame.quality().ok().map(|q| q.sharpness);
+            if let Some((idx, from_sharpness)) =
+                nearest_sharp_frame(frames.len(), representative_idx, threshold, sharpness)
+            {
+                relocation = Some(Relocation {
+                    from_frame_index: frames[representative_idx].borrow().index(),
+                    reason: RelocationReason::Blur,
+                    from_sharpness,
+                });
+                representative_idx = idx;
+            }
+        }
+        let representative = frames[representative_idx].borrow().clone();
+        let alternate_frames = if self.per_segment > 1 {
+            farthest_point_sample(frames, representative_idx, self.per_segment)
+                .into_iter()
+                .skip(1)
+                .map(|i| frames[i].borrow().clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let first = frames[0].borrow();
+        let last = frames[frames.len() - 1].borrow();
 
         SemanticSegment {
             index,
-            start_frame_idx: frames[0].index(),
-            end_frame_idx: frames[frames.len() - 1].index(),
+            start_frame_idx: first.index(),
+            end_frame_idx: last.index(),
+            start_timestamp_seconds: first.timestamp_seconds(),
+            end_timestamp_seconds: last.timestamp_seconds(),
             representative_frame: representative,
+            alternate_frames,
             frame_count: frames.len(),
+            boundary_drop: boundary.map(|(drop, _)| drop),
+            boundary_kind: boundary.map(|(_, kind)| kind),
+            relocation,
+            stride_seconds: 0.0,
         }
     }
 
-    fn update_anchor(&self, current_anchor: &[f32], new_embedding: &[f32]) -> Vec<f32> {
+    /// Blend a frame into the anchor, returning the re-normalized anchor and
+    /// its norm before re-normalization.
+    fn update_anchor(&self, current_anchor: &[f32], new_embedding: &[f32]) -> (Vec<f32>, f32) {
         // Exponential moving average for stability
-        let alpha = 0.9f32;
+        let alpha = self.anchor_alpha;
 
         let updated: Vec<f32> = current_anchor
             .iter()
@@ -139,11 +442,752 @@ impl SemanticSegmenter {
         // Re-normalize
         let norm: f32 = updated.iter().map(|x| x * x).sum::<f32>().sqrt();
         if norm > 0.0 {
-            updated.iter().map(|x| x / norm).collect()
+            (updated.iter().map(|x| x / norm).collect(), norm)
+        } else {
+            (updated, norm)
+        }
+    }
+}
+
+/// In-progress segmentation over a stream of embedded frames.
+pub struct SegmentState<'s, B> {
+    segmenter: &'s SemanticSegmenter,
+    frames: Vec<B>,
+    anchor_embedding: Vec<f32>,
+    previous_similarity: f32,
+    /// Similarities of the segment's last few frames, oldest first.
+    recent_similarities: VecDeque<f32>,
+    /// Drop and kind of the boundary that opened the current segment.
+    boundary: Option<(f32, BoundaryKind)>,
+    /// Frame that crossed the split threshold, awaiting the next frame to
+    /// confirm or cancel the boundary.
+    candidate: Option<Candidate<B>>,
+    next_index: usize,
+    trace: Vec<AnchorSample>,
+    /// Timestamp of the last frame pushed.
+    last_timestamp: Option<Timestamp>,
+    /// Time between the last two frames pushed, taken as the stride after
+    /// the final segment.
+    stride_seconds: f64,
+}
+
+/// A frame that may open a new segment.
+struct Candidate<B> {
+    frame: B,
+    similarity: f32,
+    drop: f32,
+    kind: BoundaryKind,
+}
+
+impl<B: Borrow<EmbeddedFrame>> SegmentState<'_, B> {
+    /// Add the next frame, returning the previous segment if it just closed.
+    ///
+    /// A segment closes one frame after its boundary, once the boundary is
+    /// confirmed.
+    pub fn push(&mut self, frame: B) -> Option<SemanticSegment> {
+        let timestamp = frame.borrow().timestamp_seconds();
+        if let Some(previous) = self.last_timestamp {
+            self.stride_seconds = timestamp - previous;
+        }
+        self.last_timestamp = Some(timestamp);
+
+        let mut closed = None;
+        if let Some(candidate) = self.candidate.take() {
+            let similarity = cosine_similarity(&self.anchor_embedding, &frame.borrow().embedding);
+            let merge_threshold = self
+                .segmenter
+                .merge_threshold
+                .max(self.segmenter.similarity_threshold);
+            if similarity >= merge_threshold {
+                // Back to the current scene: the candidate was a flicker
+                self.extend(candidate.frame, candidate.similarity);
+            } else {
+                closed = Some(self.open_segment(candidate));
+            }
+        }
+        self.advance(frame);
+        closed
+    }
+
+    /// Compare a frame to the anchor, either holding it as a candidate
+    /// boundary or adding it to the current segment.
+    fn advance(&mut self, frame: B) {
+        let segmenter = self.segmenter;
+
+        if self.frames.is_empty() {
+            self.anchor_embedding = frame.borrow().embedding.clone();
+            self.set_previous_similarity(1.0);
+            self.record(frame.borrow(), 1.0, false, 1.0);
+            self.frames.push(frame);
+            return;
+        }
+
+        let similarity = cosine_similarity(&self.anchor_embedding, &frame.borrow().embedding);
+        let drop = self.previous_similarity - similarity;
+
+        // Check if we've crossed the similarity threshold
+        // AND we have enough frames in the current segment
+        // AND the drop is decisive enough to count as a cut
+        let is_semantic_change = similarity < segmenter.similarity_threshold;
+        let has_min_frames = self.frames.len() >= segmenter.min_segment_frames;
+        let is_strong_drop = drop >= segmenter.min_drop;
+
+        if is_semantic_change && has_min_frames && is_strong_drop {
+            // How far similarity had already fallen from its recent peak
+            let peak = self
+                .recent_similarities
+                .iter()
+                .copied()
+                .fold(self.previous_similarity, f32::max);
+            self.candidate = Some(Candidate {
+                frame,
+                similarity,
+                drop,
+                kind: BoundaryKind::classify(drop, peak - self.previous_similarity),
+            });
+        } else {
+            self.extend(frame, similarity);
+        }
+    }
+
+    /// Add a frame to the current segment, blending it into the anchor.
+    fn extend(&mut self, frame: B, similarity: f32) {
+        // Update anchor using exponential moving average
+        let embedding = &frame.borrow().embedding;
+        let (anchor, norm) = self
+            .segmenter
+            .update_anchor(&self.anchor_embedding, embedding);
+        self.anchor_embedding = anchor;
+        self.set_previous_similarity(similarity);
+        self.record(frame.borrow(), similarity, false, norm);
+        self.frames.push(frame);
+    }
+
+    /// Finalize the current segment and start a new one at `candidate`.
+    fn open_segment(&mut self, candidate: Candidate<B>) -> SemanticSegment {
+        let mut segment =
+            self.segmenter
+                .create_segment(self.next_index, &self.frames, self.boundary);
+        segment.stride_seconds =
+            candidate.frame.borrow().timestamp_seconds() - segment.end_timestamp_seconds;
+        self.next_index += 1;
+
+        self.anchor_embedding = candidate.frame.borrow().embedding.clone();
+        self.recent_similarities.clear();
+        self.set_previous_similarity(1.0);
+        self.boundary = Some((candidate.drop, candidate.kind));
+        self.record(candidate.frame.borrow(), candidate.similarity, true, 1.0);
+        self.frames = vec![candidate.frame];
+
+        segment
+    }
+
+    /// Record the latest frame's similarity, keeping the last few.
+    fn set_previous_similarity(&mut self, similarity: f32) {
+        if self.recent_similarities.len() == TRANSITION_LEAD_IN_FRAMES {
+            self.recent_similarities.pop_front();
+        }
+        self.recent_similarities.push_back(similarity);
+        self.previous_similarity = similarity;
+    }
+
+    fn record(&mut self, frame: &EmbeddedFrame, similarity: f32, boundary: bool, norm: f32) {
+        if self.segmenter.trace_anchor {
+            self.trace.push(AnchorSample {
+                frame_index: frame.index(),
+                timestamp_seconds: frame.timestamp_seconds(),
+                similarity,
+                boundary,
+                anchor_norm: norm,
+            });
+        }
+    }
+
+    /// Close the remaining segments.
+    ///
+    /// A boundary still awaiting confirmation is accepted, since no later
+    /// frame can cancel it.
+    pub fn finish(mut self) -> Vec<SemanticSegment> {
+        let mut segments = Vec::new();
+        if let Some(candidate) = self.candidate.take() {
+            segments.push(self.open_segment(candidate));
+        }
+        if !self.frames.is_empty() {
+            let mut segment =
+                self.segmenter
+                    .create_segment(self.next_index, &self.frames, self.boundary);
+            segment.stride_seconds = self.stride_seconds;
+            segments.push(segment);
+        }
+        segments
+    }
+}
+
+/// Iterator over the segments of a frame stream, from
+/// [`SemanticSegmenter::segment_streaming`].
+pub struct SegmentStream<'s, I: Iterator> {
+    frames: I,
+    /// `None` once the input is exhausted.
+    state: Option<SegmentState<'s, I::Item>>,
+    /// Segments closed by the end of the input, yet to be yielded.
+    tail: std::vec::IntoIter<SemanticSegment>,
+    trace: Vec<AnchorSample>,
+}
+
+impl<I> SegmentStream<'_, I>
+where
+    I: Iterator,
+    I::Item: Borrow<EmbeddedFrame>,
+{
+    /// The anchor trace of the frames consumed so far.
+    ///
+    /// Empty unless tracing was enabled on the segmenter.
+    pub fn into_trace(mut self) -> Vec<AnchorSample> {
+        if let Some(state) = &mut self.state {
+            self.trace.append(&mut state.trace);
+        }
+        self.trace
+    }
+}
+
+impl<I> Iterator for SegmentStream<'_, I>
+where
+    I: Iterator,
+    I::Item: Borrow<EmbeddedFrame>,
+{
+    type Item = SemanticSegment;
+
+    fn next(&mut self) -> Option<SemanticSegment> {
+        if let Some(state) = self.state.as_mut() {
+            for frame in self.frames.by_ref() {
+                if let Some(segment) = state.push(frame) {
+                    return Some(segment);
+                }
+            }
+
+            // Don't forget the last segments
+            if let Some(mut state) = self.state.take() {
+                self.trace.append(&mut state.trace);
+                self.tail = state.finish().into_iter();
+            }
+        }
+        self.tail.next()
+    }
+}
+
+/// Pick up to `n` mutually distant frames, starting from `seed`.
+///
+/// Each step adds the frame whose nearest already-chosen frame is least
+/// similar. Returns indices into `frames`, beginning with `seed`.
+fn farthest_point_sample<B: Borrow<EmbeddedFrame>>(
+    frames: &[B],
+    seed: usize,
+    n: usize,
+) -> Vec<usize> {
+    let mut chosen = vec![seed];
+    // Highest similarity of each frame to any chosen frame
+    let mut nearest: Vec<f32> = frames
+        .iter()
+        .map(|f| cosine_similarity(&f.borrow().embedding, &frames[seed].borrow().embedding))
+        .collect();
+
+    while chosen.len() < n.min(frames.len()) {
+        let next = nearest
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !chosen.contains(i))
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i);
+
+        let Some(next) = next else { break };
+        chosen.push(next);
+
+        let next_embedding = &frames[next].borrow().embedding;
+        for (i, frame) in frames.iter().enumerate() {
+            let similarity = cosine_similarity(&frame.borrow().embedding, next_embedding);
+            nearest[i] = nearest[i].max(similarity);
+        }
+    }
+
+    chosen
+}
+
+/// Find the first frame of a new scene by bisecting between two samples.
+///
+/// `before` and `after` are `(frame index, embedding)` of the last sampled
+/// frame before a boundary and the first one after it. Each step embeds the
+/// midpoint with `embed` and keeps the half whose endpoints are least
+/// similar, so the cut is found in about log2(after - before) embeds instead
+/// of decoding every frame in the gap.
+///
+/// `embed` returns `None` for a frame that can't be embedded (with
+/// `--skip-bad-frames`); bisection stops there and returns the first frame
+/// of the narrowest range found so far.
+pub fn bisect_cut<E>(before: (usize, &[f32]), after: (usize, &[f32]), mut embed: E) -> Result<usize>
+where
+    E: FnMut(usize) -> Result<Option<Vec<f32>>>,
+{
+    let (mut lo, mut hi) = (before.0, after.0);
+    let mut lo_embedding = before.1.to_vec();
+    let mut hi_embedding = after.1.to_vec();
+
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        let Some(mid_embedding) = embed(mid)? else {
+            break;
+        };
+
+        // The cut lies on the side with the larger similarity drop
+        if cosine_similarity(&lo_embedding, &mid_embedding)
+            < cosine_similarity(&mid_embedding, &hi_embedding)
+        {
+            hi = mid;
+            hi_embedding = mid_embedding;
+        } else {
+            lo = mid;
+            lo_embedding = mid_embedding;
+        }
+    }
+
+    Ok(hi)
+}
+
+/// Move every segment boundary onto the exact cut frame.
+///
+/// `frames` are the sampled frames the segments were built from, in order.
+/// Boundaries between adjacent samples are already exact and are skipped.
+pub fn bisect_boundaries<E>(
+    segments: &mut [SemanticSegment],
+    frames: &[EmbeddedFrame],
+    fps: f64,
+    mut embed: E,
+) -> Result<()>
+where
+    E: FnMut(usize) -> Result<Option<Vec<f32>>>,
+{
+    let sample = |index: usize| {
+        frames
+            .binary_search_by_key(&index, |f| f.index())
+            .ok()
+            .map(|i| &frames[i])
+    };
+    let timestamp = |index: usize| Timestamp::from_frame(index, fps);
+
+    for i in 1..segments.len() {
+        let (Some(before), Some(after)) = (
+            sample(segments[i - 1].end_frame_idx),
+            sample(segments[i].start_frame_idx),
+        ) else {
+            continue;
+        };
+        if after.index() - before.index() <= 1 {
+            continue;
+        }
+
+        let cut = bisect_cut(
+            (before.index(), &before.embedding),
+            (after.index(), &after.embedding),
+            &mut embed,
+        )?;
+
+        segments[i - 1].end_frame_idx = cut - 1;
+        segments[i - 1].end_timestamp_seconds = timestamp(cut - 1);
+        segments[i - 1].stride_seconds = timestamp(cut) - timestamp(cut - 1);
+        segments[i].start_frame_idx = cut;
+        segments[i].start_timestamp_seconds = timestamp(cut);
+    }
+
+    Ok(())
+}
+
+/// Keep segments `0, k, 2k, ...` and renumber them consecutively.
+///
+/// A purely positional reducer: which segments survive depends only on their
+/// order, never on their content. `k` of 0 or 1 keeps everything.
+pub fn every_nth_segment(segments: Vec<SemanticSegment>, k: usize) -> Vec<SemanticSegment> {
+    segments
+        .into_iter()
+        .step_by(k.max(1))
+        .enumerate()
+        .map(|(index, segment)| SemanticSegment { index, ..segment })
+        .collect()
+}
+
+/// Drop each segment whose representative is less than `seconds` after the
+/// previous kept segment's, then renumber.
+///
+/// Greedy from the start, so the first segment is always kept. Unlike the
+/// minimum segment length, which shapes detection, this only thins the
+/// stills that are written.
+pub fn space_out_segments(segments: Vec<SemanticSegment>, seconds: f64) -> Vec<SemanticSegment> {
+    let mut kept: Vec<SemanticSegment> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let too_close = kept.last().is_some_and(|previous| {
+            segment
+                .representative_frame
+                .timestamp_seconds()
+                .as_seconds()
+                - previous
+                    .representative_frame
+                    .timestamp_seconds()
+                    .as_seconds()
+                < seconds
+        });
+        if !too_close {
+            kept.push(SemanticSegment {
+                index: kept.len(),
+                ..segment
+            });
+        }
+    }
+    kept
+}
+
+/// Keep at most `n` segments, chosen by `strategy`, in timeline order, then
+/// renumber.
+///
+/// `Distinct` picks representatives by farthest-point sampling from the
+/// first segment's. `Coverage` picks the representatives nearest the
+/// centers of `n` equal slices of the analyzed span, from the first
+/// segment's start to the last one's end (see [`coverage_picks`]), for an
+/// evenly paced storyboard of a trimmed or offset run too.
+pub fn limit_segments(
+    segments: Vec<SemanticSegment>,
+    n: usize,
+    strategy: MaxFramesStrategy,
+) -> Vec<SemanticSegment> {
+    if segments.len() <= n {
+        return segments;
+    }
+    let mut keep = match strategy {
+        MaxFramesStrategy::Distinct => {
+            let representatives: Vec<&EmbeddedFrame> =
+                segments.iter().map(|s| &s.representative_frame).collect();
+            farthest_point_sample(&representatives, 0, n)
+        }
+        MaxFramesStrategy::Coverage => {
+            let times: Vec<f64> = segments
+                .iter()
+                .map(|s| s.representative_frame.timestamp_seconds().as_seconds())
+                .collect();
+            let span = match (segments.first(), segments.last()) {
+                (Some(first), Some(last)) => {
+                    first.start_timestamp_seconds.as_seconds()
+                        ..last.end_timestamp_seconds.as_seconds()
+                }
+                _ => 0.0..0.0,
+            };
+            coverage_picks(&times, n, span)
+        }
+    };
+    keep.sort_unstable();
+
+    segments
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| keep.binary_search(i).is_ok())
+        .enumerate()
+        .map(|(index, (_, segment))| SemanticSegment { index, ..segment })
+        .collect()
+}
+
+/// Indices of `n` of the ascending `times`, matched in order to the centers
+/// of `n` equal slices of `span` so that the total distance from each pick
+/// to its slice center is as small as possible.
+///
+/// Solved exactly by dynamic programming over (slices, times), so a cluster
+/// of nearby times can't crowd out a sparse stretch of the video.
+fn coverage_picks(times: &[f64], n: usize, span: Range<f64>) -> Vec<usize> {
+    let m = times.len();
+    let n = n.min(m);
+    let center = |k: usize| span.start + (span.end - span.start) * (k as f64 + 0.5) / n as f64;
+
+    // cost[k][j]: least total distance matching the first k slices to k of
+    // the first j times
+    let mut cost = vec![vec![f64::INFINITY; m + 1]; n + 1];
+    cost[0].fill(0.0);
+    for k in 1..=n {
+        for j in k..=m {
+            let take = cost[k - 1][j - 1] + (times[j - 1] - center(k - 1)).abs();
+            cost[k][j] = cost[k][j - 1].min(take);
+        }
+    }
+
+    let mut picks = Vec::with_capacity(n);
+    let (mut k, mut j) = (n, m);
+    while k > 0 {
+        if cost[k][j] == cost[k][j - 1] {
+            j -= 1;
         } else {
-            updated
+            picks.push(j - 1);
+            k -= 1;
+            j -= 1;
         }
     }
+    picks.reverse();
+    picks
+}
+
+/// Fold each segment whose representative is a near pixel-level duplicate of
+/// the previous kept segment's (SSIM of at least `threshold`) into that
+/// segment, then renumber.
+///
+/// Complements embedding similarity, which can rate visually identical
+/// stills as slightly different. Returns the kept segments and how many
+/// were folded away.
+pub fn merge_near_duplicates(
+    segments: Vec<SemanticSegment>,
+    threshold: f64,
+) -> Result<(Vec<SemanticSegment>, usize)> {
+    let mut kept: Vec<SemanticSegment> = Vec::with_capacity(segments.len());
+    let mut removed = 0;
+    for segment in segments {
+        if let Some(previous) = kept.last_mut() {
+            let similarity = previous
+                .representative_frame
+                .frame
+                .ssim(&segment.representative_frame.frame)?;
+            if similarity >= threshold {
+                previous.end_frame_idx = segment.end_frame_idx;
+                previous.end_timestamp_seconds = segment.end_timestamp_seconds;
+                previous.stride_seconds = segment.stride_seconds;
+                previous.frame_count += segment.frame_count;
+                removed += 1;
+                continue;
+            }
+        }
+        kept.push(segment);
+    }
+
+    for (index, segment) in kept.iter_mut().enumerate() {
+        segment.index = index;
+    }
+    Ok((kept, removed))
+}
+
+/// Make the first and last of `frames` representatives of their own
+/// one-frame segments, trimming the neighbouring segment.
+///
+/// Segments whose representative already is an endpoint are left alone, so
+/// a single-frame video yields no duplicates.
+pub fn include_endpoints(
+    mut segments: Vec<SemanticSegment>,
+    frames: &[EmbeddedFrame],
+) -> Vec<SemanticSegment> {
+    let (Some(first), Some(last)) = (frames.first(), frames.last()) else {
+        return segments;
+    };
+
+    if segments
+        .first()
+        .is_some_and(|s| s.representative_frame.index() != first.index())
+    {
+        // The segment holds at least one frame besides `first`
+        let next = &frames[1];
+        let segment = &mut segments[0];
+        segment.start_frame_idx = next.index();
+        segment.start_timestamp_seconds = next.timestamp_seconds();
+        segment.frame_count -= 1;
+        segment
+            .alternate_frames
+            .retain(|f| f.index() != first.index());
+        let stride_seconds = next.timestamp_seconds() - first.timestamp_seconds();
+        segments.insert(0, endpoint_segment(first, stride_seconds));
+    }
+
+    if segments
+        .last()
+        .is_some_and(|s| s.representative_frame.index() != last.index())
+    {
+        let previous = &frames[frames.len() - 2];
+        let segment = segments.last_mut().unwrap();
+        let stride_seconds = segment.stride_seconds;
+        segment.end_frame_idx = previous.index();
+        segment.end_timestamp_seconds = previous.timestamp_seconds();
+        segment.stride_seconds = last.timestamp_seconds() - previous.timestamp_seconds();
+        segment.frame_count -= 1;
+        segment
+            .alternate_frames
+            .retain(|f| f.index() != last.index());
+        segments.push(endpoint_segment(last, stride_seconds));
+    }
+
+    for (index, segment) in segments.iter_mut().enumerate() {
+        segment.index = index;
+    }
+    segments
+}
+
+/// If frame `selected` of `len` is blurrier than `threshold`, find the
+/// nearest frame at or above it, searching outward and trying the earlier
+/// side first on ties.
+///
+/// Returns the replacement's position and the selected frame's sharpness.
+/// Frames whose sharpness can't be measured are never rejected or chosen.
+fn nearest_sharp_frame(
+    len: usize,
+    selected: usize,
+    threshold: f64,
+    mut sharpness: impl FnMut(usize) -> Option<f64>,
+) -> Option<(usize, f64)> {
+    let from_sharpness = sharpness(selected)?;
+    if from_sharpness >= threshold {
+        return None;
+    }
+
+    (1..len)
+        .flat_map(|d| [selected.checked_sub(d), Some(selected + d)])
+        .flatten()
+        .filter(|&i| i < len)
+        .find(|&i| sharpness(i).is_some_and(|s| s >= threshold))
+        .map(|i| (i, from_sharpness))
+}
+
+/// A segment made of `frame` alone, running `stride_seconds` past it.
+fn endpoint_segment(frame: &EmbeddedFrame, stride_seconds: f64) -> SemanticSegment {
+    SemanticSegment {
+        index: 0,
+        start_frame_idx: frame.index(),
+        end_frame_idx: frame.index(),
+        start_timestamp_seconds: frame.timestamp_seconds(),
+        end_timestamp_seconds: frame.timestamp_seconds(),
+        representative_frame: frame.clone(),
+        alternate_frames: Vec::new(),
+        frame_count: 1,
+        boundary_drop: None,
+        boundary_kind: None,
+        relocation: None,
+        stride_seconds,
+    }
+}
+
+/// Segment `frames` into super-scenes with `coarse`, then the frames of each
+/// super-scene into scenes with `fine`.
+pub fn scene_tree(
+    coarse: &SemanticSegmenter,
+    fine: &SemanticSegmenter,
+    frames: &[EmbeddedFrame],
+) -> Vec<SceneNode> {
+    coarse
+        .segment::<fn(usize, usize)>(frames, None)
+        .iter()
+        .map(|outer| {
+            let first = frames.partition_point(|f| f.index() < outer.start_frame_idx);
+            let end = frames.partition_point(|f| f.index() <= outer.end_frame_idx);
+            let children = fine
+                .segment::<fn(usize, usize)>(&frames[first..end], None)
+                .iter()
+                .map(|inner| SceneNode::new(inner, Vec::new()))
+                .collect();
+            SceneNode::new(outer, children)
+        })
+        .collect()
+}
+
+/// Fraction of consecutive frame pairs whose similarity falls below
+/// `threshold`, or 0 for fewer than two frames.
+pub fn change_share(frames: &[EmbeddedFrame], threshold: f32) -> f32 {
+    let pairs = frames.len().saturating_sub(1);
+    if pairs == 0 {
+        return 0.0;
+    }
+    let changes = frames
+        .windows(2)
+        .filter(|pair| cosine_similarity(&pair[0].embedding, &pair[1].embedding) < threshold)
+        .count();
+    changes as f32 / pairs as f32
+}
+
+/// The segment whose representative is most similar to the mean of all
+/// representatives' embeddings, or `None` without segments.
+///
+/// Ties go to the earliest segment.
+pub fn most_central_segment(segments: &[SemanticSegment]) -> Option<&SemanticSegment> {
+    let dim = segments.first()?.representative_frame.embedding.len();
+    let mut centroid = vec![0.0f32; dim];
+    for segment in segments {
+        for (sum, x) in centroid
+            .iter_mut()
+            .zip(&segment.representative_frame.embedding)
+        {
+            *sum += x;
+        }
+    }
+
+    // Scaling the centroid to unit length wouldn't change the ranking
+    let mut best: Option<(&SemanticSegment, f32)> = None;
+    for segment in segments {
+        let similarity = cosine_similarity(&segment.representative_frame.embedding, &centroid);
+        if best.is_none_or(|(_, b)| similarity > b) {
+            best = Some((segment, similarity));
+        }
+    }
+    best.map(|(segment, _)| segment)
+}
+
+/// The segment whose representative `strategy` picks as the poster, or
+/// `None` without segments.
+///
+/// Ties go to the earliest segment.
+pub fn poster_segment(
+    segments: &[SemanticSegment],
+    strategy: PosterStrategy,
+) -> Option<&SemanticSegment> {
+    match strategy {
+        PosterStrategy::Centroid => most_central_segment(segments),
+        PosterStrategy::Longest => segments.iter().reduce(|best, s| {
+            if s.frame_count > best.frame_count {
+                s
+            } else {
+                best
+            }
+        }),
+        PosterStrategy::First => segments.first(),
+    }
+}
+
+/// Build one segment per frame for a fixed time grid, without embeddings.
+///
+/// Each segment spans from its frame to the next grid frame (or to
+/// `end_seconds`, where the analyzed part of the video ends), so the regular
+/// output writer can be reused.
+pub fn uniform_segments(frames: Vec<Frame>, end_seconds: f64) -> Vec<SemanticSegment> {
+    let bounds: Vec<(usize, Timestamp)> = frames
+        .iter()
+        .map(|f| (f.index, f.timestamp_seconds))
+        .collect();
+
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            let (end_frame_idx, end_timestamp_seconds) = match bounds.get(i + 1) {
+                Some(&(next_idx, next_ts)) => (next_idx.saturating_sub(1), next_ts),
+                None => (
+                    frame.index,
+                    Timestamp::from_seconds(end_seconds).max(frame.timestamp_seconds),
+                ),
+            };
+
+            SemanticSegment {
+                index: i,
+                start_frame_idx: frame.index,
+                end_frame_idx,
+                start_timestamp_seconds: frame.timestamp_seconds,
+                end_timestamp_seconds,
+                representative_frame: EmbeddedFrame {
+                    frame,
+                    embedding: Vec::new(),
+                },
+                alternate_frames: Vec::new(),
+                frame_count: 1,
+                boundary_drop: None,
+                boundary_kind: None,
+                relocation: None,
+                stride_seconds: 0.0,
+            }
+        })
+        .collect()
 }
 
 /// Select representative frames from segments in deterministic order.
@@ -160,15 +1204,15 @@ pub fn deterministic_frame_selection(segments: &[SemanticSegment]) -> Vec<&Embed
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::video::Frame;
 
     fn create_mock_frame(index: usize, timestamp: f64) -> Frame {
         Frame {
             index,
-            timestamp_seconds: timestamp,
+            timestamp_seconds: Timestamp::from_seconds(timestamp),
             data: vec![0u8; 100 * 100 * 3],
             width: 100,
             height: 100,
+            data16: None,
         }
     }
 
@@ -187,6 +1231,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scene_tree() {
+        // An opening scene, then two related scenes that only a fine
+        // threshold tells apart
+        let frames: Vec<_> = (0..90)
+            .map(|i| {
+                let embedding = match i / 30 {
+                    0 => vec![1.0, 0.0, 0.0],
+                    1 => vec![0.0, 1.0, 0.0],
+                    _ => vec![0.0, 0.8, 0.6],
+                };
+                create_embedded_frame(i, i as f64 / 30.0, embedding)
+            })
+            .collect();
+        let coarse = SemanticSegmenter::new(DetailLevel::All).with_similarity_threshold(0.5);
+        let fine = SemanticSegmenter::new(DetailLevel::All).with_similarity_threshold(0.9);
+
+        let tree = scene_tree(&coarse, &fine, &frames);
+        let spans = |nodes: &[SceneNode]| {
+            nodes
+                .iter()
+                .map(|n| (n.start_frame_idx, n.end_frame_idx))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(spans(&tree), vec![(0, 29), (30, 89)]);
+        assert_eq!(spans(&tree[0].children), vec![(0, 29)]);
+        assert_eq!(spans(&tree[1].children), vec![(30, 59), (60, 89)]);
+    }
+
     #[test]
     fn test_empty_input() {
         let segmenter = SemanticSegmenter::new(DetailLevel::Summary);
@@ -203,6 +1276,99 @@ mod tests {
 
         assert_eq!(segments.len(), 1);
         assert_eq!(segments[0].frame_count, 1);
+        assert_eq!(segments[0].duration_seconds(), 0.0);
+    }
+
+    #[test]
+    fn test_short_input_relaxes_min_segment_frames() {
+        // Ten frames, far fewer than any level's minimum segment length
+        let frames: Vec<_> = (0..10)
+            .map(|i| {
+                let embedding = match i {
+                    0..=1 => vec![1.0, 0.0, 0.0],
+                    2..=4 => vec![0.0, 1.0, 0.0],
+                    _ => vec![0.0, 0.0, 1.0],
+                };
+                create_embedded_frame(i, i as f64 / 30.0, embedding)
+            })
+            .collect();
+        let count = |detail| {
+            SemanticSegmenter::new(detail)
+                .segment::<fn(usize, usize)>(&frames, None)
+                .len()
+        };
+
+        assert_eq!(count(DetailLevel::Key), 1);
+        assert_eq!(count(DetailLevel::Summary), 2);
+        assert_eq!(count(DetailLevel::All), 3);
+
+        let segmenter = SemanticSegmenter::new(DetailLevel::All);
+        assert_eq!(segmenter.min_segment_frames_for(10), 2);
+        assert_eq!(segmenter.min_segment_frames_for(200), 15);
+    }
+
+    #[test]
+    fn test_short_input_keeps_detail_levels_ordered() {
+        // Twenty frames: longer than `all`'s minimum, shorter than the others
+        let frames: Vec<_> = (0..20)
+            .map(|i| {
+                let mut embedding = vec![0.0; 4];
+                embedding[i / 5] = 1.0;
+                create_embedded_frame(i, i as f64 / 30.0, embedding)
+            })
+            .collect();
+        let count = |detail| {
+            SemanticSegmenter::new(detail)
+                .segment::<fn(usize, usize)>(&frames, None)
+                .len()
+        };
+
+        assert_eq!(count(DetailLevel::Key), 1);
+        assert_eq!(count(DetailLevel::Summary), 2);
+        assert_eq!(count(DetailLevel::All), 4);
+
+        let minimum = |detail| SemanticSegmenter::new(detail).min_segment_frames_for(20);
+        assert_eq!(minimum(DetailLevel::Key), 20);
+        assert_eq!(minimum(DetailLevel::Summary), 10);
+        assert_eq!(minimum(DetailLevel::All), 4);
+    }
+
+    #[test]
+    fn test_segment_bounds() {
+        let frames: Vec<_> = (0..60)
+            .map(|i| create_embedded_frame(i * 5, i as f64 / 6.0, vec![1.0, 0.0, 0.0]))
+            .collect();
+
+        let segmenter = SemanticSegmenter::new(DetailLevel::Summary);
+        let segments = segmenter.segment::<fn(usize, usize)>(&frames, None);
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].start_frame_idx, 0);
+        assert_eq!(segments[0].end_frame_idx, 295);
+        assert_eq!(segments[0].start_timestamp_seconds, Timestamp::ZERO);
+        assert!((segments[0].end_timestamp_seconds.as_seconds() - 59.0 / 6.0).abs() < 1e-9);
+        // Through the stride after the last frame
+        assert!((segments[0].duration_seconds() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_segment_durations_cover_strides() {
+        // Two scenes of fifteen frames, sampled once a second
+        let frames: Vec<_> = (0..30)
+            .map(|i| {
+                let embedding = if i < 15 {
+                    vec![1.0, 0.0]
+                } else {
+                    vec![0.0, 1.0]
+                };
+                create_embedded_frame(i * 30, i as f64, embedding)
+            })
+            .collect();
+
+        let segments =
+            SemanticSegmenter::new(DetailLevel::All).segment::<fn(usize, usize)>(&frames, None);
+        let durations: Vec<f64> = segments.iter().map(|s| s.duration_seconds()).collect();
+        assert_eq!(durations, vec![15.0, 15.0]);
     }
 
     #[test]
@@ -222,4 +1388,644 @@ mod tests {
             assert!(segments[i].index > segments[i - 1].index);
         }
     }
+
+    #[test]
+    fn test_segment_streaming_is_lazy() {
+        use std::cell::Cell;
+
+        // Three flat scenes of 20 frames each
+        let pulled = Cell::new(0);
+        let frames = (0..60).map(|i| {
+            pulled.set(pulled.get() + 1);
+            let mut embedding = vec![0.0; 3];
+            embedding[i / 20] = 1.0;
+            create_embedded_frame(i, i as f64, embedding)
+        });
+
+        let segmenter = SemanticSegmenter::new(DetailLevel::All);
+        let mut stream = segmenter.segment_streaming(frames);
+
+        let first = stream.next().unwrap();
+        assert_eq!((first.start_frame_idx, first.end_frame_idx), (0, 19));
+        // Only the boundary frame and the one confirming it were read past it
+        assert_eq!(pulled.get(), 22);
+
+        let rest: Vec<_> = stream.collect();
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[1].end_frame_idx, 59);
+        assert_eq!(pulled.get(), 60);
+    }
+
+    #[test]
+    fn test_merge_near_duplicates() {
+        let still = |index: usize, shade: u8| {
+            let mut frame = create_embedded_frame(index, index as f64, vec![1.0, 0.0, 0.0]);
+            // Left half dark, right half at `shade`
+            frame.frame.data = (0..16 * 16)
+                .flat_map(|i| {
+                    let v = if i % 16 < 8 { 10 } else { shade };
+                    [v, v, v]
+                })
+                .collect();
+            frame.frame.width = 16;
+            frame.frame.height = 16;
+            frame
+        };
+        let segments: Vec<_> = [(0, 200), (10, 201), (20, 90), (30, 200)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (index, shade))| {
+                let frames = vec![still(index, shade), still(index + 9, shade)];
+                SemanticSegmenter::new(DetailLevel::All).create_segment(i, &frames, None)
+            })
+            .collect();
+
+        let (merged, removed) = merge_near_duplicates(segments.clone(), 0.95).unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(
+            (merged[0].start_frame_idx, merged[0].end_frame_idx),
+            (0, 19)
+        );
+        assert_eq!(merged[0].frame_count, 4);
+        assert_eq!(merged[1].start_frame_idx, 20);
+        assert_eq!(
+            merged.iter().map(|s| s.index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        let (kept, removed) = merge_near_duplicates(segments, 1.0 + f64::EPSILON).unwrap();
+        assert_eq!((kept.len(), removed), (4, 0));
+    }
+
+    #[test]
+    fn test_most_central_segment() {
+        let norm = |v: [f32; 3]| {
+            let len = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            v.map(|x| x / len).to_vec()
+        };
+        let segments: Vec<_> = [[1.0, 0.0, 0.0], [0.7, 0.7, 0.0], [0.0, 1.0, 0.0]]
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let frames = vec![create_embedded_frame(i * 10, i as f64, norm(v))];
+                SemanticSegmenter::new(DetailLevel::All).create_segment(i, &frames, None)
+            })
+            .collect();
+
+        assert_eq!(most_central_segment(&segments).unwrap().index, 1);
+        assert_eq!(most_central_segment(&segments[..1]).unwrap().index, 0);
+        // Equally central representatives resolve to the first
+        let ends = [segments[0].clone(), segments[2].clone()];
+        assert_eq!(most_central_segment(&ends).unwrap().index, 0);
+        assert!(most_central_segment(&[]).is_none());
+    }
+
+    #[test]
+    fn test_poster_segment() {
+        let segments: Vec<_> = [[1.0, 0.0], [0.7, 0.7], [0.0, 1.0]]
+            .into_iter()
+            .zip([3, 2, 3])
+            .enumerate()
+            .map(|(i, (v, len))| {
+                let frames: Vec<_> = (0..len)
+                    .map(|j| create_embedded_frame(i * 10 + j, (i * 10 + j) as f64, v.to_vec()))
+                    .collect();
+                SemanticSegmenter::new(DetailLevel::All).create_segment(i, &frames, None)
+            })
+            .collect();
+
+        let pick = |strategy| poster_segment(&segments, strategy).unwrap().index;
+        assert_eq!(pick(PosterStrategy::Centroid), 1);
+        // Equally long segments resolve to the first
+        assert_eq!(pick(PosterStrategy::Longest), 0);
+        assert_eq!(pick(PosterStrategy::First), 0);
+        assert_eq!(
+            poster_segment(&segments[1..], PosterStrategy::Longest)
+                .unwrap()
+                .index,
+            2
+        );
+        assert!(poster_segment(&[], PosterStrategy::Longest).is_none());
+    }
+
+    #[test]
+    fn test_hysteresis_cancels_flash_frames() {
+        // 40 frames of one scene with a single unrelated frame at 20
+        let frames: Vec<_> = (0..40)
+            .map(|i| {
+                let embedding = if i == 20 {
+                    vec![0.0, 1.0, 0.0]
+                } else {
+                    vec![1.0, 0.0, 0.0]
+                };
+                create_embedded_frame(i, i as f64, embedding)
+            })
+            .collect();
+
+        let segments =
+            SemanticSegmenter::new(DetailLevel::All).segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].frame_count, 40);
+
+        // A merge threshold nothing can reach confirms every candidate
+        let segments = SemanticSegmenter::new(DetailLevel::All)
+            .with_merge_threshold(1.5)
+            .segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].start_frame_idx, 20);
+    }
+
+    #[test]
+    fn test_boundary_kind() {
+        assert_eq!(BoundaryKind::classify(0.5, 0.0), BoundaryKind::Cut);
+        assert_eq!(BoundaryKind::classify(0.1, 0.3), BoundaryKind::Transition);
+
+        // 20 frames of A, then B: abruptly, or mixed in over 8 frames
+        let scene = |blend: fn(usize) -> f32| -> Vec<EmbeddedFrame> {
+            (0..40)
+                .map(|i| {
+                    let t = blend(i);
+                    create_embedded_frame(i, i as f64, vec![1.0 - t, t, 0.0])
+                })
+                .collect()
+        };
+        let segmenter = SemanticSegmenter::new(DetailLevel::All);
+
+        let cut = segmenter.segment::<fn(usize, usize)>(&scene(|i| (i >= 20) as u8 as f32), None);
+        assert_eq!(cut.len(), 2);
+        assert_eq!(cut[0].boundary_kind, None);
+        assert_eq!(cut[1].boundary_kind, Some(BoundaryKind::Cut));
+
+        let dissolve = segmenter.segment::<fn(usize, usize)>(
+            &scene(|i| (i.saturating_sub(20) as f32 / 8.0).min(1.0)),
+            None,
+        );
+        assert_eq!(dissolve.len(), 2);
+        assert_eq!(dissolve[1].boundary_kind, Some(BoundaryKind::Transition));
+    }
+
+    #[test]
+    fn test_merge_threshold_decides_candidates() {
+        // After 20 frames of A, frame 20 drops below the split threshold and
+        // the rest are 0.78 similar to A: above the split threshold (0.75)
+        // but below the default merge threshold (0.80)
+        let hover = vec![0.78, (1.0f32 - 0.78 * 0.78).sqrt(), 0.0];
+        let frames: Vec<_> = (0..40)
+            .map(|i| {
+                let embedding = match i {
+                    0..=19 => vec![1.0, 0.0, 0.0],
+                    20 => vec![0.0, 1.0, 0.0],
+                    _ => hover.clone(),
+                };
+                create_embedded_frame(i, i as f64, embedding)
+            })
+            .collect();
+
+        let split =
+            SemanticSegmenter::new(DetailLevel::All).segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[1].start_frame_idx, 20);
+        assert!(split[1].boundary_drop.is_some());
+
+        let merged = SemanticSegmenter::new(DetailLevel::All)
+            .with_merge_threshold(0.77)
+            .segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(merged.len(), 1);
+
+        // A pending boundary on the last frame is kept
+        let segments = SemanticSegmenter::new(DetailLevel::All)
+            .segment::<fn(usize, usize)>(&frames[..21], None);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].frame_count, 1);
+    }
+
+    #[test]
+    fn test_incremental_matches_batch() {
+        let frames: Vec<_> = (0..100)
+            .map(|i| {
+                let angle = (i as f32) * 0.1;
+                create_embedded_frame(i, i as f64 / 30.0, vec![angle.cos(), angle.sin(), 0.0])
+            })
+            .collect();
+
+        let segmenter = SemanticSegmenter::new(DetailLevel::All);
+        let batch = segmenter.segment::<fn(usize, usize)>(&frames, None);
+
+        let mut state = segmenter.start();
+        let mut incremental = Vec::new();
+        for frame in frames.clone() {
+            incremental.extend(state.push(frame));
+        }
+        incremental.extend(state.finish());
+
+        assert_eq!(batch.len(), incremental.len());
+        for (a, b) in batch.iter().zip(incremental.iter()) {
+            assert_eq!(a.index, b.index);
+            assert_eq!(a.start_frame_idx, b.start_frame_idx);
+            assert_eq!(a.end_frame_idx, b.end_frame_idx);
+            assert_eq!(
+                a.representative_frame.index(),
+                b.representative_frame.index()
+            );
+        }
+    }
+
+    #[test]
+    fn test_farthest_point_sample() {
+        let frames = vec![
+            create_embedded_frame(0, 0.0, vec![1.0, 0.0, 0.0]),
+            create_embedded_frame(1, 0.1, vec![1.0, 0.1, 0.0]),
+            create_embedded_frame(2, 0.2, vec![0.0, 1.0, 0.0]),
+            create_embedded_frame(3, 0.3, vec![0.0, 0.0, 1.0]),
+        ];
+
+        let chosen = farthest_point_sample(&frames, 1, 3);
+        assert_eq!(chosen[0], 1);
+        assert!(chosen.contains(&2));
+        assert!(chosen.contains(&3));
+
+        assert_eq!(farthest_point_sample(&frames, 0, 10).len(), 4);
+    }
+
+    #[test]
+    fn test_per_segment_default_has_no_alternates() {
+        let frames: Vec<_> = (0..10)
+            .map(|i| create_embedded_frame(i, i as f64, vec![1.0, i as f32, 0.0]))
+            .collect();
+
+        let single =
+            SemanticSegmenter::new(DetailLevel::Key).segment::<fn(usize, usize)>(&frames, None);
+        assert!(single[0].alternate_frames.is_empty());
+
+        let multi = SemanticSegmenter::new(DetailLevel::Key)
+            .with_per_segment(3)
+            .segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(multi[0].alternate_frames.len(), 2);
+    }
+
+    #[test]
+    fn test_uniform_segments() {
+        let frames: Vec<_> = [0, 60, 120]
+            .iter()
+            .map(|&i| create_mock_frame(i, i as f64 / 30.0))
+            .collect();
+
+        let segments = uniform_segments(frames, 5.0);
+
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].end_frame_idx, 59);
+        assert_eq!(
+            segments[1].start_timestamp_seconds,
+            Timestamp::from_seconds(2.0)
+        );
+        assert_eq!(
+            segments[1].end_timestamp_seconds,
+            Timestamp::from_seconds(4.0)
+        );
+        assert_eq!(
+            segments[2].end_timestamp_seconds,
+            Timestamp::from_seconds(5.0)
+        );
+        assert_eq!(segments[2].representative_frame.index(), 120);
+    }
+
+    #[test]
+    fn test_anchor_alpha_changes_boundaries() {
+        // Slowly drifting embeddings: no single step is a semantic change,
+        // but the accumulated drift is.
+        let frames: Vec<_> = (0..300)
+            .map(|i| {
+                let angle = (i as f32) * 0.02;
+                create_embedded_frame(i, i as f64 / 30.0, vec![angle.cos(), angle.sin(), 0.0])
+            })
+            .collect();
+
+        let stable = SemanticSegmenter::new(DetailLevel::All)
+            .with_anchor_alpha(0.99)
+            .segment::<fn(usize, usize)>(&frames, None);
+        let responsive = SemanticSegmenter::new(DetailLevel::All)
+            .with_anchor_alpha(0.5)
+            .segment::<fn(usize, usize)>(&frames, None);
+
+        assert!(stable.len() > responsive.len());
+    }
+
+    #[test]
+    fn test_min_drop_filters_weak_boundaries() {
+        // A gradual drift followed by a hard cut at frame 200.
+        let frames: Vec<_> = (0..300)
+            .map(|i| {
+                if i < 200 {
+                    let angle = (i as f32) * 0.004;
+                    create_embedded_frame(i, i as f64 / 30.0, vec![angle.cos(), angle.sin(), 0.0])
+                } else {
+                    create_embedded_frame(i, i as f64 / 30.0, vec![0.0, 0.0, 1.0])
+                }
+            })
+            .collect();
+
+        let unfiltered = SemanticSegmenter::new(DetailLevel::All)
+            .with_anchor_alpha(1.0)
+            .segment::<fn(usize, usize)>(&frames, None);
+        let filtered = SemanticSegmenter::new(DetailLevel::All)
+            .with_anchor_alpha(1.0)
+            .with_min_drop(0.5)
+            .segment::<fn(usize, usize)>(&frames, None);
+
+        assert!(unfiltered.len() > 2);
+        assert_eq!(filtered.len(), 2);
+        assert_eq!(filtered[1].start_frame_idx, 200);
+        assert!(filtered[1].boundary_drop.unwrap() >= 0.5);
+        assert!(filtered[0].boundary_drop.is_none());
+    }
+
+    #[test]
+    fn test_nearest_sharp_frame() {
+        let scores = [50.0, 5.0, 20.0, 2.0, 1.0, 80.0];
+        let sharpness = |i: usize| Some(scores[i]);
+
+        // A sharp enough selection is kept
+        assert_eq!(nearest_sharp_frame(6, 2, 10.0, sharpness), None);
+        // Nearest qualifying frame wins, earlier side first on ties
+        assert_eq!(nearest_sharp_frame(6, 3, 10.0, sharpness), Some((2, 2.0)));
+        assert_eq!(nearest_sharp_frame(6, 4, 60.0, sharpness), Some((5, 1.0)));
+        assert_eq!(nearest_sharp_frame(6, 1, 30.0, sharpness), Some((0, 5.0)));
+        // Nothing qualifies
+        assert_eq!(nearest_sharp_frame(6, 3, 100.0, sharpness), None);
+        // Unmeasurable frames are neither rejected nor chosen
+        assert_eq!(nearest_sharp_frame(3, 1, 10.0, |_| None), None);
+    }
+
+    #[test]
+    fn test_representative_percentile() {
+        let frames: Vec<_> = (0..10)
+            .map(|i| create_embedded_frame(i, i as f64, vec![1.0, 0.0]))
+            .collect();
+        let representative = |percentile| {
+            SemanticSegmenter::new(DetailLevel::All)
+                .with_representative_percentile(percentile)
+                .segment::<fn(usize, usize)>(&frames, None)[0]
+                .representative_frame
+                .index()
+        };
+
+        assert_eq!(representative(50), 5);
+        assert_eq!(representative(75), 7);
+        assert_eq!(representative(0), 0);
+        assert_eq!(representative(100), 9);
+        assert_eq!(representative(200), 9);
+    }
+
+    #[test]
+    fn test_anchor_trace() {
+        let frames: Vec<_> = (0..60)
+            .map(|i| {
+                let embedding = if i < 30 {
+                    vec![1.0, 0.0]
+                } else {
+                    vec![0.0, 1.0]
+                };
+                create_embedded_frame(i, i as f64 / 30.0, embedding)
+            })
+            .collect();
+
+        let (segments, trace) = SemanticSegmenter::new(DetailLevel::All)
+            .with_anchor_trace(true)
+            .segment_with_trace::<fn(usize, usize)>(&frames, None);
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(trace.len(), frames.len());
+        let boundaries: Vec<_> = trace.iter().filter(|s| s.boundary).collect();
+        assert_eq!(boundaries.len(), 1);
+        assert_eq!(boundaries[0].frame_index, 30);
+        assert!(boundaries[0].similarity.abs() < 1e-6);
+        assert!(trace.iter().all(|s| (s.anchor_norm - 1.0).abs() < 1e-6));
+
+        let (_, untraced) = SemanticSegmenter::new(DetailLevel::All)
+            .segment_with_trace::<fn(usize, usize)>(&frames, None);
+        assert!(untraced.is_empty());
+    }
+
+    #[test]
+    fn test_every_nth_segment() {
+        let frames: Vec<_> = (0..10)
+            .map(|i| create_mock_frame(i * 30, i as f64))
+            .collect();
+        let segments = uniform_segments(frames, 10.0);
+
+        let kept = every_nth_segment(segments.clone(), 4);
+        let starts: Vec<_> = kept.iter().map(|s| s.start_frame_idx).collect();
+        let indices: Vec<_> = kept.iter().map(|s| s.index).collect();
+        assert_eq!(starts, vec![0, 120, 240]);
+        assert_eq!(indices, vec![0, 1, 2]);
+
+        assert_eq!(every_nth_segment(segments, 1).len(), 10);
+    }
+
+    #[test]
+    fn test_limit_segments_distinct() {
+        let segmenter = SemanticSegmenter::new(DetailLevel::All);
+        let segments: Vec<_> = [
+            vec![1.0, 0.0, 0.0],
+            vec![1.0, 0.1, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![1.0, 0.0, 0.1],
+            vec![0.0, 0.0, 1.0],
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, embedding)| {
+            let frames = [create_embedded_frame(i * 30, i as f64, embedding)];
+            segmenter.create_segment(i, &frames, None)
+        })
+        .collect();
+
+        let kept = limit_segments(segments.clone(), 3, MaxFramesStrategy::Distinct);
+        let starts: Vec<_> = kept.iter().map(|s| s.start_frame_idx).collect();
+        assert_eq!(starts, vec![0, 60, 120]);
+        assert_eq!(
+            kept.iter().map(|s| s.index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        assert_eq!(
+            limit_segments(segments, 10, MaxFramesStrategy::Distinct).len(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_limit_segments_coverage() {
+        let segments_at = |times: &[f64]| {
+            let frames = times
+                .iter()
+                .enumerate()
+                .map(|(i, &t)| create_mock_frame(i * 30, t))
+                .collect();
+            uniform_segments(frames, 10.0)
+        };
+        let times = |segments: Vec<SemanticSegment>| {
+            segments
+                .iter()
+                .map(|s| s.representative_frame.timestamp_seconds().as_seconds())
+                .collect::<Vec<_>>()
+        };
+
+        let even = segments_at(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        let kept = limit_segments(even, 5, MaxFramesStrategy::Coverage);
+        assert_eq!(times(kept), vec![1.0, 3.0, 5.0, 7.0, 9.0]);
+
+        // A cluster early on doesn't take both picks from the sparse end
+        let clustered = segments_at(&[0.0, 0.5, 1.0, 1.5, 9.0]);
+        let kept = limit_segments(clustered, 2, MaxFramesStrategy::Coverage);
+        assert_eq!(times(kept), vec![1.5, 9.0]);
+
+        // Slices cover the analyzed span, not the whole video: a run trimmed
+        // to 60-70s of a longer video is still paced evenly
+        let frames = (0..10)
+            .map(|i| create_mock_frame(1800 + i * 30, 60.0 + i as f64))
+            .collect();
+        let trimmed = uniform_segments(frames, 70.0);
+        let kept = limit_segments(trimmed, 5, MaxFramesStrategy::Coverage);
+        assert_eq!(times(kept), vec![61.0, 63.0, 65.0, 67.0, 69.0]);
+    }
+
+    #[test]
+    fn test_space_out_segments() {
+        let frames: Vec<_> = [0.0, 1.0, 2.5, 3.0, 6.0, 7.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| create_mock_frame(i * 30, t))
+            .collect();
+        let segments = uniform_segments(frames, 8.0);
+
+        // Spacing is measured from the last kept still, not the last seen
+        let kept = space_out_segments(segments.clone(), 2.5);
+        let times: Vec<_> = kept
+            .iter()
+            .map(|s| s.representative_frame.timestamp_seconds().as_seconds())
+            .collect();
+        assert_eq!(times, vec![0.0, 2.5, 6.0]);
+        assert_eq!(
+            kept.iter().map(|s| s.index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        assert_eq!(space_out_segments(segments, 0.0).len(), 6);
+    }
+
+    #[test]
+    fn test_change_share() {
+        let frames: Vec<_> = (0..5)
+            .map(|i| create_embedded_frame(i, i as f64, step_embedding(i, 3)))
+            .collect();
+        assert_eq!(change_share(&frames, 0.85), 0.25);
+        assert_eq!(change_share(&frames[..1], 0.85), 0.0);
+    }
+
+    #[test]
+    fn test_include_endpoints() {
+        let frames: Vec<_> = (0..20)
+            .map(|i| create_embedded_frame(i * 5, i as f64 / 6.0, vec![1.0, 0.0, 0.0]))
+            .collect();
+        let segments =
+            SemanticSegmenter::new(DetailLevel::All).segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].representative_frame.index(), 50);
+
+        let segments = include_endpoints(segments, &frames);
+        let representatives: Vec<_> = segments
+            .iter()
+            .map(|s| s.representative_frame.index())
+            .collect();
+        assert_eq!(representatives, vec![0, 50, 95]);
+        assert_eq!(
+            segments.iter().map(|s| s.index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            (segments[1].start_frame_idx, segments[1].end_frame_idx),
+            (5, 90)
+        );
+        assert_eq!(segments[1].frame_count, 18);
+        assert_eq!(
+            segments[2].start_timestamp_seconds,
+            frames[19].timestamp_seconds()
+        );
+
+        // A single frame is both endpoints and is kept once
+        let single = &frames[..1];
+        let segments =
+            SemanticSegmenter::new(DetailLevel::All).segment::<fn(usize, usize)>(single, None);
+        assert_eq!(include_endpoints(segments, single).len(), 1);
+    }
+
+    /// Embedding of frame `i` in a video with a hard cut at `cut`.
+    fn step_embedding(i: usize, cut: usize) -> Vec<f32> {
+        if i < cut {
+            vec![1.0, 0.0, 0.0]
+        } else {
+            vec![0.0, 1.0, 0.0]
+        }
+    }
+
+    #[test]
+    fn test_bisect_cut_finds_step() {
+        for cut in 1..=30 {
+            let mut embeds = 0;
+            let found = bisect_cut(
+                (0, &step_embedding(0, cut)),
+                (30, &step_embedding(30, cut)),
+                |i| {
+                    embeds += 1;
+                    Ok(Some(step_embedding(i, cut)))
+                },
+            )
+            .unwrap();
+
+            assert_eq!(found, cut);
+            assert!(embeds <= 5);
+        }
+    }
+
+    #[test]
+    fn test_bisect_boundaries_moves_to_exact_cut() {
+        let cut = 237;
+        // Sampled every 10 frames at 30 fps
+        let frames: Vec<_> = (0..40)
+            .map(|i| create_embedded_frame(i * 10, i as f64 / 3.0, step_embedding(i * 10, cut)))
+            .collect();
+
+        let mut segments =
+            SemanticSegmenter::new(DetailLevel::All).segment::<fn(usize, usize)>(&frames, None);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].start_frame_idx, 240);
+
+        bisect_boundaries(&mut segments, &frames, 30.0, |i| {
+            Ok(Some(step_embedding(i, cut)))
+        })
+        .unwrap();
+
+        assert_eq!(segments[0].end_frame_idx, cut - 1);
+        assert_eq!(segments[1].start_frame_idx, cut);
+        assert!(
+            (segments[1].start_timestamp_seconds.as_seconds() - cut as f64 / 30.0).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_bisect_cut_stops_at_bad_frame() {
+        let cut = 23;
+        // The first midpoint (15) narrows the range to 15..30; the next (22)
+        // can't be embedded, so the cut stays at the end of that range
+        let found = bisect_cut(
+            (0, &step_embedding(0, cut)),
+            (30, &step_embedding(30, cut)),
+            |i| Ok((i != 22).then(|| step_embedding(i, cut))),
+        )
+        .unwrap();
+
+        assert_eq!(found, 30);
+    }
 }
@@ -0,0 +1,132 @@
+//! Segment timelines for web timeline widgets.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::output::FrameMetadata;
+use crate::segmentation::SemanticSegment;
+
+/// One segment as a timeline widget expects it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineEntry {
+    /// Seconds from the start of the video.
+    pub start: f64,
+    /// Start of the next segment, or the end of the analyzed part of the
+    /// video for the last one.
+    pub end: f64,
+    /// Filename of the segment's still, relative to the output directory.
+    pub thumb: String,
+    pub label: String,
+}
+
+/// Build a timeline entry for each segment with a written still.
+///
+/// `frames` is the written metadata; a segment's `thumb` is its
+/// representative's still. The last entry ends at `end_seconds`, where the
+/// analyzed part of the video ends.
+pub fn timeline_entries(
+    segments: &[SemanticSegment],
+    frames: &[FrameMetadata],
+    end_seconds: f64,
+) -> Vec<TimelineEntry> {
+    segments
+        .iter()
+        .enumerate()
+        .filter_map(|(i, segment)| {
+            let still = frames.iter().find(|f| {
+                f.segment_index == segment.index
+                    && f.frame_index == segment.representative_frame.index()
+            })?;
+            let start = segment.start_timestamp_seconds.as_seconds();
+            let end = segments
+                .get(i + 1)
+                .map(|next| next.start_timestamp_seconds.as_seconds())
+                .unwrap_or(end_seconds)
+                .max(start);
+            Some(TimelineEntry {
+                start,
+                end,
+                thumb: still.filename.clone(),
+                label: segment.index.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Write `entries` to `path` as a JSON array.
+pub fn write_timeline(path: &Path, entries: &[TimelineEntry]) -> Result<()> {
+    let file = File::create(path)
+        .map_err(|e| Error::Output(format!("Failed to create '{}': {}", path.display(), e)))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, entries)
+        .map_err(|e| Error::Output(format!("Failed to write timeline: {}", e)))?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segmentation::uniform_segments;
+    use crate::timestamp::Timestamp;
+    use crate::video::Frame;
+
+    #[test]
+    fn test_timeline_entries() {
+        let frames = [0, 100, 200]
+            .into_iter()
+            .map(|index| Frame {
+                index,
+                timestamp_seconds: Timestamp::from_frame(index, 25.0),
+                data: Vec::new(),
+                width: 0,
+                height: 0,
+                data16: None,
+            })
+            .collect();
+        let segments = uniform_segments(frames, 10.0);
+        // The middle segment's still was not written
+        let written: Vec<FrameMetadata> = [(0, 0), (2, 200)]
+            .into_iter()
+            .map(|(segment_index, frame_index)| FrameMetadata {
+                filename: format!("{:04}.jpg", segment_index + 1),
+                segment_index,
+                frame_index,
+                timestamp_seconds: Timestamp::from_frame(frame_index, 25.0),
+                timestamp_formatted: String::new(),
+                start_frame_timestamp: Timestamp::ZERO,
+                end_frame_timestamp: Timestamp::ZERO,
+                duration_seconds: 0.0,
+                sharpness: 0.0,
+                exposure: 0.0,
+                relocation: None,
+                boundary: None,
+                clip: None,
+                pixel_sha256: None,
+                palette: None,
+                page: None,
+            })
+            .collect();
+
+        let entries = timeline_entries(&segments, &written, 10.0);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0],
+            TimelineEntry {
+                start: 0.0,
+                end: 4.0,
+                thumb: "0001.jpg".to_string(),
+                label: "0".to_string(),
+            }
+        );
+        assert_eq!((entries[1].start, entries[1].end), (8.0, 10.0));
+        assert_eq!(entries[1].label, "2");
+
+        let json = serde_json::to_value(&entries).unwrap();
+        assert_eq!(json[1]["thumb"], "0003.jpg");
+    }
+}
@@ -0,0 +1,111 @@
+//! Strongly-typed media timestamps.
+
+use std::fmt;
+use std::ops::Sub;
+
+use serde::{Deserialize, Serialize};
+
+/// A position on the video timeline, stored as seconds.
+///
+/// Serializes as a plain float so JSON output keeps its shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timestamp(f64);
+
+impl Timestamp {
+    /// The start of the video.
+    pub const ZERO: Self = Self(0.0);
+
+    /// Timestamp at `seconds` into the video.
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self(seconds)
+    }
+
+    /// Timestamp of frame `index` at `fps`, or zero when the frame rate is
+    /// unknown.
+    pub fn from_frame(index: usize, fps: f64) -> Self {
+        if fps > 0.0 {
+            Self(index as f64 / fps)
+        } else {
+            Self::ZERO
+        }
+    }
+
+    /// Seconds into the video.
+    pub fn as_seconds(self) -> f64 {
+        self.0
+    }
+
+    /// Whole milliseconds into the video.
+    #[allow(dead_code)]
+    pub fn as_ms(self) -> u64 {
+        (self.0 * 1000.0) as u64
+    }
+
+    /// Nearest frame index at `fps`.
+    pub fn as_frames(self, fps: f64) -> usize {
+        (self.0 * fps).round().max(0.0) as usize
+    }
+
+    /// The later of two timestamps.
+    pub fn max(self, other: Self) -> Self {
+        if other > self {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Elapsed seconds between two timestamps.
+impl Sub for Timestamp {
+    type Output = f64;
+
+    fn sub(self, earlier: Self) -> f64 {
+        self.0 - earlier.0
+    }
+}
+
+/// Formats as `HH:MM:SS.mmm`.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.0;
+        let hours = (seconds / 3600.0) as u32;
+        let minutes = ((seconds % 3600.0) / 60.0) as u32;
+        let secs = seconds % 60.0;
+        write!(f, "{:02}:{:02}:{:06.3}", hours, minutes, secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Timestamp::from_seconds(0.0).to_string(), "00:00:00.000");
+        assert_eq!(Timestamp::from_seconds(61.5).to_string(), "00:01:01.500");
+        assert_eq!(
+            Timestamp::from_seconds(3661.123).to_string(),
+            "01:01:01.123"
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let t = Timestamp::from_frame(45, 30.0);
+        assert_eq!(t.as_seconds(), 1.5);
+        assert_eq!(t.as_ms(), 1500);
+        assert_eq!(t.as_frames(30.0), 45);
+        assert_eq!(t.as_frames(24.0), 36);
+        assert_eq!(Timestamp::from_frame(45, 0.0), Timestamp::ZERO);
+        assert_eq!(Timestamp::from_seconds(4.0) - t, 2.5);
+    }
+
+    #[test]
+    fn test_serializes_as_float() {
+        let t = Timestamp::from_seconds(1.25);
+        assert_eq!(serde_json::to_string(&t).unwrap(), "1.25");
+        assert_eq!(serde_json::from_str::<Timestamp>("1.25").unwrap(), t);
+    }
+}
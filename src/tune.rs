@@ -0,0 +1,128 @@
+//! Interactive similarity-threshold tuning over precomputed embeddings.
+
+use std::io::{BufRead, Write};
+
+use crate::config::DetailLevel;
+use crate::embeddings::EmbeddedFrame;
+use crate::error::Result;
+use crate::segmentation::SemanticSegmenter;
+
+const PROMPT: &str = "threshold> ";
+
+/// Read thresholds from `input` and print the resulting segmentation.
+///
+/// Embeddings don't depend on the threshold, so each line only reruns the
+/// cheap segmentation pass. Stops at `quit`, `q` or end of input.
+pub fn tune_loop<R: BufRead, W: Write>(
+    frames: &[EmbeddedFrame],
+    detail: DetailLevel,
+    input: R,
+    mut output: W,
+) -> Result<()> {
+    writeln!(
+        output,
+        "Embedded {} frames. Presets: key={}, summary={}, all={}",
+        frames.len(),
+        DetailLevel::Key.similarity_threshold(),
+        DetailLevel::Summary.similarity_threshold(),
+        DetailLevel::All.similarity_threshold(),
+    )?;
+    writeln!(output, "Enter a threshold (0.0-1.0), or 'quit'.")?;
+    write!(output, "{}", PROMPT)?;
+    output.flush()?;
+
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if matches!(line, "q" | "quit" | "exit") {
+            break;
+        }
+
+        if !line.is_empty() {
+            match parse_threshold(line) {
+                Ok(threshold) => {
+                    let segments = SemanticSegmenter::new(detail)
+                        .with_similarity_threshold(threshold)
+                        .segment::<fn(usize, usize)>(frames, None);
+                    let starts: Vec<String> = segments
+                        .iter()
+                        .map(|s| s.start_timestamp_seconds.to_string())
+                        .collect();
+                    writeln!(
+                        output,
+                        "{} segments at threshold {}",
+                        segments.len(),
+                        threshold
+                    )?;
+                    writeln!(output, "  {}", starts.join(" "))?;
+                }
+                Err(reason) => writeln!(output, "{}", reason)?,
+            }
+        }
+
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+    }
+
+    writeln!(output)?;
+    Ok(())
+}
+
+/// Parse a similarity threshold in [0.0, 1.0].
+fn parse_threshold(s: &str) -> std::result::Result<f32, String> {
+    let value: f32 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("{} is not in range 0.0-1.0", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Timestamp;
+    use crate::video::Frame;
+
+    fn frames() -> Vec<EmbeddedFrame> {
+        // Two scenes whose embeddings are ~0.8 similar, split at frame 100
+        (0..200)
+            .map(|i| EmbeddedFrame {
+                frame: Frame {
+                    index: i,
+                    timestamp_seconds: Timestamp::from_seconds(i as f64 / 10.0),
+                    data: Vec::new(),
+                    width: 0,
+                    height: 0,
+                    data16: None,
+                },
+                embedding: if i < 100 {
+                    vec![1.0, 0.0]
+                } else {
+                    vec![0.8, 0.6]
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tune_loop_resegments_per_threshold() {
+        let input = "0.7\n0.9\nabc\nquit\n0.5\n";
+        let mut output = Vec::new();
+        tune_loop(&frames(), DetailLevel::All, input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("1 segments at threshold 0.7"));
+        assert!(output.contains("2 segments at threshold 0.9"));
+        assert!(output.contains("00:00:10.000"));
+        assert!(output.contains("'abc' is not a number"));
+        assert!(!output.contains("threshold 0.5"));
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(parse_threshold("0.85"), Ok(0.85));
+        assert!(parse_threshold("1.5").is_err());
+        assert!(parse_threshold("x").is_err());
+    }
+}
@@ -1,14 +1,23 @@
 //! Video loading and frame extraction module.
 
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use opencv::core::Mat;
-use opencv::imgproc;
+use image::DynamicImage;
+use opencv::core::{self, Mat, Vector};
 use opencv::prelude::*;
-use opencv::videoio::{self, VideoCapture, VideoCaptureTraitConst};
+use opencv::videoio::{self, VideoCapture, VideoCaptureTraitConst, VideoWriter};
+use opencv::{imgcodecs, imgproc};
 
-use crate::config::QualityPreset;
+use crate::config::{
+    JpegSubsampling, COMBING_LUMA_DELTA, EXPOSURE_BLACK_LEVEL, EXPOSURE_WHITE_LEVEL,
+    PALETTE_ITERATIONS, PALETTE_SAMPLE_PIXELS, PREVIEW_WINDOW_SECONDS, SSIM_BLOCK_SIZE,
+    SSIM_COMPARE_WIDTH,
+};
 use crate::error::{Error, Result, SUPPORTED_FORMATS};
+use crate::profile;
+use crate::timestamp::Timestamp;
 
 /// Metadata extracted from a video file.
 #[derive(Debug, Clone)]
@@ -21,23 +30,265 @@ pub struct VideoMetadata {
     pub frame_count: u32,
     pub duration_seconds: f64,
     pub codec: String,
+    /// Frames a sequential read actually decoded, set when it ended before
+    /// `frame_count` (a corrupt or truncated tail).
+    pub decoded_frame_count: Option<u32>,
 }
 
 /// A single video frame with metadata.
 #[derive(Debug, Clone)]
 pub struct Frame {
     pub index: usize,
-    pub timestamp_seconds: f64,
+    pub timestamp_seconds: Timestamp,
     pub data: Vec<u8>,
     pub width: u32,
     pub height: u32,
+    /// Full-precision RGB samples, kept for 16-bit sources when depth
+    /// preservation is on. `data` always holds the 8-bit version.
+    pub data16: Option<Vec<u16>>,
+}
+
+/// Image quality metrics for a single frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameQuality {
+    /// Variance of the Laplacian of the grayscale frame; higher is sharper.
+    pub sharpness: f64,
+    /// Fraction of pixels not clipped to black or white (1.0 = none clipped).
+    pub exposure: f64,
 }
 
 impl Frame {
-    /// Timestamp in milliseconds.
+    /// Decode a still image file into a frame using the `image` crate.
+    ///
+    /// Supports every format the crate is built with (PNG, JPEG, WebP, BMP,
+    /// TIFF, ...) independently of OpenCV's codecs. Grayscale and alpha
+    /// images are converted to packed RGB.
     #[allow(dead_code)]
-    pub fn timestamp_ms(&self) -> u64 {
-        (self.timestamp_seconds * 1000.0) as u64
+    pub fn from_image_file(
+        path: &Path,
+        index: usize,
+        timestamp_seconds: Timestamp,
+    ) -> Result<Self> {
+        let img = image::open(path).map_err(|e| Error::VideoDecode {
+            path: path.to_path_buf(),
+            reason: format!("Failed to decode image: {}", e),
+        })?;
+        Ok(Frame::from_image(img, index, timestamp_seconds))
+    }
+
+    /// Convert an already decoded image into a frame, as packed 8-bit RGB.
+    pub fn from_image(img: DynamicImage, index: usize, timestamp_seconds: Timestamp) -> Self {
+        let img = img.into_rgb8();
+        Frame {
+            index,
+            timestamp_seconds,
+            width: img.width(),
+            height: img.height(),
+            data: img.into_raw(),
+            data16: None,
+        }
+    }
+
+    /// Encode the frame as a JPEG at `quality` (0-100) with the given
+    /// chroma subsampling.
+    pub fn encode_jpeg(&self, quality: i32, subsampling: JpegSubsampling) -> Result<Vec<u8>> {
+        let flat = Mat::from_slice(&self.data)?;
+        let rgb = flat.reshape(3, self.height as i32)?;
+        let mut bgr = Mat::default();
+        imgproc::cvt_color_def(&rgb, &mut bgr, imgproc::COLOR_RGB2BGR)?;
+
+        let sampling_factor = match subsampling {
+            JpegSubsampling::Chroma444 => imgcodecs::IMWRITE_JPEG_SAMPLING_FACTOR_444,
+            JpegSubsampling::Chroma422 => imgcodecs::IMWRITE_JPEG_SAMPLING_FACTOR_422,
+            JpegSubsampling::Chroma420 => imgcodecs::IMWRITE_JPEG_SAMPLING_FACTOR_420,
+        };
+        let params = Vector::from_slice(&[
+            imgcodecs::IMWRITE_JPEG_QUALITY,
+            quality,
+            imgcodecs::IMWRITE_JPEG_SAMPLING_FACTOR,
+            sampling_factor,
+        ]);
+        let mut bytes = Vector::<u8>::new();
+        if !imgcodecs::imencode(".jpg", &bgr, &mut bytes, &params)? {
+            return Err(Error::Output(format!(
+                "OpenCV could not encode frame {} as JPEG",
+                self.index
+            )));
+        }
+        Ok(bytes.to_vec())
+    }
+
+    /// Measure blur and exposure of the frame.
+    pub fn quality(&self) -> Result<FrameQuality> {
+        let flat = Mat::from_slice(&self.data)?;
+        let rgb = flat.reshape(3, self.height as i32)?;
+
+        let mut gray = Mat::default();
+        imgproc::cvt_color_def(&rgb, &mut gray, imgproc::COLOR_RGB2GRAY)?;
+
+        let mut laplacian = Mat::default();
+        imgproc::laplacian_def(&gray, &mut laplacian, core::CV_64F)?;
+
+        let mut mean = Vector::<f64>::new();
+        let mut stddev = Vector::<f64>::new();
+        core::mean_std_dev_def(&laplacian, &mut mean, &mut stddev)?;
+        let sharpness = stddev.get(0).map(|sd| sd * sd).unwrap_or(0.0);
+
+        Ok(FrameQuality {
+            sharpness,
+            exposure: exposure_score(gray.data_bytes()?),
+        })
+    }
+
+    /// Structural similarity (SSIM) of this frame's luma to `other`'s, from
+    /// about 0 for unrelated images to 1 for identical ones.
+    ///
+    /// Both frames are scaled to at most [`SSIM_COMPARE_WIDTH`] pixels wide
+    /// and compared block by block; the result is the mean over blocks.
+    pub fn ssim(&self, other: &Frame) -> Result<f64> {
+        let width = self.width.clamp(1, SSIM_COMPARE_WIDTH);
+        let height = ((self.height as u64 * width as u64 / self.width.max(1) as u64) as u32).max(1);
+        let a = self.luma(width, height)?;
+        let b = other.luma(width, height)?;
+        Ok(ssim_blocks(&a, &b))
+    }
+
+    /// Share of pixels showing interlacing combs; see [`combing_share`].
+    pub fn combing(&self) -> Result<f64> {
+        Ok(combing_share(&self.luma(self.width, self.height)?))
+    }
+
+    /// Grayscale copy of the frame resized to `width`×`height`.
+    fn luma(&self, width: u32, height: u32) -> Result<image::GrayImage> {
+        let rgb = image::RgbImage::from_raw(self.width, self.height, self.data.clone())
+            .ok_or_else(|| {
+                Error::Output(format!(
+                    "Failed to create image buffer for frame {}",
+                    self.index
+                ))
+            })?;
+        let gray = image::imageops::grayscale(&rgb);
+        if gray.dimensions() == (width, height) {
+            return Ok(gray);
+        }
+        Ok(image::imageops::resize(
+            &gray,
+            width,
+            height,
+            image::imageops::FilterType::Triangle,
+        ))
+    }
+}
+
+/// A rectangle of a frame, in pixels: the area kept by `--crop` or one
+/// blanked by `--mask`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// Fail unless the region lies within a `width`×`height` frame.
+    ///
+    /// `name` says what the region is for in the error message.
+    pub fn check_fits(&self, name: &str, width: u32, height: u32) -> Result<()> {
+        let right = self.x as u64 + self.width as u64;
+        let bottom = self.y as u64 + self.height as u64;
+        if right > width as u64 || bottom > height as u64 {
+            return Err(Error::Config(format!(
+                "{} {} extends past the {}x{} frame",
+                name, self, width, height
+            )));
+        }
+        Ok(())
+    }
+
+    /// The region of a `to`-sized frame covering what this region covers on
+    /// a `from`-sized one, such as the same video decoded smaller. Edges are
+    /// rounded outward so nothing the region covered is left out.
+    pub fn rescale(&self, from: (u32, u32), to: (u32, u32)) -> Region {
+        let scale = |value: u32, from: u32, to: u32| value as u64 * to as u64 / from.max(1) as u64;
+        let scale_up = |value: u32, from: u32, to: u32| {
+            (value as u64 * to as u64).div_ceil(from.max(1) as u64)
+        };
+        let x = scale(self.x, from.0, to.0);
+        let y = scale(self.y, from.1, to.1);
+        let right = scale_up(self.x + self.width, from.0, to.0);
+        let bottom = scale_up(self.y + self.height, from.1, to.1);
+        Region {
+            x: x as u32,
+            y: y as u32,
+            width: (right - x).max(1) as u32,
+            height: (bottom - y).max(1) as u32,
+        }
+    }
+
+    fn rect(&self) -> core::Rect {
+        core::Rect::new(
+            self.x as i32,
+            self.y as i32,
+            self.width as i32,
+            self.height as i32,
+        )
+    }
+
+    /// Zero the region's pixels in packed RGB `data` of the given width.
+    ///
+    /// Parts of the region outside the image are ignored.
+    pub fn blank(&self, data: &mut [u8], width: u32, height: u32) {
+        let right = self.x.saturating_add(self.width).min(width) as usize;
+        let bottom = self.y.saturating_add(self.height).min(height) as usize;
+        let stride = width as usize * 3;
+        for y in self.y as usize..bottom {
+            let row = y * stride;
+            if let Some(pixels) = data.get_mut(row + self.x as usize * 3..row + right * 3) {
+                pixels.fill(0);
+            }
+        }
+    }
+}
+
+/// Parses `X,Y,W,H`.
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| format!("'{}' is not X,Y,W,H in whole pixels", s))?;
+        let [x, y, width, height] = values[..] else {
+            return Err(format!("'{}' is not X,Y,W,H in whole pixels", s));
+        };
+        if width == 0 || height == 0 {
+            return Err(format!("region '{}' has zero width or height", s));
+        }
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// Serializes as `X,Y,W,H`, the form it's parsed from.
+impl serde::Serialize for Region {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
     }
 }
 
@@ -45,6 +296,12 @@ impl Frame {
 pub struct VideoLoader {
     path: PathBuf,
     metadata: Option<VideoMetadata>,
+    preserve_depth: bool,
+    crop: Option<Region>,
+    deinterlace: bool,
+    decode_scale: Option<u32>,
+    trim: (f64, f64),
+    sample_jitter: Option<u64>,
 }
 
 impl VideoLoader {
@@ -60,9 +317,96 @@ impl VideoLoader {
         Ok(Self {
             path,
             metadata: None,
+            preserve_depth: false,
+            crop: None,
+            deinterlace: false,
+            decode_scale: None,
+            trim: (0.0, 0.0),
+            sample_jitter: None,
         })
     }
 
+    /// Keep full 16-bit samples in `Frame::data16` when the decoder
+    /// delivers 16-bit frames. 8-bit sources are unaffected.
+    pub fn with_preserve_depth(mut self, preserve: bool) -> Self {
+        self.preserve_depth = preserve;
+        self
+    }
+
+    /// Keep only this region of every decoded frame.
+    pub fn with_crop(mut self, crop: Option<Region>) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    /// Blend the two fields of every decoded frame, removing the combs
+    /// interlaced video shows wherever there's motion.
+    pub fn with_deinterlace(mut self, enabled: bool) -> Self {
+        self.deinterlace = enabled;
+        self
+    }
+
+    /// Sample a random frame of each stride instead of its first, drawn
+    /// from `seed` so the same seed picks the same frames.
+    pub fn with_sample_jitter(mut self, seed: Option<u64>) -> Self {
+        self.sample_jitter = seed;
+        self
+    }
+
+    /// Shrink decoded frames so their long edge is at most this many pixels.
+    ///
+    /// Scaled frames carry no `data16`; re-read representatives with a
+    /// loader without a scale to get full-resolution pixels.
+    pub fn with_decode_scale(mut self, long_edge: Option<u32>) -> Self {
+        self.decode_scale = long_edge;
+        self
+    }
+
+    /// Leave this many seconds at the start and end of the video out of
+    /// sampling. `get_frame_at` still reads any frame.
+    pub fn with_trim(mut self, start_seconds: f64, end_seconds: f64) -> Self {
+        self.trim = (start_seconds, end_seconds);
+        self
+    }
+
+    /// Frames that sampling visits, after trimming and excluding any tail
+    /// found not to decode.
+    pub fn frame_range(&mut self) -> Result<Range<usize>> {
+        let (start_seconds, end_seconds) = self.trim;
+        let metadata = self.metadata()?;
+        let range = trimmed_range(
+            metadata.frame_count as usize,
+            metadata.fps,
+            start_seconds,
+            end_seconds,
+        );
+        let end = metadata
+            .decoded_frame_count
+            .map_or(range.end, |n| range.end.min(n as usize));
+        Ok(range.start..end.max(range.start))
+    }
+
+    /// Record that the stream ended after `decoded` frames, short of the
+    /// `expected` ones, and finish the progress count there.
+    fn stream_ended_early<F>(
+        &mut self,
+        decoded: usize,
+        expected: usize,
+        progress_callback: &mut Option<F>,
+    ) where
+        F: FnMut(usize, usize),
+    {
+        if decoded >= expected {
+            return;
+        }
+        if let Some(metadata) = self.metadata.as_mut() {
+            metadata.decoded_frame_count = Some(decoded as u32);
+        }
+        if let Some(ref mut cb) = progress_callback {
+            cb(decoded, decoded);
+        }
+    }
+
     fn validate_file(path: &Path) -> Result<()> {
         if !path.exists() {
             return Err(Error::VideoNotFound(path.to_path_buf()));
@@ -89,7 +433,7 @@ impl VideoLoader {
     }
 
     fn open_capture(&self) -> Result<VideoCapture> {
-        let cap = VideoCapture::from_file(self.path.to_str().unwrap(), videoio::CAP_ANY)?;
+        let mut cap = VideoCapture::from_file(self.path.to_str().unwrap(), videoio::CAP_ANY)?;
 
         if !cap.is_opened()? {
             return Err(Error::VideoDecode {
@@ -97,6 +441,10 @@ impl VideoLoader {
                 reason: "Failed to open video file".to_string(),
             });
         }
+        if self.preserve_depth {
+            // Otherwise the backend converts every frame to 8-bit BGR
+            cap.set(videoio::CAP_PROP_CONVERT_RGB, 0.0)?;
+        }
 
         Ok(cap)
     }
@@ -133,64 +481,135 @@ impl VideoLoader {
                 frame_count,
                 duration_seconds: duration,
                 codec,
+                decoded_frame_count: None,
             });
         }
 
         Ok(self.metadata.as_ref().unwrap())
     }
 
-    /// Extract frames from the video at the specified sample rate.
+    /// Extract every `sample_rate`th frame from the video.
+    ///
+    /// Sampling starts at frame `sample_offset` instead of frame 0, which
+    /// skips black leaders and slates. With
+    /// [`with_sample_jitter`](Self::with_sample_jitter), the frame taken from
+    /// each stride is random rather than its first.
     pub fn extract_frames<F>(
         &mut self,
-        quality: QualityPreset,
-        mut progress_callback: Option<F>,
+        sample_rate: usize,
+        sample_offset: usize,
+        progress_callback: Option<F>,
     ) -> Result<Vec<Frame>>
     where
         F: FnMut(usize, usize),
     {
-        let metadata = self.metadata()?.clone();
+        let mut frames = Vec::new();
+        self.for_each_frame(sample_rate, sample_offset, progress_callback, |frame| {
+            frames.push(frame);
+            Ok(())
+        })?;
+        Ok(frames)
+    }
+
+    /// Decode frames at the sample rate, handing each to `on_frame` as soon
+    /// as it's read.
+    ///
+    /// Unlike `extract_frames`, only one decoded frame is held at a time.
+    /// Stops at the first error returned by `on_frame`.
+    pub fn for_each_frame<F, G>(
+        &mut self,
+        sample_rate: usize,
+        sample_offset: usize,
+        mut progress_callback: Option<F>,
+        mut on_frame: G,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, usize),
+        G: FnMut(Frame) -> Result<()>,
+    {
+        let fps = self.metadata()?.fps;
+        let range = self.frame_range()?;
+        let sample_offset = sample_offset.max(range.start);
         let mut cap = self.open_capture()?;
 
-        let sample_rate = quality.frame_sample_rate();
-        let total_frames = metadata.frame_count as usize;
-        let fps = metadata.fps;
+        let total_frames = range.end;
 
-        let mut frames = Vec::new();
         let mut frame_mat = Mat::default();
         let mut frame_index = 0usize;
 
-        loop {
-            let ret = cap.read(&mut frame_mat)?;
+        while frame_index < range.end {
+            let ret = {
+                let _span = profile::span("decode");
+                cap.read(&mut frame_mat)?
+            };
             if !ret || frame_mat.empty() {
                 break;
             }
 
             // Only process frames at the sample rate
-            if frame_index.is_multiple_of(sample_rate) {
-                let timestamp = if fps > 0.0 {
-                    frame_index as f64 / fps
-                } else {
-                    0.0
-                };
+            if is_sampled(
+                frame_index,
+                sample_offset..range.end,
+                sample_rate,
+                self.sample_jitter,
+            ) {
+                let timestamp = Timestamp::from_frame(frame_index, fps);
+                on_frame(self.mat_to_frame(&frame_mat, frame_index, timestamp)?)?;
+            }
+
+            frame_index += 1;
+
+            if let Some(ref mut cb) = progress_callback {
+                cb(frame_index, total_frames);
+            }
+        }
+        self.stream_ended_early(frame_index, total_frames, &mut progress_callback);
 
-                // Convert BGR to RGB
-                let mut rgb_mat = Mat::default();
-                imgproc::cvt_color_def(&frame_mat, &mut rgb_mat, imgproc::COLOR_BGR2RGB)?;
+        Ok(())
+    }
 
-                // Get frame dimensions
-                let width = rgb_mat.cols() as u32;
-                let height = rgb_mat.rows() as u32;
+    /// Extract only the frames the container marks as keyframes (I-frames).
+    ///
+    /// Every frame is still demuxed and decoded, but only keyframes are
+    /// converted and returned, so far fewer frames reach embedding.
+    /// Timestamps are the decoder's position for each keyframe (its PTS)
+    /// rather than `index / fps`.
+    ///
+    /// Returns `None` when the backend doesn't report keyframe flags,
+    /// detected by the first frame (always a keyframe) not being flagged.
+    pub fn extract_keyframes<F>(
+        &mut self,
+        mut progress_callback: Option<F>,
+    ) -> Result<Option<Vec<Frame>>>
+    where
+        F: FnMut(usize, usize),
+    {
+        let range = self.frame_range()?;
+        let total_frames = range.end;
+        let mut cap = self.open_capture()?;
 
-                // Convert to Vec<u8>
-                let data = mat_to_vec(&rgb_mat)?;
+        let mut frames = Vec::new();
+        let mut frame_mat = Mat::default();
+        let mut frame_index = 0usize;
 
-                frames.push(Frame {
-                    index: frame_index,
-                    timestamp_seconds: timestamp,
-                    data,
-                    width,
-                    height,
-                });
+        while frame_index < range.end && cap.grab()? {
+            if cap.get(videoio::CAP_PROP_LRF_HAS_KEY_FRAME)? > 0.0 {
+                if frame_index < range.start {
+                    frame_index += 1;
+                    continue;
+                }
+                let timestamp =
+                    Timestamp::from_seconds(cap.get(videoio::CAP_PROP_POS_MSEC)? / 1000.0);
+                let retrieved = {
+                    let _span = profile::span("decode");
+                    cap.retrieve_def(&mut frame_mat)?
+                };
+                if !retrieved || frame_mat.empty() {
+                    break;
+                }
+                frames.push(self.mat_to_frame(&frame_mat, frame_index, timestamp)?);
+            } else if frame_index == 0 {
+                return Ok(None);
             }
 
             frame_index += 1;
@@ -199,12 +618,96 @@ impl VideoLoader {
                 cb(frame_index, total_frames);
             }
         }
+        self.stream_ended_early(frame_index, total_frames, &mut progress_callback);
+
+        Ok(Some(frames))
+    }
+
+    /// Extract one frame every `interval_seconds` on a fixed time grid.
+    ///
+    /// The grid starts at frame `sample_offset`.
+    pub fn extract_frames_uniform<F>(
+        &mut self,
+        interval_seconds: f64,
+        sample_offset: usize,
+        mut progress_callback: Option<F>,
+    ) -> Result<Vec<Frame>>
+    where
+        F: FnMut(usize, usize),
+    {
+        let fps = self.metadata()?.fps;
+        let range = self.frame_range()?;
+        let sample_offset = sample_offset.max(range.start);
+        let indices: Vec<usize> = uniform_grid_indices(
+            range.end.saturating_sub(sample_offset),
+            fps,
+            interval_seconds,
+        )
+        .into_iter()
+        .map(|index| index + sample_offset)
+        .collect();
+
+        let mut frames = Vec::with_capacity(indices.len());
+        for (i, index) in indices.iter().enumerate() {
+            frames.push(self.get_frame_at(*index)?);
+
+            if let Some(ref mut cb) = progress_callback {
+                cb(i + 1, indices.len());
+            }
+        }
+
+        Ok(frames)
+    }
+
+    /// Extract one frame per `sample_rate` frames of each `(first, last)` window,
+    /// seeking to the start of each instead of decoding the frames between.
+    pub fn extract_windows<F>(
+        &mut self,
+        windows: &[(usize, usize)],
+        sample_rate: usize,
+        mut progress_callback: Option<F>,
+    ) -> Result<Vec<Frame>>
+    where
+        F: FnMut(usize, usize),
+    {
+        let fps = self.metadata()?.fps;
+        let total: usize = windows.iter().map(|(first, last)| last + 1 - first).sum();
+        let mut cap = self.open_capture()?;
+
+        let mut frames = Vec::new();
+        let mut frame_mat = Mat::default();
+        let mut decoded = 0;
+        for &(first, last) in windows {
+            cap.set(videoio::CAP_PROP_POS_FRAMES, first as f64)?;
+            for frame_index in first..=last {
+                let ret = {
+                    let _span = profile::span("decode");
+                    cap.read(&mut frame_mat)?
+                };
+                if !ret || frame_mat.empty() {
+                    break;
+                }
+                if is_sampled(
+                    frame_index,
+                    first..last + 1,
+                    sample_rate,
+                    self.sample_jitter,
+                ) {
+                    let timestamp = Timestamp::from_frame(frame_index, fps);
+                    frames.push(self.mat_to_frame(&frame_mat, frame_index, timestamp)?);
+                }
+
+                decoded += 1;
+                if let Some(ref mut cb) = progress_callback {
+                    cb(decoded, total);
+                }
+            }
+        }
 
         Ok(frames)
     }
 
     /// Get a specific frame by index.
-    #[allow(dead_code)]
     pub fn get_frame_at(&mut self, index: usize) -> Result<Frame> {
         let metadata = self.metadata()?.clone();
 
@@ -222,7 +725,10 @@ impl VideoLoader {
         cap.set(videoio::CAP_PROP_POS_FRAMES, index as f64)?;
 
         let mut frame_mat = Mat::default();
-        let ret = cap.read(&mut frame_mat)?;
+        let ret = {
+            let _span = profile::span("decode");
+            cap.read(&mut frame_mat)?
+        };
 
         if !ret || frame_mat.empty() {
             return Err(Error::VideoDecode {
@@ -231,19 +737,131 @@ impl VideoLoader {
             });
         }
 
-        let timestamp = if metadata.fps > 0.0 {
-            index as f64 / metadata.fps
-        } else {
-            0.0
-        };
+        let timestamp = Timestamp::from_frame(index, metadata.fps);
+
+        self.mat_to_frame(&frame_mat, index, timestamp)
+    }
+
+    /// Re-encode frames `start..=end` to an MPEG-4 clip at `path`, at the
+    /// source frame rate and with the crop applied.
+    pub fn write_clip(&mut self, start: usize, end: usize, path: &Path) -> Result<()> {
+        let _span = profile::span("clip");
+        let fps = self.metadata()?.fps;
+        let mut cap = self.open_capture()?;
+        cap.set(videoio::CAP_PROP_POS_FRAMES, start as f64)?;
+
+        let mut writer: Option<VideoWriter> = None;
+        for _ in start..=end {
+            let mut mat = Mat::default();
+            if !cap.read(&mut mat)? || mat.empty() {
+                break;
+            }
+            if let Some(crop) = self.crop {
+                mat = mat.roi(crop.rect())?.try_clone()?;
+            }
+            if mat.depth() == core::CV_16U {
+                let mut mat8 = Mat::default();
+                mat.convert_to(&mut mat8, core::CV_8U, 1.0 / 257.0, 0.0)?;
+                mat = mat8;
+            }
+
+            let writer = match &mut writer {
+                Some(writer) => writer,
+                None => writer.insert(self.open_writer(path, fps, mat.cols(), mat.rows())?),
+            };
+            writer.write(&mat)?;
+        }
+
+        match writer {
+            Some(mut writer) => Ok(writer.release()?),
+            None => Err(Error::VideoDecode {
+                path: self.path.clone(),
+                reason: format!("Failed to read frames {}-{} for a clip", start, end),
+            }),
+        }
+    }
+
+    fn open_writer(&self, path: &Path, fps: f64, width: i32, height: i32) -> Result<VideoWriter> {
+        let writer = VideoWriter::new(
+            &path.to_string_lossy(),
+            VideoWriter::fourcc('m', 'p', '4', 'v')?,
+            fps,
+            core::Size::new(width, height),
+            true,
+        )?;
+        if !writer.is_opened()? {
+            return Err(Error::Output(format!(
+                "Failed to open clip '{}' for writing",
+                path.display()
+            )));
+        }
+        Ok(writer)
+    }
 
-        // Convert BGR to RGB
+    /// Convert a decoded BGR Mat into an RGB `Frame`.
+    ///
+    /// 16-bit frames are scaled down to 8 bits for `data`; their original
+    /// samples go to `data16` when depth preservation is on. Without the
+    /// backend's RGB conversion, gray and BGRA frames are expanded here.
+    fn mat_to_frame(&self, frame_mat: &Mat, index: usize, timestamp: Timestamp) -> Result<Frame> {
+        let _span = profile::span("convert");
+
+        let code = match frame_mat.channels() {
+            1 => imgproc::COLOR_GRAY2RGB,
+            4 => imgproc::COLOR_BGRA2RGB,
+            _ => imgproc::COLOR_BGR2RGB,
+        };
         let mut rgb_mat = Mat::default();
-        imgproc::cvt_color_def(&frame_mat, &mut rgb_mat, imgproc::COLOR_BGR2RGB)?;
+        imgproc::cvt_color_def(frame_mat, &mut rgb_mat, code)?;
+
+        if self.deinterlace {
+            let _span = profile::span("deinterlace");
+            // Linear blend: each row becomes half itself and a quarter of
+            // each neighbor, so both fields merge at the cost of some
+            // vertical detail
+            let mut blended = Mat::default();
+            imgproc::gaussian_blur_def(&rgb_mat, &mut blended, core::Size::new(1, 3), 0.0)?;
+            rgb_mat = blended;
+        }
+
+        if let Some(crop) = self.crop {
+            crop.check_fits("crop", rgb_mat.cols() as u32, rgb_mat.rows() as u32)?;
+            // Copy so the pixel data is contiguous
+            rgb_mat = rgb_mat.roi(crop.rect())?.try_clone()?;
+        }
+
+        let scaled = self.decode_scale.and_then(|long_edge| {
+            scaled_size(rgb_mat.cols() as u32, rgb_mat.rows() as u32, long_edge)
+        });
+        if let Some((width, height)) = scaled {
+            let _span = profile::span("scale");
+            let mut small = Mat::default();
+            imgproc::resize(
+                &rgb_mat,
+                &mut small,
+                core::Size::new(width as i32, height as i32),
+                0.0,
+                0.0,
+                imgproc::INTER_AREA,
+            )?;
+            rgb_mat = small;
+        }
 
         let width = rgb_mat.cols() as u32;
         let height = rgb_mat.rows() as u32;
+
+        let mut data16 = None;
+        if rgb_mat.depth() == core::CV_16U {
+            if self.preserve_depth && scaled.is_none() {
+                data16 = Some(u16_samples(mat_to_vec(&rgb_mat)?));
+            }
+            let mut rgb8 = Mat::default();
+            rgb_mat.convert_to(&mut rgb8, core::CV_8U, 1.0 / 257.0, 0.0)?;
+            rgb_mat = rgb8;
+        }
+
         let data = mat_to_vec(&rgb_mat)?;
+        validate_rgb_data(&self.path, index, &data, width, height, rgb_mat.channels())?;
 
         Ok(Frame {
             index,
@@ -251,6 +869,7 @@ impl VideoLoader {
             data,
             width,
             height,
+            data16,
         })
     }
 
@@ -261,14 +880,644 @@ impl VideoLoader {
     }
 }
 
+/// Frames of a `frame_count`-frame video left after dropping `start_seconds`
+/// from its start and `end_seconds` from its end.
+pub fn trimmed_range(
+    frame_count: usize,
+    fps: f64,
+    start_seconds: f64,
+    end_seconds: f64,
+) -> Range<usize> {
+    let frames = |seconds: f64| ((seconds * fps).round().max(0.0) as usize).min(frame_count);
+    let start = frames(start_seconds);
+    let end = (frame_count - frames(end_seconds)).max(start);
+    start..end
+}
+
+/// Share of pixels that differ from the rows above and below in the same
+/// direction while those two rows agree: the comb teeth left where the two
+/// fields of an interlaced frame caught motion at different moments.
+///
+/// Progressive frames score near zero; static interlaced scenes do too,
+/// since both fields show the same picture.
+pub fn combing_share(gray: &image::GrayImage) -> f64 {
+    let (width, height) = gray.dimensions();
+    if height < 3 || width == 0 {
+        return 0.0;
+    }
+
+    let luma = |x: u32, y: u32| gray.get_pixel(x, y)[0] as i32;
+    let mut combed = 0u64;
+    for y in 1..height - 1 {
+        for x in 0..width {
+            let (above, pixel, below) = (luma(x, y - 1), luma(x, y), luma(x, y + 1));
+            let teeth = (pixel - above) * (pixel - below) > COMBING_LUMA_DELTA * COMBING_LUMA_DELTA;
+            if teeth && (above - below).abs() < COMBING_LUMA_DELTA {
+                combed += 1;
+            }
+        }
+    }
+    combed as f64 / (width as u64 * (height as u64 - 2)) as f64
+}
+
+/// Size of a `width`×`height` frame shrunk to fit `long_edge`, keeping its
+/// aspect ratio, or `None` when it already fits.
+pub fn scaled_size(width: u32, height: u32, long_edge: u32) -> Option<(u32, u32)> {
+    let longest = width.max(height);
+    if longest <= long_edge || long_edge == 0 {
+        return None;
+    }
+    let scale = |side: u32| {
+        ((side as u64 * long_edge as u64 + longest as u64 / 2) / longest as u64).max(1) as u32
+    };
+    Some((scale(width), scale(height)))
+}
+
+/// Frame indices at `0, interval, 2*interval, ...` seconds within the video.
+fn uniform_grid_indices(frame_count: usize, fps: f64, interval_seconds: f64) -> Vec<usize> {
+    if frame_count == 0 {
+        return Vec::new();
+    }
+    if fps <= 0.0 {
+        return vec![0];
+    }
+
+    let mut indices: Vec<usize> = Vec::new();
+    let mut step = 0usize;
+    loop {
+        let index = (step as f64 * interval_seconds * fps).round() as usize;
+        if index >= frame_count {
+            break;
+        }
+        // Intervals shorter than a frame would repeat the same index
+        if indices.last() != Some(&index) {
+            indices.push(index);
+        }
+        step += 1;
+    }
+    indices
+}
+
+/// First and last frame of a clip `seconds` long centered on `center`,
+/// shifted to stay within `frames`, the video's frames after trimming.
+pub fn clip_frame_range(
+    center: usize,
+    seconds: f64,
+    fps: f64,
+    frames: &Range<usize>,
+) -> (usize, usize) {
+    let first = frames.start;
+    let end = frames.end.max(first + 1);
+    let length = ((seconds * fps).round() as usize).clamp(1, end - first);
+    let start = center
+        .saturating_sub(length / 2)
+        .max(first)
+        .min(end - length);
+    (start, start + length - 1)
+}
+
+/// `(first, last)` frames of evenly spaced windows totalling about `seconds`
+/// of a `frame_count`-frame video, starting at `sample_offset`.
+///
+/// The first window starts at `sample_offset` and the last ends with the
+/// video. Asking for at least the whole video gives one window covering it.
+pub fn preview_windows(
+    frame_count: usize,
+    fps: f64,
+    seconds: f64,
+    sample_offset: usize,
+) -> Vec<(usize, usize)> {
+    let available = frame_count.saturating_sub(sample_offset);
+    if available == 0 {
+        return Vec::new();
+    }
+    let window = ((PREVIEW_WINDOW_SECONDS * fps).round() as usize).clamp(1, available);
+    let count = (seconds / PREVIEW_WINDOW_SECONDS).ceil().max(1.0) as usize;
+    if count.saturating_mul(window) >= available {
+        return vec![(sample_offset, frame_count - 1)];
+    }
+
+    let spread = available - window;
+    (0..count)
+        .map(|i| {
+            let first = match count {
+                1 => sample_offset + spread / 2,
+                _ => sample_offset + i * spread / (count - 1),
+            };
+            (first, first + window - 1)
+        })
+        .collect()
+}
+
+/// Number of frames sampling visits in `range` at `sample_rate` from
+/// `sample_offset`, if every frame the container counts decodes.
+pub fn expected_sample_count(
+    range: &Range<usize>,
+    sample_offset: usize,
+    sample_rate: usize,
+) -> usize {
+    range
+        .end
+        .saturating_sub(sample_offset.max(range.start))
+        .div_ceil(sample_rate)
+}
+
+/// Whether a frame is the one sampled from its stride of `rate` frames,
+/// counted from the start of `range`.
+///
+/// Without a seed that's the first frame of each stride, the grid
+/// `start, start + rate, ...`. With one it's a position drawn from the seed
+/// and the stride's number; a stride cut short by the end of `range` draws
+/// within what's left, so each stride still yields exactly one frame.
+fn is_sampled(frame_index: usize, range: Range<usize>, rate: usize, jitter: Option<u64>) -> bool {
+    if !range.contains(&frame_index) {
+        return false;
+    }
+    let offset = frame_index - range.start;
+    let stride = offset / rate;
+    let Some(seed) = jitter else {
+        return offset.is_multiple_of(rate);
+    };
+    let length = rate.min(range.end - range.start - stride * rate);
+    offset % rate == stride_jitter(seed, stride, length)
+}
+
+/// A position in `0..length` for stride number `stride`, from SplitMix64.
+fn stride_jitter(seed: u64, stride: usize, length: usize) -> usize {
+    let mut z = seed.wrapping_add((stride as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z % length as u64) as usize
+}
+
+/// Check that decoded pixel data is tightly packed 8-bit RGB.
+///
+/// Padded Mats or frames with an unexpected channel count would otherwise
+/// fail much later with a generic image buffer error.
+fn validate_rgb_data(
+    path: &Path,
+    index: usize,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    channels: i32,
+) -> Result<()> {
+    let expected = width as usize * height as usize * 3;
+    if data.len() != expected {
+        return Err(Error::VideoDecode {
+            path: path.to_path_buf(),
+            reason: format!(
+                "Frame {} has {} bytes of pixel data, expected {} ({}x{}x3); decoded frame has {} channel(s)",
+                index,
+                data.len(),
+                expected,
+                width,
+                height,
+                channels
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// The "Video I/O" section of OpenCV's build information, listing the
+/// capture backends (FFMPEG, GStreamer, ...) that decide which codecs open.
+pub fn opencv_video_io() -> Result<String> {
+    let info = core::get_build_information()?;
+    Ok(video_io_section(&info).unwrap_or(&info).to_string())
+}
+
+/// Lines from the `Video I/O:` header up to the next blank line.
+fn video_io_section(build_info: &str) -> Option<&str> {
+    let start = build_info.find("Video I/O:")?;
+    let line_start = build_info[..start].rfind('\n').map_or(0, |i| i + 1);
+    let section = &build_info[line_start..];
+    let end = section.find("\n\n").unwrap_or(section.len());
+    Some(section[..end].trim_end())
+}
+
+/// Mean SSIM over the [`SSIM_BLOCK_SIZE`] blocks of two same-sized images.
+fn ssim_blocks(a: &image::GrayImage, b: &image::GrayImage) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (width, height) = a.dimensions();
+    let mut total = 0.0;
+    let mut blocks = 0;
+    for by in (0..height).step_by(SSIM_BLOCK_SIZE as usize) {
+        for bx in (0..width).step_by(SSIM_BLOCK_SIZE as usize) {
+            let pixels: Vec<(f64, f64)> = (by..(by + SSIM_BLOCK_SIZE).min(height))
+                .flat_map(|y| (bx..(bx + SSIM_BLOCK_SIZE).min(width)).map(move |x| (x, y)))
+                .map(|(x, y)| (a.get_pixel(x, y)[0] as f64, b.get_pixel(x, y)[0] as f64))
+                .collect();
+            let n = pixels.len() as f64;
+            let mean_a = pixels.iter().map(|p| p.0).sum::<f64>() / n;
+            let mean_b = pixels.iter().map(|p| p.1).sum::<f64>() / n;
+            let (mut var_a, mut var_b, mut cov) = (0.0, 0.0, 0.0);
+            for (pa, pb) in &pixels {
+                var_a += (pa - mean_a) * (pa - mean_a);
+                var_b += (pb - mean_b) * (pb - mean_b);
+                cov += (pa - mean_a) * (pb - mean_b);
+            }
+            let (var_a, var_b, cov) = (var_a / n, var_b / n, cov / n);
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            blocks += 1;
+        }
+    }
+    if blocks == 0 {
+        1.0
+    } else {
+        total / blocks as f64
+    }
+}
+
+/// Fraction of grayscale pixels that are not clipped at either end of the
+/// histogram.
+fn exposure_score(gray: &[u8]) -> f64 {
+    if gray.is_empty() {
+        return 0.0;
+    }
+
+    let clipped = gray
+        .iter()
+        .filter(|&&v| v <= EXPOSURE_BLACK_LEVEL || v >= EXPOSURE_WHITE_LEVEL)
+        .count();
+    1.0 - clipped as f64 / gray.len() as f64
+}
+
+/// Up to `count` dominant colors of packed RGB `data`, most common first.
+///
+/// Runs OpenCV's k-means over an even sample of at most
+/// [`PALETTE_SAMPLE_PIXELS`] pixels. Clusters start as equal ranks of the
+/// sample ordered by brightness, so the same image always gives the same
+/// palette. Clusters that settle on the same color are merged, so images
+/// with fewer distinct colors than `count` give fewer.
+pub fn dominant_colors(data: &[u8], count: usize) -> Result<Vec<[u8; 3]>> {
+    let stride = (data.len() / 3).div_ceil(PALETTE_SAMPLE_PIXELS).max(1);
+    let pixels: Vec<[f32; 3]> = data
+        .chunks_exact(3)
+        .step_by(stride)
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+    if pixels.is_empty() || count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let luma = |p: &[f32; 3]| 0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2];
+    let mut by_luma: Vec<usize> = (0..pixels.len()).collect();
+    by_luma.sort_by(|&a, &b| luma(&pixels[a]).total_cmp(&luma(&pixels[b])));
+    let k = count.min(pixels.len());
+    let mut initial = vec![0i32; pixels.len()];
+    for (rank, &i) in by_luma.iter().enumerate() {
+        initial[i] = (rank * k / pixels.len()) as i32;
+    }
+
+    let samples = Mat::new_rows_cols_with_data(pixels.len() as i32, 3, pixels.as_flattened())?;
+    let mut labels = Mat::new_rows_cols_with_data(pixels.len() as i32, 1, &initial)?.try_clone()?;
+    let mut centers = Mat::default();
+    let criteria = core::TermCriteria::new(
+        core::TermCriteria_COUNT + core::TermCriteria_EPS,
+        PALETTE_ITERATIONS as i32,
+        0.5,
+    )?;
+    core::kmeans(
+        &samples,
+        k as i32,
+        &mut labels,
+        criteria,
+        1,
+        core::KMEANS_USE_INITIAL_LABELS,
+        &mut centers,
+    )?;
+
+    let centers = centers.data_typed::<f32>()?;
+    let mut palette: Vec<([u8; 3], usize)> = Vec::new();
+    for &label in labels.data_typed::<i32>()? {
+        let center = &centers[label as usize * 3..][..3];
+        let color = [0, 1, 2].map(|c| center[c].round().clamp(0.0, 255.0) as u8);
+        match palette.iter_mut().find(|(existing, _)| *existing == color) {
+            Some((_, size)) => *size += 1,
+            None => palette.push((color, 1)),
+        }
+    }
+    palette.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+    Ok(palette.into_iter().map(|(color, _)| color).collect())
+}
+
 /// Convert an OpenCV Mat to a Vec<u8>.
+///
+/// The raw bytes are copied as-is; callers validate the layout so that a
+/// size mismatch surfaces as an error instead of a panic.
 fn mat_to_vec(mat: &Mat) -> Result<Vec<u8>> {
-    let total = mat.total() * mat.channels() as usize;
-    let mut data = vec![0u8; total];
+    Ok(mat.data_bytes()?.to_vec())
+}
+
+/// Reinterpret native-endian bytes from a 16-bit Mat as samples.
+fn u16_samples(bytes: Vec<u8>) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crop_region() {
+        let crop: Region = "960, 540,960,540".parse().unwrap();
+        assert_eq!(
+            crop,
+            Region {
+                x: 960,
+                y: 540,
+                width: 960,
+                height: 540
+            }
+        );
+        assert!(crop.check_fits("crop", 1920, 1080).is_ok());
+        assert!(crop.check_fits("crop", 1280, 720).is_err());
+
+        assert!("1,2,3".parse::<Region>().is_err());
+        assert!("0,0,-5,10".parse::<Region>().is_err());
+        assert!("0,0,0,10".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_video_io_section() {
+        let info = "\nGeneral configuration for OpenCV 4.8.0\n  Version control: 4.8.0\n\n  \
+                    Video I/O:\n    FFMPEG:   YES\n      avcodec:  YES (60.3.100)\n    \
+                    GStreamer:   NO\n\n  Parallel framework:   pthreads\n";
+        assert_eq!(
+            video_io_section(info),
+            Some("  Video I/O:\n    FFMPEG:   YES\n      avcodec:  YES (60.3.100)\n    GStreamer:   NO")
+        );
+        assert_eq!(video_io_section("no such section"), None);
+    }
+
+    #[test]
+    fn test_ssim() {
+        let frame = |data: Vec<u8>| Frame {
+            index: 0,
+            timestamp_seconds: Timestamp::ZERO,
+            data,
+            width: 16,
+            height: 16,
+            data16: None,
+        };
+        let gradient = frame((0..16 * 16).flat_map(|i| [i as u8; 3]).collect());
+        let flat = frame(vec![128; 16 * 16 * 3]);
+
+        assert!((gradient.ssim(&gradient).unwrap() - 1.0).abs() < 1e-9);
+        assert!(gradient.ssim(&flat).unwrap() < 0.5);
+    }
+
+    #[test]
+    fn test_region_blank() {
+        let mut data = vec![255u8; 4 * 3 * 3];
+        let region: Region = "1,1,2,5".parse().unwrap();
+        region.blank(&mut data, 4, 3);
+
+        let pixel = |x: usize, y: usize| data[(y * 4 + x) * 3];
+        assert_eq!(pixel(0, 0), 255);
+        assert_eq!(pixel(1, 0), 255);
+        assert_eq!(pixel(1, 1), 0);
+        assert_eq!(pixel(2, 2), 0);
+        assert_eq!(pixel(3, 1), 255);
+        assert_eq!(data.iter().filter(|&&v| v == 0).count(), 2 * 2 * 3);
+    }
+
+    #[test]
+    fn test_stream_ended_early() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.mp4");
+        std::fs::write(&path, b"").unwrap();
+        let mut video = VideoLoader::new(&path).unwrap().with_trim(1.0, 0.0);
+        video.metadata = Some(VideoMetadata {
+            path,
+            width: 4,
+            height: 4,
+            fps: 30.0,
+            frame_count: 300,
+            duration_seconds: 10.0,
+            codec: "avc1".to_string(),
+            decoded_frame_count: None,
+        });
+
+        let mut reported = Vec::new();
+        let mut progress = Some(|done, total| reported.push((done, total)));
+        video.stream_ended_early(300, 300, &mut progress);
+        assert_eq!(video.frame_range().unwrap(), 30..300);
+
+        // A short read finishes the progress count and limits later sampling
+        video.stream_ended_early(240, 300, &mut progress);
+        assert_eq!(reported, vec![(240, 240)]);
+        assert_eq!(video.metadata().unwrap().decoded_frame_count, Some(240));
+        assert_eq!(video.frame_range().unwrap(), 30..240);
+    }
+
+    #[test]
+    fn test_frame_from_image_file_converts_to_rgb() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let gray_path = dir.path().join("gray.png");
+        image::GrayImage::from_pixel(3, 2, image::Luma([200]))
+            .save(&gray_path)
+            .unwrap();
+        let gray = Frame::from_image_file(&gray_path, 4, Timestamp::from_seconds(0.5)).unwrap();
+        assert_eq!((gray.width, gray.height), (3, 2));
+        assert_eq!(gray.data, vec![200; 3 * 2 * 3]);
+        assert_eq!(gray.index, 4);
+
+        let rgba_path = dir.path().join("rgba.png");
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([10, 20, 30, 128]))
+            .save(&rgba_path)
+            .unwrap();
+        let rgba = Frame::from_image_file(&rgba_path, 0, Timestamp::ZERO).unwrap();
+        assert_eq!(rgba.data, [10, 20, 30].repeat(4));
+
+        assert!(
+            Frame::from_image_file(&dir.path().join("missing.webp"), 0, Timestamp::ZERO).is_err()
+        );
+    }
+
+    #[test]
+    fn test_exposure_score() {
+        assert_eq!(exposure_score(&[128, 64, 200, 32]), 1.0);
+        assert_eq!(exposure_score(&[0, 255, 128, 128]), 0.5);
+        assert_eq!(exposure_score(&[0, 0, 0, 0]), 0.0);
+        assert_eq!(exposure_score(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_dominant_colors() {
+        // Three quarters red, one quarter blue
+        let mut data = [255u8, 0, 0].repeat(12);
+        data.extend([0u8, 0, 255].repeat(4));
+        assert_eq!(
+            dominant_colors(&data, 2).unwrap(),
+            vec![[255, 0, 0], [0, 0, 255]]
+        );
+        // Asking for more colors than the image has gives only those it has
+        assert_eq!(
+            dominant_colors(&data, 4).unwrap(),
+            vec![[255, 0, 0], [0, 0, 255]]
+        );
+
+        // Near shades merge into their mean
+        let shades = [[100u8, 100, 100], [104, 104, 104], [250, 250, 250]].concat();
+        assert_eq!(
+            dominant_colors(&shades, 2).unwrap(),
+            vec![[102, 102, 102], [250, 250, 250]]
+        );
+        assert!(dominant_colors(&[], 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_sampled() {
+        let sampled = |offset| {
+            (0..12)
+                .filter(|&i| is_sampled(i, offset..12, 5, None))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sampled(0), vec![0, 5, 10]);
+        assert_eq!(sampled(3), vec![3, 8]);
+        assert_eq!(sampled(20), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_is_sampled_with_jitter() {
+        let sampled = |seed| {
+            (0..53)
+                .filter(|&i| is_sampled(i, 3..53, 10, Some(seed)))
+                .collect::<Vec<_>>()
+        };
+
+        // One frame from each stride, including the short last one
+        let frames = sampled(7);
+        assert_eq!(frames.len(), expected_sample_count(&(0..53), 3, 10));
+        for (stride, frame) in frames.iter().enumerate() {
+            assert!((3 + stride * 10..(3 + stride * 10 + 10).min(53)).contains(frame));
+        }
+
+        // Reproducible for a seed, different across seeds
+        assert_eq!(sampled(7), frames);
+        assert!((0..8).any(|seed| sampled(seed) != frames));
+    }
+
+    #[test]
+    fn test_expected_sample_count_matches_sampling() {
+        for (range, offset, rate) in [
+            (0..3000, 0, 5),
+            (0..3001, 0, 5),
+            (0..12, 3, 5),
+            (150..2700, 0, 7),
+            (150..2700, 200, 30),
+            (0..40, 50, 1),
+            (10..10, 0, 1),
+        ] {
+            let sampled = range
+                .clone()
+                .filter(|&i| is_sampled(i, offset.max(range.start)..range.end, rate, None))
+                .count();
+            assert_eq!(expected_sample_count(&range, offset, rate), sampled);
+        }
+    }
+
+    #[test]
+    fn test_clip_frame_range() {
+        // Two seconds at 30 fps around frame 300
+        assert_eq!(clip_frame_range(300, 2.0, 30.0, &(0..1000)), (270, 329));
+        // Shifted to fit at either end
+        assert_eq!(clip_frame_range(10, 2.0, 30.0, &(0..1000)), (0, 59));
+        assert_eq!(clip_frame_range(995, 2.0, 30.0, &(0..1000)), (940, 999));
+        // Never longer than the video, never shorter than a frame
+        assert_eq!(clip_frame_range(5, 10.0, 30.0, &(0..20)), (0, 19));
+        assert_eq!(clip_frame_range(5, 0.001, 30.0, &(0..20)), (5, 5));
+        // Kept within the trimmed frames
+        assert_eq!(clip_frame_range(110, 2.0, 30.0, &(100..900)), (100, 159));
+        assert_eq!(clip_frame_range(895, 2.0, 30.0, &(100..900)), (840, 899));
+    }
+
+    #[test]
+    fn test_preview_windows() {
+        // Three 1s windows over 100s at 30 fps: start, middle and end
+        assert_eq!(
+            preview_windows(3000, 30.0, 3.0, 0),
+            vec![(0, 29), (1485, 1514), (2970, 2999)]
+        );
+        // Partial seconds round up to another window
+        assert_eq!(preview_windows(3000, 30.0, 2.5, 0).len(), 3);
+        assert_eq!(preview_windows(3000, 30.0, 1.0, 100), vec![(1535, 1564)]);
+        // Asking for the whole video analyzes all of it
+        assert_eq!(preview_windows(3000, 30.0, 100.0, 100), vec![(100, 2999)]);
+        assert!(preview_windows(50, 30.0, 1.0, 50).is_empty());
+    }
 
-    // Copy data from Mat
-    let mat_data = mat.data_bytes()?;
-    data.copy_from_slice(mat_data);
+    #[test]
+    fn test_trimmed_range() {
+        assert_eq!(trimmed_range(3000, 30.0, 0.0, 0.0), 0..3000);
+        assert_eq!(trimmed_range(3000, 30.0, 5.0, 10.0), 150..2700);
+        // Trims longer than the video leave nothing
+        assert!(trimmed_range(3000, 30.0, 60.0, 60.0).is_empty());
+        assert!(trimmed_range(3000, 30.0, 200.0, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_scaled_size() {
+        assert_eq!(scaled_size(3840, 2160, 512), Some((512, 288)));
+        assert_eq!(scaled_size(1080, 1920, 512), Some((288, 512)));
+        // Frames that already fit are left alone
+        assert_eq!(scaled_size(640, 360, 640), None);
+        assert_eq!(scaled_size(4000, 1, 512), Some((512, 1)));
+    }
+
+    #[test]
+    fn test_combing_share() {
+        // Alternate rows from two fields that caught an edge at different
+        // positions: on the left of the edge the fields disagree
+        let combed = image::GrayImage::from_fn(8, 6, |x, y| {
+            let field_edge = if y % 2 == 0 { 2 } else { 6 };
+            image::Luma([if x < field_edge { 200 } else { 40 }])
+        });
+        // Interior rows 1..5, columns 2..6 alternate
+        assert_eq!(combing_share(&combed), 16.0 / 32.0);
+
+        // Horizontal stripes two rows tall aren't combs
+        let stripes = image::GrayImage::from_fn(8, 6, |_, y| {
+            image::Luma([if y / 2 % 2 == 0 { 200 } else { 40 }])
+        });
+        assert_eq!(combing_share(&stripes), 0.0);
+        assert_eq!(combing_share(&image::GrayImage::new(4, 2)), 0.0);
+    }
+
+    #[test]
+    fn test_uniform_grid_indices() {
+        assert_eq!(
+            uniform_grid_indices(300, 30.0, 2.0),
+            vec![0, 60, 120, 180, 240]
+        );
+        assert_eq!(
+            uniform_grid_indices(10, 30.0, 0.01),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(uniform_grid_indices(0, 30.0, 1.0), Vec::<usize>::new());
+        assert_eq!(uniform_grid_indices(100, 0.0, 1.0), vec![0]);
+    }
 
-    Ok(data)
+    #[test]
+    fn test_validate_rgb_data() {
+        let path = Path::new("video.mp4");
+        assert!(validate_rgb_data(path, 0, &[0u8; 12], 2, 2, 3).is_ok());
+
+        let err = validate_rgb_data(path, 7, &[0u8; 4], 2, 2, 1).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Frame 7 has 4 bytes"));
+        assert!(message.contains("expected 12"));
+        assert!(message.contains("1 channel(s)"));
+    }
 }